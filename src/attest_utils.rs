@@ -0,0 +1,103 @@
+use crate::{EpochRewards, ENDORSE_MESSAGE};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    offchain_message::OffchainMessage, pubkey::Pubkey, signature::Signature, signer::Signer,
+};
+use std::str::FromStr;
+
+/// Contents of an `attestation_<identity>_<epoch>.json` file: rewards computed
+/// by `calculate`, signed by the validator identity
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RewardsAttestation {
+    pub identity_pubkey: String,
+    pub epoch: u64,
+    pub total_block_rewards: u64,
+    pub num_leader_slots: u64,
+    pub source: String,
+    /// The signed message, see [`attestation_message`]
+    pub message: String,
+    /// Base58 signature of `message` as a Solana off-chain message, so it can
+    /// also be checked with `solana verify-offchain-signature`
+    pub signature: String,
+}
+
+/// Canonical message attesting to the rewards of `identity_pubkey` in `epoch`
+pub fn attestation_message(
+    identity_pubkey: &Pubkey,
+    epoch: u64,
+    total_block_rewards: u64,
+    num_leader_slots: u64,
+    source: &str,
+) -> String {
+    format!(
+        "{}-rewards-{}-{}-{}-{}-{}",
+        ENDORSE_MESSAGE, identity_pubkey, epoch, total_block_rewards, num_leader_slots, source
+    )
+}
+
+fn offchain_message(message: &str) -> Result<OffchainMessage, String> {
+    OffchainMessage::new(0, message.as_bytes())
+        .map_err(|e| format!("Error: Invalid attestation message: {}", e))
+}
+
+pub fn sign_attestation(
+    identity: &dyn Signer,
+    epoch: u64,
+    rewards: &EpochRewards,
+) -> Result<RewardsAttestation, String> {
+    let (Some(num_leader_slots), Some(source)) = (rewards.num_leader_slots, &rewards.source) else {
+        return Err(
+            "Error: The rewards file has no leader slot count or source, please calculate it again"
+                .to_string(),
+        );
+    };
+
+    let identity_pubkey = identity.pubkey();
+    let message = attestation_message(
+        &identity_pubkey,
+        epoch,
+        rewards.total_block_rewards,
+        num_leader_slots,
+        source,
+    );
+    let signature = offchain_message(&message)?
+        .sign(identity)
+        .map_err(|e| format!("Error: Failed to sign attestation: {}", e))?;
+
+    Ok(RewardsAttestation {
+        identity_pubkey: identity_pubkey.to_string(),
+        epoch,
+        total_block_rewards: rewards.total_block_rewards,
+        num_leader_slots,
+        source: source.clone(),
+        message,
+        signature: signature.to_string(),
+    })
+}
+
+/// Checks that `attestation`'s message matches its fields and is signed by its identity
+pub fn verify_attestation(attestation: &RewardsAttestation) -> Result<(), String> {
+    let identity_pubkey = Pubkey::from_str(&attestation.identity_pubkey)
+        .map_err(|_| "Error: Invalid identity pubkey in attestation".to_string())?;
+    let signature = Signature::from_str(&attestation.signature)
+        .map_err(|_| "Error: Invalid signature in attestation".to_string())?;
+
+    let message = attestation_message(
+        &identity_pubkey,
+        attestation.epoch,
+        attestation.total_block_rewards,
+        attestation.num_leader_slots,
+        &attestation.source,
+    );
+    if message != attestation.message {
+        return Err("Error: The attestation's message doesn't match its rewards".to_string());
+    }
+
+    match offchain_message(&message)?.verify(&identity_pubkey, &signature) {
+        Ok(true) => Ok(()),
+        _ => Err(format!(
+            "Error: The attestation is not signed by {}",
+            identity_pubkey
+        )),
+    }
+}
@@ -0,0 +1,178 @@
+use chrono::{DateTime, Utc};
+use reqwest::{RequestBuilder, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+pub const DUNE_API_BASE_URL: &str = "https://api.dune.com/api/v1";
+const DUNE_API_KEY_HEADER: &str = "X-Dune-API-Key";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ExecutionState {
+    #[serde(rename = "QUERY_STATE_PENDING")]
+    Pending,
+    #[serde(rename = "QUERY_STATE_EXECUTING")]
+    Executing,
+    #[serde(rename = "QUERY_STATE_COMPLETED")]
+    Completed,
+    #[serde(rename = "QUERY_STATE_COMPLETED_PARTIAL")]
+    CompletedPartial,
+    #[serde(rename = "QUERY_STATE_FAILED")]
+    Failed,
+    #[serde(rename = "QUERY_STATE_CANCELLED")]
+    Cancelled,
+    #[serde(rename = "QUERY_STATE_EXPIRED")]
+    Expired,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExecuteQueryResponse {
+    pub execution_id: String,
+    pub state: ExecutionState,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExecutionStatusResponse {
+    pub execution_id: String,
+    pub state: ExecutionState,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResultMetadata {
+    #[serde(default)]
+    pub column_names: Vec<String>,
+    #[serde(default)]
+    pub total_row_count: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExecutionResult<T> {
+    pub rows: Vec<T>,
+    pub metadata: ResultMetadata,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExecutionResultsResponse<T> {
+    pub execution_id: String,
+    pub state: ExecutionState,
+    #[serde(default)]
+    pub execution_ended_at: Option<String>,
+    /// Missing until the execution completes
+    #[serde(default)]
+    pub result: Option<ExecutionResult<T>>,
+}
+
+impl<T> ExecutionResultsResponse<T> {
+    pub fn execution_ended_at(&self) -> Option<DateTime<Utc>> {
+        self.execution_ended_at
+            .as_deref()
+            .and_then(|ended_at| DateTime::parse_from_rfc3339(ended_at).ok())
+            .map(|ended_at| ended_at.with_timezone(&Utc))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// Minimal client for the Dune Analytics API
+#[derive(Debug, Clone)]
+pub struct DuneClient {
+    http: reqwest::Client,
+    api_key: String,
+    base_url: String,
+}
+
+impl DuneClient {
+    pub fn new(api_key: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            api_key: api_key.to_string(),
+            base_url: DUNE_API_BASE_URL.to_string(),
+        }
+    }
+
+    /// Use another Dune API endpoint, e.g. a proxy or a local mock server
+    pub fn with_base_url(self, base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            ..self
+        }
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, String> {
+        let response = request
+            .header(DUNE_API_KEY_HEADER, &self.api_key)
+            .send()
+            .await
+            .map_err(|e| format!("Error: Failed to reach the Dune API: {}", e))?;
+
+        let status = response.status();
+        if status != StatusCode::OK {
+            let error = response
+                .json::<ErrorResponse>()
+                .await
+                .map(|response| response.error)
+                .unwrap_or_else(|_| "unknown error".to_string());
+            return Err(format!("Error: Dune API returned {}: {}", status, error));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| format!("Error: Failed to parse Dune API response: {}", e))
+    }
+
+    pub async fn execute_query(
+        &self,
+        query_id: u32,
+        params: &BTreeMap<String, String>,
+    ) -> Result<ExecuteQueryResponse, String> {
+        self.send(
+            self.http
+                .post(format!("{}/query/{}/execute", self.base_url, query_id))
+                .json(&json!({ "query_parameters": params })),
+        )
+        .await
+    }
+
+    pub async fn get_status(&self, execution_id: &str) -> Result<ExecutionStatusResponse, String> {
+        self.send(self.http.get(format!(
+            "{}/execution/{}/status",
+            self.base_url, execution_id
+        )))
+        .await
+    }
+
+    pub async fn get_results<T: DeserializeOwned>(
+        &self,
+        execution_id: &str,
+    ) -> Result<ExecutionResultsResponse<T>, String> {
+        self.send(self.http.get(format!(
+            "{}/execution/{}/results",
+            self.base_url, execution_id
+        )))
+        .await
+    }
+
+    /// Results of the latest execution of `query_id` with `params`, without
+    /// spending credits on a new execution
+    pub async fn get_latest_results<T: DeserializeOwned>(
+        &self,
+        query_id: u32,
+        params: &BTreeMap<String, String>,
+    ) -> Result<ExecutionResultsResponse<T>, String> {
+        let params: Vec<_> = params
+            .iter()
+            .map(|(key, value)| (format!("params.{}", key), value))
+            .collect();
+
+        self.send(
+            self.http
+                .get(format!("{}/query/{}/results", self.base_url, query_id))
+                .query(&params),
+        )
+        .await
+    }
+}
@@ -0,0 +1,189 @@
+use crate::{get_successful_transaction, ENDORSE_MESSAGE};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config,
+};
+use solana_sdk::{
+    account::Account,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::VersionedTransaction,
+    vote,
+};
+use std::str::FromStr;
+
+/// Most signatures `getSignaturesForAddress` returns per request
+pub const MAX_SIGNATURES_PER_PAGE: usize = 1_000;
+
+/// `node_pubkey` is the first field of every vote state version, right after the
+/// `VoteStateVersions` tag
+const VOTE_STATE_NODE_PUBKEY_OFFSET: usize = 4;
+
+/// Checks that `vote_account` is a vote account whose node is `identity_pubkey`
+pub fn check_vote_account_identity(
+    vote_pubkey: &Pubkey,
+    vote_account: &Account,
+    identity_pubkey: &Pubkey,
+) -> Result<(), String> {
+    if vote_account.owner != vote::program::ID {
+        return Err(format!("Error: {} is not a vote account", vote_pubkey));
+    }
+
+    let node_pubkey = vote_account
+        .data
+        .get(VOTE_STATE_NODE_PUBKEY_OFFSET..VOTE_STATE_NODE_PUBKEY_OFFSET + 32)
+        .and_then(|bytes| Pubkey::try_from(bytes).ok())
+        .ok_or_else(|| format!("Error: Failed to read vote account {}", vote_pubkey))?;
+
+    if node_pubkey != *identity_pubkey {
+        return Err(format!(
+            "Error: Vote account {} belongs to identity {}, not {}",
+            vote_pubkey, node_pubkey, identity_pubkey
+        ));
+    }
+
+    Ok(())
+}
+
+/// Fetches `vote_pubkey` and checks it with [`check_vote_account_identity`]
+pub async fn validate_vote_account(
+    rpc: &RpcClient,
+    vote_pubkey: &Pubkey,
+    identity_pubkey: &Pubkey,
+) -> Result<(), String> {
+    let vote_account = rpc
+        .get_account(vote_pubkey)
+        .await
+        .map_err(|e| format!("Error: Failed to fetch vote account: {}", e))?;
+
+    check_vote_account_identity(vote_pubkey, &vote_account, identity_pubkey)
+}
+
+/// Memo of a transaction by which a validator identity endorses distributing
+/// its rewards to `stake_pool_pubkey`
+pub fn endorsement_memo(stake_pool_pubkey: &Pubkey, vote_pubkey: &Pubkey) -> String {
+    format!("{}-{}-{}", ENDORSE_MESSAGE, stake_pool_pubkey, vote_pubkey)
+}
+
+/// Memo instruction signed by the identity. The v1 memo program is used since it
+/// accepts non signer accounts, which makes the stake pool and vote account list
+/// the endorsement in `getSignaturesForAddress`.
+pub fn endorse_ix(
+    identity_pubkey: &Pubkey,
+    stake_pool_pubkey: &Pubkey,
+    vote_pubkey: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: spl_memo::v1::id(),
+        accounts: vec![
+            AccountMeta::new_readonly(*identity_pubkey, true),
+            AccountMeta::new_readonly(*stake_pool_pubkey, false),
+            AccountMeta::new_readonly(*vote_pubkey, false),
+        ],
+        data: endorsement_memo(stake_pool_pubkey, vote_pubkey).into_bytes(),
+    }
+}
+
+/// Checks that `tx` is signed by `identity_pubkey` and has the endorsement memo
+pub fn check_endorsement_tx(
+    tx: &VersionedTransaction,
+    identity_pubkey: &Pubkey,
+    stake_pool_pubkey: &Pubkey,
+    vote_pubkey: &Pubkey,
+) -> Result<(), String> {
+    let account_keys = tx.message.static_account_keys();
+    let num_signers = usize::from(tx.message.header().num_required_signatures);
+
+    if !account_keys
+        .iter()
+        .take(num_signers)
+        .any(|key| key == identity_pubkey)
+    {
+        return Err(format!(
+            "Error: The transaction is not signed by {}",
+            identity_pubkey
+        ));
+    }
+
+    let memo = endorsement_memo(stake_pool_pubkey, vote_pubkey);
+    let has_memo = tx.message.instructions().iter().any(|ix| {
+        let program_id = ix.program_id(account_keys);
+        (*program_id == spl_memo::v1::id() || *program_id == spl_memo::id())
+            && ix.data == memo.as_bytes()
+    });
+
+    if !has_memo {
+        return Err(format!("Error: The transaction has no `{}` memo", memo));
+    }
+
+    Ok(())
+}
+
+/// Checks the endorsement transaction `signature`
+pub async fn check_endorsement_signature(
+    rpc: &RpcClient,
+    signature: &Signature,
+    identity_pubkey: &Pubkey,
+    stake_pool_pubkey: &Pubkey,
+    vote_pubkey: &Pubkey,
+) -> Result<(), String> {
+    match get_successful_transaction(rpc, signature).await? {
+        Some(tx) => check_endorsement_tx(&tx, identity_pubkey, stake_pool_pubkey, vote_pubkey),
+        None => Err(format!("Error: Transaction {} failed", signature)),
+    }
+}
+
+/// Most recent successful endorsement among the last `max_signatures`
+/// transactions of the stake pool
+pub async fn find_endorsement(
+    rpc: &RpcClient,
+    identity_pubkey: &Pubkey,
+    stake_pool_pubkey: &Pubkey,
+    vote_pubkey: &Pubkey,
+    max_signatures: usize,
+) -> Result<Option<Signature>, String> {
+    // The memo is in the signature list, which spares fetching every transaction
+    let memo = endorsement_memo(stake_pool_pubkey, vote_pubkey);
+    let mut before = None;
+    let mut num_searched = 0;
+
+    while num_searched < max_signatures {
+        let page = rpc
+            .get_signatures_for_address_with_config(
+                stake_pool_pubkey,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until: None,
+                    limit: Some(MAX_SIGNATURES_PER_PAGE.min(max_signatures - num_searched)),
+                    commitment: Some(rpc.commitment()),
+                },
+            )
+            .await
+            .map_err(|e| format!("Error: Failed to fetch stake pool transactions: {}", e))?;
+
+        let Some(last) = page.last() else {
+            break;
+        };
+        before = Some(
+            Signature::from_str(&last.signature)
+                .map_err(|_| "Error: Invalid signature returned by RPC".to_string())?,
+        );
+        num_searched += page.len();
+
+        for status in page.iter().filter(|status| {
+            status.err.is_none() && status.memo.as_deref().is_some_and(|m| m.contains(&memo))
+        }) {
+            let signature = Signature::from_str(&status.signature)
+                .map_err(|_| "Error: Invalid signature returned by RPC".to_string())?;
+            if let Some(tx) = get_successful_transaction(rpc, &signature).await? {
+                if check_endorsement_tx(&tx, identity_pubkey, stake_pool_pubkey, vote_pubkey)
+                    .is_ok()
+                {
+                    return Ok(Some(signature));
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,165 @@
+use borsh::BorshDeserialize;
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{pubkey, pubkey::Pubkey};
+
+use crate::read_json_file;
+
+pub const JITO_TIP_DISTRIBUTION_PROGRAM_ID: Pubkey =
+    pubkey!("4R3gSG8BpU4t19KYj8CfnbtRpnT8gtk4dvTHxVRwc2r7");
+const TIP_DISTRIBUTION_ACCOUNT_SEED: &[u8] = b"TIP_DISTRIBUTION_ACCOUNT";
+/// `sha256("account:TipDistributionAccount")[..8]`
+const TIP_DISTRIBUTION_ACCOUNT_DISCRIMINATOR: [u8; 8] = [85, 64, 113, 198, 234, 94, 120, 123];
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshDeserialize)]
+pub struct MerkleRoot {
+    pub root: [u8; 32],
+    pub max_total_claim: u64,
+    pub max_num_nodes: u64,
+    pub total_funds_claimed: u64,
+    pub num_nodes_claimed: u64,
+}
+
+/// Jito tip distribution program's per validator, per epoch account holding MEV tips
+#[derive(Debug, Clone, PartialEq, Eq, BorshDeserialize)]
+pub struct TipDistributionAccount {
+    pub validator_vote_account: [u8; 32],
+    pub merkle_root_upload_authority: [u8; 32],
+    pub merkle_root: Option<MerkleRoot>,
+    pub epoch_created_at: u64,
+    pub validator_commission_bps: u16,
+    pub expires_at: u64,
+    pub bump: u8,
+}
+
+impl TipDistributionAccount {
+    pub fn deserialize_account_data(data: &[u8]) -> Result<Self, String> {
+        let mut data = data
+            .strip_prefix(&TIP_DISTRIBUTION_ACCOUNT_DISCRIMINATOR)
+            .ok_or_else(|| "Error: Not a tip distribution account".to_string())?;
+        let account = Self::deserialize(&mut data).map_err(|e| {
+            format!(
+                "Error: Failed to deserialize tip distribution account: {}",
+                e
+            )
+        })?;
+
+        // Accounts are allocated for a `Some` merkle root, so only zero padding may follow
+        if data.iter().any(|byte| *byte != 0) {
+            return Err("Error: Unexpected trailing data in tip distribution account".to_string());
+        }
+
+        Ok(account)
+    }
+
+    /// The validator's share of the epoch's MEV tips, i.e. its commission on the
+    /// total tips. Total tips are the `max_total_claim` once the merkle root is
+    /// uploaded, or the account's balance above rent exemption before that.
+    pub fn validator_mev_rewards(&self, lamports: u64, rent_exempt_minimum: u64) -> Option<u64> {
+        let total_tips = match &self.merkle_root {
+            Some(merkle_root) => merkle_root.max_total_claim,
+            None => lamports.saturating_sub(rent_exempt_minimum),
+        };
+        u64::try_from(u128::from(total_tips) * u128::from(self.validator_commission_bps) / 10_000)
+            .ok()
+    }
+}
+
+pub fn find_tip_distribution_account(vote_pubkey: &Pubkey, epoch: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            TIP_DISTRIBUTION_ACCOUNT_SEED,
+            vote_pubkey.as_ref(),
+            &epoch.to_le_bytes(),
+        ],
+        &JITO_TIP_DISTRIBUTION_PROGRAM_ID,
+    )
+}
+
+pub async fn get_vote_account_for_identity(
+    rpc: &RpcClient,
+    identity_pubkey: &Pubkey,
+) -> Result<Pubkey, String> {
+    let vote_accounts = rpc
+        .get_vote_accounts()
+        .await
+        .map_err(|e| format!("Error: Failed to fetch vote accounts: {}", e))?;
+
+    let identity_pubkey = identity_pubkey.to_string();
+    vote_accounts
+        .current
+        .iter()
+        .chain(vote_accounts.delinquent.iter())
+        .find(|vote_account| vote_account.node_pubkey == identity_pubkey)
+        .ok_or_else(|| {
+            format!(
+                "Error: No vote account found for identity {}",
+                identity_pubkey
+            )
+        })
+        .and_then(|vote_account| {
+            vote_account
+                .vote_pubkey
+                .parse()
+                .map_err(|_| "Error: Invalid vote pubkey returned by RPC".to_string())
+        })
+}
+
+/// Reads the validator's MEV rewards for `epoch` from its Jito tip distribution account
+pub async fn get_mev_rewards_for_epoch(
+    rpc: &RpcClient,
+    vote_pubkey: &Pubkey,
+    epoch: u64,
+) -> Result<u64, String> {
+    let (tip_distribution_account, _bump) = find_tip_distribution_account(vote_pubkey, epoch);
+
+    let account = rpc
+        .get_account(&tip_distribution_account)
+        .await
+        .map_err(|e| {
+            format!(
+                "Error: Failed to fetch tip distribution account {} for epoch {}: {}",
+                tip_distribution_account, epoch, e
+            )
+        })?;
+
+    if account.owner != JITO_TIP_DISTRIBUTION_PROGRAM_ID {
+        return Err(format!(
+            "Error: Tip distribution account {} is not owned by the Jito tip distribution program",
+            tip_distribution_account
+        ));
+    }
+
+    let rent_exempt_minimum = rpc
+        .get_minimum_balance_for_rent_exemption(account.data.len())
+        .await
+        .map_err(|e| format!("Error: Failed to fetch rent exempt minimum: {}", e))?;
+
+    TipDistributionAccount::deserialize_account_data(&account.data)?
+        .validator_mev_rewards(account.lamports, rent_exempt_minimum)
+        .ok_or_else(|| "Error: Overflow in calculating MEV rewards".to_string())
+}
+
+/// Contents of a `--mev-file`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MevRewardsFile {
+    pub epoch: u64,
+    /// The validator's share of MEV tips in lamports
+    pub mev_rewards: u64,
+}
+
+pub fn read_mev_rewards_file(path: &str, epoch: u64) -> Result<u64, String> {
+    let MevRewardsFile {
+        epoch: file_epoch,
+        mev_rewards,
+    } = read_json_file(path)?;
+
+    if file_epoch != epoch {
+        return Err(format!(
+            "Error: MEV rewards file is for epoch {}, not {}",
+            file_epoch, epoch
+        ));
+    }
+
+    Ok(mev_rewards)
+}
@@ -0,0 +1,578 @@
+use crate::{
+    decode_tx, get_ledger_file_path, parse_distribution_tx, read_json_file, DistributionPlan,
+    EpochRewards, FeeBreakdown, SignedTransferFile, SlotReward,
+};
+use chrono::Utc;
+use colored::Colorize;
+use rusqlite::{params, types::Type, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::{fmt, path::Path, str::FromStr};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS calculations (
+    id INTEGER PRIMARY KEY,
+    identity_pubkey TEXT NOT NULL,
+    epoch INTEGER NOT NULL,
+    source TEXT,
+    total_block_rewards INTEGER NOT NULL,
+    num_leader_slots INTEGER,
+    mev_rewards INTEGER,
+    fee_breakdown TEXT,
+    recorded_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS calculations_identity_epoch ON calculations (identity_pubkey, epoch);
+
+CREATE TABLE IF NOT EXISTS slot_rewards (
+    calculation_id INTEGER NOT NULL REFERENCES calculations (id),
+    slot INTEGER NOT NULL,
+    lamports INTEGER NOT NULL,
+    skipped INTEGER NOT NULL,
+    fee_breakdown TEXT,
+    PRIMARY KEY (calculation_id, slot)
+);
+
+CREATE TABLE IF NOT EXISTS transfers (
+    id INTEGER PRIMARY KEY,
+    identity_pubkey TEXT NOT NULL,
+    epoch INTEGER NOT NULL,
+    stake_pool_pubkey TEXT NOT NULL,
+    payer_pubkey TEXT NOT NULL,
+    transfer_lamports INTEGER NOT NULL,
+    plan TEXT,
+    signature TEXT,
+    status TEXT NOT NULL,
+    recorded_at TEXT NOT NULL,
+    UNIQUE (identity_pubkey, epoch, stake_pool_pubkey)
+);
+CREATE INDEX IF NOT EXISTS transfers_stake_pool ON transfers (stake_pool_pubkey);
+";
+
+/// How far a recorded distribution got, ordered by progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum TransferStatus {
+    /// Written to a plan file by `plan`
+    Planned,
+    /// Signed and written to a file by `transfer --sign-only`
+    Signed,
+    /// Sent and confirmed
+    Sent,
+}
+
+impl TransferStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Planned => "planned",
+            Self::Signed => "signed",
+            Self::Sent => "sent",
+        }
+    }
+}
+
+impl fmt::Display for TransferStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TransferStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "planned" => Ok(Self::Planned),
+            "signed" => Ok(Self::Signed),
+            "sent" => Ok(Self::Sent),
+            _ => Err(format!("Error: Unknown transfer status `{}`", s)),
+        }
+    }
+}
+
+/// A calculation of an epoch's rewards
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalculationRecord {
+    pub id: i64,
+    pub identity_pubkey: String,
+    pub epoch: u64,
+    pub rewards: EpochRewards,
+    /// RFC 3339 timestamp
+    pub recorded_at: String,
+}
+
+/// A distribution of an epoch's rewards to a stake pool
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferRecord {
+    pub identity_pubkey: String,
+    pub epoch: u64,
+    pub stake_pool_pubkey: String,
+    pub payer_pubkey: String,
+    pub transfer_lamports: u64,
+    /// The reviewed plan, for distributions made with `plan` and `execute`
+    pub plan: Option<DistributionPlan>,
+    pub signature: Option<String>,
+    pub status: TransferStatus,
+}
+
+impl TransferRecord {
+    pub fn from_plan(plan: &DistributionPlan, signature: Option<String>) -> Self {
+        Self {
+            identity_pubkey: plan.identity_pubkey.clone(),
+            epoch: plan.epoch,
+            stake_pool_pubkey: plan.stake_pool_pubkey.clone(),
+            payer_pubkey: plan.payer_pubkey.clone(),
+            transfer_lamports: plan.split.transfer_lamports,
+            plan: Some(plan.clone()),
+            status: match signature {
+                Some(_) => TransferStatus::Sent,
+                None => TransferStatus::Planned,
+            },
+            signature,
+        }
+    }
+
+    /// The amount is read from the signed tx rather than its summary, since that is
+    /// what gets sent
+    pub fn from_signed_transfer(
+        signed_transfer: &SignedTransferFile,
+        status: TransferStatus,
+    ) -> Result<Self, String> {
+        let tx = decode_tx(&signed_transfer.transaction)?;
+        Ok(Self {
+            identity_pubkey: signed_transfer.identity_pubkey.clone(),
+            epoch: signed_transfer.summary.epoch,
+            stake_pool_pubkey: signed_transfer.stake_pool_pubkey.clone(),
+            payer_pubkey: signed_transfer.payer_pubkey.clone(),
+            transfer_lamports: parse_distribution_tx(&tx)?.lamports,
+            plan: None,
+            signature: tx.signatures.first().map(|sig| sig.to_string()),
+            status,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferEntry {
+    pub id: i64,
+    pub transfer: TransferRecord,
+    /// RFC 3339 timestamp
+    pub recorded_at: String,
+}
+
+/// Filters of ledger queries. Calculations are filtered by stake pool through the
+/// transfers recorded for the same identity and epoch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LedgerQuery {
+    pub identity_pubkey: Option<Pubkey>,
+    pub epoch: Option<u64>,
+    pub stake_pool_pubkey: Option<Pubkey>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub calculations: usize,
+    pub transfers: usize,
+    /// Files that were already recorded
+    pub skipped: usize,
+    /// Files that couldn't be read, with the reason
+    pub failed: Vec<String>,
+}
+
+/// SQLite database of every calculation and distribution, at `~/.local/sanctum/ledger.sqlite`
+pub struct Ledger {
+    conn: Connection,
+}
+
+fn sql_err(e: rusqlite::Error) -> String {
+    format!("Error: Ledger query failed: {}", e)
+}
+
+fn to_json<T: Serialize>(value: &Option<T>) -> Result<Option<String>, String> {
+    value
+        .as_ref()
+        .map(|value| serde_json::to_string(value).map_err(|e| e.to_string()))
+        .transpose()
+}
+
+fn from_json<T: serde::de::DeserializeOwned>(json: Option<String>) -> rusqlite::Result<Option<T>> {
+    json.map(|json| {
+        serde_json::from_str(&json)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(e)))
+    })
+    .transpose()
+}
+
+/// Id and status of the distribution recorded for `identity_pubkey`, `epoch` and
+/// `stake_pool_pubkey`, if any
+fn transfer_status(
+    conn: &Connection,
+    identity_pubkey: &str,
+    epoch: u64,
+    stake_pool_pubkey: &str,
+) -> Result<Option<(i64, TransferStatus)>, String> {
+    let recorded = conn
+        .query_row(
+            "SELECT id, status FROM transfers
+             WHERE identity_pubkey = ?1 AND epoch = ?2 AND stake_pool_pubkey = ?3",
+            params![identity_pubkey, epoch, stake_pool_pubkey],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+        )
+        .optional()
+        .map_err(sql_err)?;
+    recorded
+        .map(|(id, status)| Ok((id, status.parse::<TransferStatus>()?)))
+        .transpose()
+}
+
+/// `<prefix><identity>_<epoch>.json`
+fn parse_file_name<'a>(file_name: &'a str, prefix: &str) -> Option<(&'a str, u64)> {
+    let (identity_pubkey, epoch) = file_name
+        .strip_prefix(prefix)?
+        .strip_suffix(".json")?
+        .rsplit_once('_')?;
+    Some((identity_pubkey, epoch.parse().ok()?))
+}
+
+impl Ledger {
+    pub fn open(path: &str) -> Result<Self, String> {
+        if let Some(parent) = Path::new(path).parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory - {}", e))?;
+        }
+        let conn = Connection::open(path)
+            .map_err(|e| format!("Error: Failed to open ledger {}: {}", path, e))?;
+        Self::init(conn)
+    }
+
+    pub fn open_in_memory() -> Result<Self, String> {
+        Self::init(Connection::open_in_memory().map_err(sql_err)?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, String> {
+        conn.execute_batch(SCHEMA).map_err(sql_err)?;
+        Ok(Self { conn })
+    }
+
+    pub fn record_calculation(
+        &mut self,
+        identity_pubkey: &Pubkey,
+        epoch: u64,
+        rewards: &EpochRewards,
+    ) -> Result<i64, String> {
+        let tx = self.conn.transaction().map_err(sql_err)?;
+        tx.execute(
+            "INSERT INTO calculations (identity_pubkey, epoch, source, total_block_rewards, num_leader_slots, mev_rewards, fee_breakdown, recorded_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                identity_pubkey.to_string(),
+                epoch,
+                rewards.source,
+                rewards.total_block_rewards,
+                rewards.num_leader_slots,
+                rewards.mev_rewards,
+                to_json(&rewards.fee_breakdown)?,
+                Utc::now().to_rfc3339(),
+            ],
+        )
+        .map_err(sql_err)?;
+        let id = tx.last_insert_rowid();
+
+        for slot_reward in rewards.slot_rewards.iter().flatten() {
+            tx.execute(
+                "INSERT INTO slot_rewards (calculation_id, slot, lamports, skipped, fee_breakdown)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    id,
+                    slot_reward.slot,
+                    slot_reward.lamports,
+                    slot_reward.skipped,
+                    to_json(&slot_reward.fee_breakdown)?,
+                ],
+            )
+            .map_err(sql_err)?;
+        }
+
+        tx.commit().map_err(sql_err)?;
+        Ok(id)
+    }
+
+    /// Records a distribution, or updates the one already recorded for the same
+    /// identity, epoch and stake pool as it goes from planned to signed to sent.
+    /// A record of an earlier status than the recorded one is ignored.
+    pub fn record_transfer(&mut self, transfer: &TransferRecord) -> Result<i64, String> {
+        let tx = self.conn.transaction().map_err(sql_err)?;
+        let recorded = transfer_status(
+            &tx,
+            &transfer.identity_pubkey,
+            transfer.epoch,
+            &transfer.stake_pool_pubkey,
+        )?;
+
+        let id = match recorded {
+            Some((id, status)) if status > transfer.status => id,
+            Some((id, _)) => {
+                tx.execute(
+                    "UPDATE transfers
+                     SET payer_pubkey = ?1, transfer_lamports = ?2, plan = COALESCE(?3, plan),
+                         signature = COALESCE(?4, signature), status = ?5, recorded_at = ?6
+                     WHERE id = ?7",
+                    params![
+                        transfer.payer_pubkey,
+                        transfer.transfer_lamports,
+                        to_json(&transfer.plan)?,
+                        transfer.signature,
+                        transfer.status.as_str(),
+                        Utc::now().to_rfc3339(),
+                        id,
+                    ],
+                )
+                .map_err(sql_err)?;
+                id
+            }
+            None => {
+                tx.execute(
+                    "INSERT INTO transfers (identity_pubkey, epoch, stake_pool_pubkey, payer_pubkey, transfer_lamports, plan, signature, status, recorded_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    params![
+                        transfer.identity_pubkey,
+                        transfer.epoch,
+                        transfer.stake_pool_pubkey,
+                        transfer.payer_pubkey,
+                        transfer.transfer_lamports,
+                        to_json(&transfer.plan)?,
+                        transfer.signature,
+                        transfer.status.as_str(),
+                        Utc::now().to_rfc3339(),
+                    ],
+                )
+                .map_err(sql_err)?;
+                tx.last_insert_rowid()
+            }
+        };
+
+        tx.commit().map_err(sql_err)?;
+        Ok(id)
+    }
+
+    /// Oldest first
+    pub fn calculations(&self, query: &LedgerQuery) -> Result<Vec<CalculationRecord>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, identity_pubkey, epoch, source, total_block_rewards, num_leader_slots, mev_rewards, fee_breakdown, recorded_at
+                 FROM calculations c
+                 WHERE (?1 IS NULL OR identity_pubkey = ?1)
+                   AND (?2 IS NULL OR epoch = ?2)
+                   AND (?3 IS NULL OR EXISTS (
+                       SELECT 1 FROM transfers t
+                       WHERE t.identity_pubkey = c.identity_pubkey AND t.epoch = c.epoch AND t.stake_pool_pubkey = ?3
+                   ))
+                 ORDER BY epoch, id",
+            )
+            .map_err(sql_err)?;
+
+        let mut calculations = stmt
+            .query_map(
+                params![
+                    query.identity_pubkey.map(|pk| pk.to_string()),
+                    query.epoch,
+                    query.stake_pool_pubkey.map(|pk| pk.to_string()),
+                ],
+                |row| {
+                    Ok(CalculationRecord {
+                        id: row.get(0)?,
+                        identity_pubkey: row.get(1)?,
+                        epoch: row.get(2)?,
+                        rewards: EpochRewards {
+                            source: row.get(3)?,
+                            total_block_rewards: row.get(4)?,
+                            num_leader_slots: row.get(5)?,
+                            mev_rewards: row.get(6)?,
+                            fee_breakdown: from_json(row.get(7)?)?,
+                            slot_rewards: None,
+                        },
+                        recorded_at: row.get(8)?,
+                    })
+                },
+            )
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(sql_err)?;
+
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT slot, lamports, skipped, fee_breakdown FROM slot_rewards
+                 WHERE calculation_id = ?1 ORDER BY slot",
+            )
+            .map_err(sql_err)?;
+        for calculation in calculations.iter_mut() {
+            let slot_rewards = stmt
+                .query_map([calculation.id], |row| {
+                    Ok(SlotReward {
+                        slot: row.get(0)?,
+                        lamports: row.get(1)?,
+                        skipped: row.get(2)?,
+                        fee_breakdown: from_json::<FeeBreakdown>(row.get(3)?)?,
+                    })
+                })
+                .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+                .map_err(sql_err)?;
+            if !slot_rewards.is_empty() {
+                calculation.rewards.slot_rewards = Some(slot_rewards);
+            }
+        }
+
+        Ok(calculations)
+    }
+
+    /// Oldest first
+    pub fn transfers(&self, query: &LedgerQuery) -> Result<Vec<TransferEntry>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, identity_pubkey, epoch, stake_pool_pubkey, payer_pubkey, transfer_lamports, plan, signature, status, recorded_at
+                 FROM transfers
+                 WHERE (?1 IS NULL OR identity_pubkey = ?1)
+                   AND (?2 IS NULL OR epoch = ?2)
+                   AND (?3 IS NULL OR stake_pool_pubkey = ?3)
+                 ORDER BY epoch, id",
+            )
+            .map_err(sql_err)?;
+
+        let transfers = stmt
+            .query_map(
+                params![
+                    query.identity_pubkey.map(|pk| pk.to_string()),
+                    query.epoch,
+                    query.stake_pool_pubkey.map(|pk| pk.to_string()),
+                ],
+                |row| {
+                    Ok(TransferEntry {
+                        id: row.get(0)?,
+                        transfer: TransferRecord {
+                            identity_pubkey: row.get(1)?,
+                            epoch: row.get(2)?,
+                            stake_pool_pubkey: row.get(3)?,
+                            payer_pubkey: row.get(4)?,
+                            transfer_lamports: row.get(5)?,
+                            plan: from_json(row.get(6)?)?,
+                            signature: row.get(7)?,
+                            status: row.get::<_, String>(8)?.parse().map_err(|e: String| {
+                                rusqlite::Error::FromSqlConversionFailure(8, Type::Text, e.into())
+                            })?,
+                        },
+                        recorded_at: row.get(9)?,
+                    })
+                },
+            )
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(sql_err)?;
+        Ok(transfers)
+    }
+
+    fn has_calculation(
+        &self,
+        identity_pubkey: &str,
+        epoch: u64,
+        rewards: &EpochRewards,
+    ) -> Result<bool, String> {
+        self.conn
+            .query_row(
+                "SELECT 1 FROM calculations
+                 WHERE identity_pubkey = ?1 AND epoch = ?2 AND total_block_rewards = ?3
+                   AND source IS ?4 AND mev_rewards IS ?5",
+                params![
+                    identity_pubkey,
+                    epoch,
+                    rewards.total_block_rewards,
+                    rewards.source,
+                    rewards.mev_rewards,
+                ],
+                |_| Ok(()),
+            )
+            .optional()
+            .map(|row| row.is_some())
+            .map_err(sql_err)
+    }
+
+    /// Whether the distribution is already recorded with at least the transfer's status
+    fn has_transfer(&self, transfer: &TransferRecord) -> Result<bool, String> {
+        let recorded = transfer_status(
+            &self.conn,
+            &transfer.identity_pubkey,
+            transfer.epoch,
+            &transfer.stake_pool_pubkey,
+        )?;
+        Ok(recorded.is_some_and(|(_, status)| status >= transfer.status))
+    }
+
+    /// Records the `rewards_*.json`, `plan_*.json` and `signed_transfer_*.json`
+    /// files of `dir` that aren't already in the ledger
+    pub fn import_dir(&mut self, dir: &str) -> Result<ImportSummary, String> {
+        let mut file_names = std::fs::read_dir(dir)
+            .map_err(|e| format!("Error: Failed to read {}: {}", dir, e))?
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .collect::<Vec<_>>();
+        file_names.sort();
+
+        let mut summary = ImportSummary::default();
+        for file_name in file_names {
+            let path = Path::new(dir).join(&file_name);
+            let Some(path) = path.to_str() else {
+                continue;
+            };
+            match self.import_file(&file_name, path) {
+                Ok(Some(true)) if file_name.starts_with("rewards_") => summary.calculations += 1,
+                Ok(Some(true)) => summary.transfers += 1,
+                Ok(Some(false)) => summary.skipped += 1,
+                Ok(None) => (),
+                Err(err) => summary.failed.push(format!("{}: {}", path, err)),
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// `None` if the file isn't a ledger file, otherwise whether it was recorded
+    fn import_file(&mut self, file_name: &str, path: &str) -> Result<Option<bool>, String> {
+        if let Some((identity_pubkey, epoch)) = parse_file_name(file_name, "rewards_") {
+            let identity_pubkey = Pubkey::from_str(identity_pubkey)
+                .map_err(|_| "Invalid identity pubkey in file name".to_string())?;
+            let rewards: EpochRewards = read_json_file(path)?;
+            if self.has_calculation(&identity_pubkey.to_string(), epoch, &rewards)? {
+                return Ok(Some(false));
+            }
+            self.record_calculation(&identity_pubkey, epoch, &rewards)?;
+            return Ok(Some(true));
+        }
+
+        let transfer = if parse_file_name(file_name, "plan_").is_some() {
+            let plan: DistributionPlan = read_json_file(path)?;
+            TransferRecord::from_plan(&plan, None)
+        } else if parse_file_name(file_name, "signed_transfer_").is_some() {
+            let signed_transfer: SignedTransferFile = read_json_file(path)?;
+            TransferRecord::from_signed_transfer(&signed_transfer, TransferStatus::Signed)?
+        } else {
+            return Ok(None);
+        };
+
+        if self.has_transfer(&transfer)? {
+            return Ok(Some(false));
+        }
+        self.record_transfer(&transfer)?;
+        Ok(Some(true))
+    }
+}
+
+/// Records in the default ledger, only warning on failure since the ledger is a
+/// secondary record of the rewards and plan files
+pub fn record_in_ledger<T>(record: impl FnOnce(&mut Ledger) -> Result<T, String>) {
+    if let Err(err) = get_ledger_file_path()
+        .and_then(|path| Ledger::open(&path))
+        .and_then(|mut ledger| record(&mut ledger))
+    {
+        println!(
+            "{}",
+            format!("⚠ Failed to record in the ledger ({})", err).yellow()
+        );
+    }
+}
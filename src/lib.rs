@@ -2,11 +2,27 @@ use clap::Parser;
 use sanctum_solana_cli_utils::TxSendMode;
 use solana_sdk::commitment_config::CommitmentConfig;
 
+mod attest_utils;
+mod dune_utils;
+mod endorse_utils;
+mod jito_utils;
+mod ledger_utils;
+mod reward_sources;
+mod rewards_utils;
 mod solana_utils;
+mod stake_pool_utils;
 mod subcmd;
 mod utils;
 
+pub use attest_utils::*;
+pub use dune_utils::*;
+pub use endorse_utils::*;
+pub use jito_utils::*;
+pub use ledger_utils::*;
+pub use reward_sources::*;
+pub use rewards_utils::*;
 pub use solana_utils::*;
+pub use stake_pool_utils::*;
 pub use subcmd::*;
 pub use utils::*;
 
@@ -0,0 +1,580 @@
+use crate::{
+    get_block_time_at_or_before, get_first_slot_of_epoch, get_leader_slots_for_identity,
+    DuneClient, EpochRewards, ExecutionResultsResponse, ExecutionState, RewardSource, SlotReward,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use colored::Colorize;
+use serde_json::{Map, Value};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{epoch_schedule::EpochSchedule, pubkey::Pubkey};
+use spinners::{Spinner, Spinners};
+use std::{collections::BTreeMap, time::Duration};
+
+pub const DUNE_QUERY_ID: u32 = 4745888;
+pub const DEFAULT_DUNE_EPOCH_COLUMN: &str = "epoch";
+pub const DEFAULT_DUNE_REWARDS_COLUMN: &str = "block_rewards";
+pub const DEFAULT_DUNE_LAST_SLOT_COLUMN: &str = "last_slot";
+pub const DEFAULT_DUNE_LAST_BLOCK_TIME_COLUMN: &str = "last_block_time";
+/// How long after the end of an epoch Dune is assumed to have indexed all of it,
+/// if the results don't say up to which slot or block time they are
+pub const DUNE_INDEXING_LAG_SECS: i64 = 3 * 60 * 60;
+pub const DEFAULT_DUNE_TIMEOUT_SECS: u64 = 300; // 5 minutes
+const DUNE_POLL_INTERVAL_SECS: u64 = 5;
+
+pub type DuneResultRow = Map<String, Value>;
+
+/// The Dune query to run and how to read its results
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuneQuery {
+    pub query_id: u32,
+    /// Passed on top of the `epoch` and `identity_pubkey` parameters, overriding
+    /// them if they have the same name
+    pub params: BTreeMap<String, String>,
+    pub epoch_column: String,
+    /// Column with the total block rewards in lamports
+    pub rewards_column: String,
+    /// Optional column with the latest slot Dune had indexed when computing the row
+    pub last_slot_column: String,
+    /// Optional column with the block time of that slot, as a unix timestamp or a
+    /// Dune timestamp
+    pub last_block_time_column: String,
+}
+
+/// A row of the results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DuneRow {
+    pub epoch: u64,
+    pub total_block_rewards: u64,
+    pub last_slot: Option<u64>,
+    /// Unix timestamp
+    pub last_block_time: Option<i64>,
+}
+
+impl Default for DuneQuery {
+    fn default() -> Self {
+        Self {
+            query_id: DUNE_QUERY_ID,
+            params: BTreeMap::new(),
+            epoch_column: DEFAULT_DUNE_EPOCH_COLUMN.to_string(),
+            rewards_column: DEFAULT_DUNE_REWARDS_COLUMN.to_string(),
+            last_slot_column: DEFAULT_DUNE_LAST_SLOT_COLUMN.to_string(),
+            last_block_time_column: DEFAULT_DUNE_LAST_BLOCK_TIME_COLUMN.to_string(),
+        }
+    }
+}
+
+/// Dune returns integers as numbers, or as strings if they are too large
+fn value_as_u64(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(number) => number.as_u64().or_else(|| {
+            number
+                .as_f64()
+                .filter(|n| n.fract() == 0.0 && *n >= 0.0 && *n <= u64::MAX as f64)
+                .map(|n| n as u64)
+        }),
+        Value::String(string) => string.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Dune returns timestamps as strings like `2025-03-01 12:00:00.000 UTC`
+fn value_as_timestamp(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(number) => number.as_i64(),
+        Value::String(string) => DateTime::parse_from_rfc3339(string)
+            .map(|time| time.timestamp())
+            .or_else(|_| {
+                NaiveDateTime::parse_from_str(string, "%Y-%m-%d %H:%M:%S%.f UTC")
+                    .map(|time| time.and_utc().timestamp())
+            })
+            .ok(),
+        _ => None,
+    }
+}
+
+impl DuneQuery {
+    /// Parses every row of the results
+    pub fn parse_rows(
+        &self,
+        column_names: &[String],
+        rows: &[DuneResultRow],
+    ) -> Result<Vec<DuneRow>, String> {
+        // Column names are missing from the metadata of some responses
+        let column_names: Vec<&str> = match column_names.is_empty() {
+            true => rows
+                .first()
+                .map(|row| row.keys().map(String::as_str).collect())
+                .unwrap_or_default(),
+            false => column_names.iter().map(String::as_str).collect(),
+        };
+        for (column, flag) in [
+            (&self.epoch_column, "--dune-epoch-column"),
+            (&self.rewards_column, "--dune-rewards-column"),
+        ] {
+            if !rows.is_empty() && !column_names.contains(&column.as_str()) {
+                return Err(format!(
+                    "Error: Dune query {} has no `{}` column. Available columns: {}. Use {} to select another column",
+                    self.query_id,
+                    column,
+                    column_names.join(", "),
+                    flag
+                ));
+            }
+        }
+
+        rows.iter()
+            .map(|row| {
+                let read_column = |column: &str| {
+                    row.get(column).and_then(value_as_u64).ok_or_else(|| {
+                        format!(
+                            "Error: Invalid value {} in column `{}` of Dune query {}, expected an integer",
+                            row.get(column).unwrap_or(&Value::Null),
+                            column,
+                            self.query_id
+                        )
+                    })
+                };
+                Ok(DuneRow {
+                    epoch: read_column(&self.epoch_column)?,
+                    total_block_rewards: read_column(&self.rewards_column)?,
+                    last_slot: row.get(&self.last_slot_column).and_then(value_as_u64),
+                    last_block_time: row
+                        .get(&self.last_block_time_column)
+                        .and_then(value_as_timestamp),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Runs a block rewards query on Dune Analytics, the Sanctum one by default.
+/// Dune only provides epoch totals, leader slots are still fetched over RPC.
+pub struct DuneRewardSource<'a> {
+    pub rpc: &'a RpcClient,
+    pub epoch_schedule: EpochSchedule,
+    pub client: DuneClient,
+    pub query: DuneQuery,
+    pub timeout_secs: u64,
+    /// Reuse the query's latest results if they are at most this old instead of
+    /// paying for a new execution
+    pub max_age_secs: Option<u64>,
+    /// Wait for an existing execution instead of starting a new one
+    pub execution_id: Option<String>,
+    /// Save results even if Dune had not indexed the whole epoch yet
+    pub allow_stale: bool,
+    /// Use the results of executions that only partially completed, whose rows
+    /// may be truncated
+    pub allow_partial: bool,
+    /// Don't show spinners or print warnings, for use as a library
+    pub quiet: bool,
+}
+
+fn stop_spinner(sp: Option<Spinner>, message: String) {
+    if let Some(mut sp) = sp {
+        sp.stop_with_message(message);
+    }
+}
+
+/// Checks that Dune had indexed every slot of the epoch, which ends at
+/// `epoch_last_slot` at `epoch_end_time`, when `row` was computed.
+///
+/// Uses the row's last slot or block time if the query returns them, and falls
+/// back to assuming Dune lags [`DUNE_INDEXING_LAG_SECS`] behind the chain.
+/// Returns `Ok(false)` if freshness can't be checked at all.
+pub fn check_dune_row_freshness(
+    row: &DuneRow,
+    epoch_last_slot: u64,
+    epoch_end_time: Option<i64>,
+    execution_ended_at: Option<DateTime<Utc>>,
+) -> Result<bool, String> {
+    if let Some(last_slot) = row.last_slot {
+        return match last_slot >= epoch_last_slot {
+            true => Ok(true),
+            false => Err(format!(
+                "Error: Dune had only indexed up to slot {} but epoch {} ends at slot {}",
+                last_slot, row.epoch, epoch_last_slot
+            )),
+        };
+    }
+
+    let Some(epoch_end_time) = epoch_end_time else {
+        return Ok(false);
+    };
+
+    if let Some(last_block_time) = row.last_block_time {
+        return match last_block_time >= epoch_end_time {
+            true => Ok(true),
+            false => Err(format!(
+                "Error: Dune had only indexed blocks up to {} seconds before the end of epoch {}",
+                epoch_end_time - last_block_time,
+                row.epoch
+            )),
+        };
+    }
+
+    match execution_ended_at {
+        Some(ended_at) if ended_at.timestamp() >= epoch_end_time + DUNE_INDEXING_LAG_SECS => {
+            Ok(true)
+        }
+        Some(ended_at) => Err(format!(
+            "Error: The Dune query ran {} minutes after the end of epoch {}, but Dune is usually lagging by 2-3 hours",
+            (ended_at.timestamp() - epoch_end_time).max(0) / 60,
+            row.epoch
+        )),
+        None => Ok(false),
+    }
+}
+
+/// Rows returned by an execution, keyed by epoch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuneResults {
+    pub execution_id: String,
+    pub execution_ended_at: Option<DateTime<Utc>>,
+    pub rows: BTreeMap<u64, DuneRow>,
+}
+
+impl DuneRewardSource<'_> {
+    fn spinner(&self, message: String) -> Option<Spinner> {
+        (!self.quiet).then(|| Spinner::new(Spinners::Dots, message))
+    }
+
+    fn query_params(&self, identity_pubkey: &Pubkey, epoch: u64) -> BTreeMap<String, String> {
+        let mut params = BTreeMap::from([
+            ("epoch".to_string(), epoch.to_string()),
+            ("identity_pubkey".to_string(), identity_pubkey.to_string()),
+        ]);
+        params.extend(self.query.params.clone());
+        params
+    }
+
+    fn parse_results(
+        &self,
+        response: &ExecutionResultsResponse<DuneResultRow>,
+    ) -> Result<DuneResults, String> {
+        let result = response
+            .result
+            .as_ref()
+            .ok_or_else(|| "Error: Execution has no results".to_string())?;
+
+        Ok(DuneResults {
+            execution_id: response.execution_id.clone(),
+            execution_ended_at: response.execution_ended_at(),
+            rows: self
+                .query
+                .parse_rows(&result.metadata.column_names, &result.rows)?
+                .into_iter()
+                .map(|row| (row.epoch, row))
+                .collect(),
+        })
+    }
+
+    /// Results of the latest execution, if it completed less than `max_age_secs` ago
+    async fn get_recent_results(
+        &self,
+        identity_pubkey: &Pubkey,
+        epoch: u64,
+        max_age_secs: u64,
+    ) -> Result<Option<DuneResults>, String> {
+        let response = self
+            .client
+            .get_latest_results::<DuneResultRow>(
+                self.query.query_id,
+                &self.query_params(identity_pubkey, epoch),
+            )
+            .await?;
+
+        let is_recent = response.execution_ended_at().is_some_and(|ended_at| {
+            Utc::now()
+                .signed_duration_since(ended_at)
+                .num_seconds()
+                .unsigned_abs()
+                <= max_age_secs
+        });
+        if response.state != ExecutionState::Completed || !is_recent {
+            return Ok(None);
+        }
+
+        self.parse_results(&response).map(Some)
+    }
+
+    async fn execute_query(&self, identity_pubkey: &Pubkey, epoch: u64) -> Result<String, String> {
+        self.client
+            .execute_query(
+                self.query.query_id,
+                &self.query_params(identity_pubkey, epoch),
+            )
+            .await
+            .map(|response| response.execution_id)
+    }
+
+    async fn wait_for_results(&self, execution_id: &str) -> Result<DuneResults, String> {
+        // Polls at least once, even with a timeout shorter than the interval
+        let max_attempts = self.timeout_secs.div_ceil(DUNE_POLL_INTERVAL_SECS).max(1);
+
+        for _ in 0..max_attempts {
+            let state = self.client.get_status(execution_id).await?.state;
+
+            match state {
+                ExecutionState::Failed => {
+                    return Err("Error: Query execution failed".to_string());
+                }
+                ExecutionState::Cancelled => {
+                    return Err("Error: Query execution cancelled".to_string());
+                }
+                ExecutionState::Expired => {
+                    return Err("Error: Query execution results expired".to_string());
+                }
+                ExecutionState::CompletedPartial if !self.allow_partial => {
+                    return Err(
+                        "Error: Query execution only partially completed, its results may be truncated. Pass --allow-partial to use them anyway"
+                            .to_string(),
+                    );
+                }
+                ExecutionState::Completed | ExecutionState::CompletedPartial => {
+                    let response = self.client.get_results(execution_id).await?;
+                    return self.parse_results(&response);
+                }
+                ExecutionState::Pending | ExecutionState::Executing => {
+                    tokio::time::sleep(Duration::from_secs(DUNE_POLL_INTERVAL_SECS)).await;
+                }
+            }
+        }
+
+        Err(format!(
+            "Error: Query timed out. Run again with `--execution-id {}` to keep waiting for this execution",
+            execution_id
+        ))
+    }
+
+    /// Runs the query with `epoch` as parameter, unless recent results have all of
+    /// `epochs` or an execution ID to wait for was given
+    pub async fn fetch_results(
+        &self,
+        identity_pubkey: &Pubkey,
+        epoch: u64,
+        epochs: &[u64],
+    ) -> Result<DuneResults, String> {
+        if let (None, Some(max_age_secs)) = (&self.execution_id, self.max_age_secs) {
+            let sp = self.spinner("Checking the latest Dune query results...".to_string());
+
+            match self
+                .get_recent_results(identity_pubkey, epoch, max_age_secs)
+                .await
+            {
+                Ok(Some(results))
+                    if epochs.iter().all(|epoch| results.rows.contains_key(epoch)) =>
+                {
+                    stop_spinner(
+                        sp,
+                        format!(
+                            "✓ Reusing results of execution ID: {}",
+                            results.execution_id
+                        )
+                        .green()
+                        .bold()
+                        .to_string(),
+                    );
+                    return Ok(results);
+                }
+                Ok(_) => stop_spinner(
+                    sp,
+                    "No recent results for the requested epochs, executing the query"
+                        .yellow()
+                        .to_string(),
+                ),
+                Err(err) => stop_spinner(
+                    sp,
+                    format!("{}, executing the query", err).yellow().to_string(),
+                ),
+            }
+        }
+
+        let execution_id = match &self.execution_id {
+            Some(execution_id) => execution_id.clone(),
+            None => {
+                let sp = self.spinner(format!(
+                    "Executing Dune query for {}...",
+                    &identity_pubkey.to_string()[..6]
+                ));
+
+                match self.execute_query(identity_pubkey, epoch).await {
+                    Ok(execution_id) => {
+                        if let Some(mut sp) = sp {
+                            sp.stop();
+                        }
+                        execution_id
+                    }
+                    Err(err) => {
+                        stop_spinner(sp, "✗ Dune query failed".red().to_string());
+                        return Err(err);
+                    }
+                }
+            }
+        };
+
+        let sp = self.spinner(format!(
+            "Waiting for result of execution ID: {}",
+            execution_id
+        ));
+
+        match self.wait_for_results(&execution_id).await {
+            Ok(results) => {
+                stop_spinner(sp, "✓ Execution completed!".green().bold().to_string());
+                Ok(results)
+            }
+            Err(err) => {
+                stop_spinner(sp, "✗ Dune query failed".red().to_string());
+                Err(err)
+            }
+        }
+    }
+
+    /// Refuses rows of epochs that Dune had not fully indexed, or for which it
+    /// can't be checked, unless `allow_stale`
+    async fn check_freshness(&self, results: &DuneResults, row: &DuneRow) -> Result<(), String> {
+        let epoch_last_slot = get_first_slot_of_epoch(row.epoch + 1, &self.epoch_schedule) - 1;
+
+        // The last slot is enough to check, without the epoch's end time
+        let epoch_end_time = match row.last_slot {
+            Some(_) => None,
+            None => match get_block_time_at_or_before(self.rpc, epoch_last_slot).await {
+                Ok(time) => Some(time),
+                Err(err) => {
+                    return self.allow_if_stale(
+                        format!(
+                            "{}, so could not check whether Dune had indexed all of epoch {}",
+                            err, row.epoch
+                        ),
+                        "Pass --allow-stale to use the data anyway",
+                    );
+                }
+            },
+        };
+
+        match check_dune_row_freshness(
+            row,
+            epoch_last_slot,
+            epoch_end_time,
+            results.execution_ended_at,
+        ) {
+            Ok(true) => Ok(()),
+            Ok(false) => self.allow_if_stale(
+                format!(
+                    "Error: Could not check whether Dune had indexed all of epoch {}",
+                    row.epoch
+                ),
+                &format!(
+                    "Return a `{}` column from the query to check it, or pass --allow-stale to use the data anyway",
+                    self.query.last_slot_column
+                ),
+            ),
+            Err(err) => self.allow_if_stale(
+                err,
+                "Wait for Dune to catch up, or pass --allow-stale to use the data anyway",
+            ),
+        }
+    }
+
+    /// Refuses with `err` followed by `hint`, or only warns with `--allow-stale`
+    fn allow_if_stale(&self, err: String, hint: &str) -> Result<(), String> {
+        if !self.allow_stale {
+            return Err(format!("{}. {}", err, hint));
+        }
+        if !self.quiet {
+            println!(
+                "{}",
+                format!("⚠️ {}. Continuing because --allow-stale was provided", err)
+                    .red()
+                    .bold()
+            );
+        }
+        Ok(())
+    }
+
+    fn epoch_rewards(&self, row: &DuneRow, num_leader_slots: Option<u64>) -> EpochRewards {
+        EpochRewards {
+            total_block_rewards: row.total_block_rewards,
+            source: Some(self.name().to_string()),
+            num_leader_slots,
+            slot_rewards: None,
+            fee_breakdown: None,
+            mev_rewards: None,
+        }
+    }
+
+    /// Rewards of every epoch in `epochs` returned by a single execution, with the
+    /// first epoch as the query's `epoch` parameter. Epochs missing from the
+    /// results, or that Dune had not fully indexed, are left out.
+    pub async fn fetch_epochs_rewards(
+        &self,
+        identity_pubkey: &Pubkey,
+        epochs: &[u64],
+    ) -> Result<BTreeMap<u64, EpochRewards>, String> {
+        let first_epoch = *epochs
+            .first()
+            .ok_or_else(|| "Error: No epochs to fetch".to_string())?;
+
+        let results = self
+            .fetch_results(identity_pubkey, first_epoch, epochs)
+            .await?;
+
+        let mut epochs_rewards = BTreeMap::new();
+        for row in results
+            .rows
+            .values()
+            .filter(|row| epochs.contains(&row.epoch))
+        {
+            match self.check_freshness(&results, row).await {
+                Ok(()) => {
+                    epochs_rewards.insert(row.epoch, self.epoch_rewards(row, None));
+                }
+                Err(err) if !self.quiet => println!("{}", err.red().bold()),
+                Err(_) => (),
+            }
+        }
+
+        Ok(epochs_rewards)
+    }
+}
+
+#[async_trait]
+impl RewardSource for DuneRewardSource<'_> {
+    fn name(&self) -> &str {
+        "dune"
+    }
+
+    async fn fetch_leader_slots(
+        &self,
+        identity_pubkey: &Pubkey,
+        epoch: u64,
+    ) -> Result<Vec<u64>, String> {
+        get_leader_slots_for_identity(self.rpc, epoch, &self.epoch_schedule, identity_pubkey).await
+    }
+
+    async fn fetch_slot_rewards(
+        &self,
+        _identity_pubkey: &Pubkey,
+        _epoch: u64,
+        _leader_slots: &[u64],
+    ) -> Result<Vec<SlotReward>, String> {
+        Err("Error: Dune only provides total block rewards per epoch".to_string())
+    }
+
+    async fn fetch_epoch_rewards(
+        &self,
+        identity_pubkey: &Pubkey,
+        epoch: u64,
+        leader_slots: &[u64],
+    ) -> Result<EpochRewards, String> {
+        let results = self.fetch_results(identity_pubkey, epoch, &[epoch]).await?;
+        let row = results
+            .rows
+            .get(&epoch)
+            .ok_or_else(|| format!("Error: No rewards data found for epoch {}", epoch))?;
+
+        self.check_freshness(&results, row).await?;
+
+        Ok(self.epoch_rewards(row, Some(leader_slots.len() as u64)))
+    }
+}
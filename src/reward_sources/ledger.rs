@@ -0,0 +1,234 @@
+use crate::{block_fee_breakdown, RewardSource, SlotReward};
+use async_trait::async_trait;
+use serde::Deserialize;
+use solana_sdk::{epoch_schedule::EpochSchedule, pubkey::Pubkey, reward_type::RewardType};
+use solana_transaction_status::UiConfirmedBlock;
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+#[derive(Debug, Deserialize)]
+struct LedgerBlock {
+    /// Present in `solana block --output json` and `solana-ledger-tool` output,
+    /// missing from raw `getBlock` responses
+    #[serde(default)]
+    slot: Option<u64>,
+    #[serde(flatten)]
+    block: UiConfirmedBlock,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum LedgerFile {
+    RpcResponse { result: LedgerBlock },
+    Block(LedgerBlock),
+    Blocks(Vec<LedgerBlock>),
+}
+
+/// Reads block rewards from a directory of exported blocks, so calculations can be
+/// done fully offline.
+///
+/// Every `.json` file must contain a block in the `getBlock` JSON shape (optionally
+/// wrapped in the JSON-RPC response), or an array of blocks. `.jsonl` files contain
+/// one block per line. Blocks without a `slot` field take their slot from the file
+/// name, e.g. `322272000.json`.
+///
+/// Since skipped slots are not in the ledger, leader slots are the slots of the
+/// blocks in which `identity_pubkey` received the fee reward.
+pub struct LedgerRewardSource {
+    pub blocks_dir: PathBuf,
+    pub epoch_schedule: EpochSchedule,
+    /// Break down the fees of each block. Blocks must be exported with full
+    /// transaction details in the `json` or `base64` encoding.
+    pub detailed: bool,
+    /// Blocks of the last epoch read, so leader slots and rewards are read from a
+    /// single pass over `blocks_dir`
+    epoch_blocks: Mutex<Option<(u64, Arc<EpochBlocks>)>>,
+}
+
+type EpochBlocks = BTreeMap<u64, UiConfirmedBlock>;
+
+impl LedgerRewardSource {
+    pub fn new(blocks_dir: PathBuf, epoch_schedule: EpochSchedule, detailed: bool) -> Self {
+        Self {
+            blocks_dir,
+            epoch_schedule,
+            detailed,
+            epoch_blocks: Mutex::new(None),
+        }
+    }
+
+    fn read_file_blocks(path: &Path) -> Result<Vec<(u64, UiConfirmedBlock)>, String> {
+        let content =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+        let file_slot = path
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .and_then(|stem| stem.parse::<u64>().ok());
+
+        let blocks = if path.extension() == Some(OsStr::new("jsonl")) {
+            content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(serde_json::from_str::<LedgerBlock>)
+                .collect::<Result<Vec<_>, _>>()
+        } else {
+            serde_json::from_str::<LedgerFile>(&content).map(|file| match file {
+                LedgerFile::RpcResponse { result } => vec![result],
+                LedgerFile::Block(block) => vec![block],
+                LedgerFile::Blocks(blocks) => blocks,
+            })
+        }
+        .map_err(|e| format!("Failed to parse {:?}: {}", path, e))?;
+
+        let single_block = blocks.len() == 1;
+        blocks
+            .into_iter()
+            .map(|LedgerBlock { slot, block }| {
+                slot.or(file_slot.filter(|_| single_block))
+                    .map(|slot| (slot, block))
+                    .ok_or_else(|| format!("Failed to find the slot of a block in {:?}", path))
+            })
+            .collect()
+    }
+
+    /// All blocks of `epoch` in `blocks_dir`, keyed by slot
+    fn read_epoch_blocks(&self, epoch: u64) -> Result<EpochBlocks, String> {
+        let first_slot = self.epoch_schedule.get_first_slot_in_epoch(epoch);
+        let last_slot = self.epoch_schedule.get_last_slot_in_epoch(epoch);
+        let in_epoch = |slot: &u64| (first_slot..=last_slot).contains(slot);
+
+        let entries = fs::read_dir(&self.blocks_dir)
+            .map_err(|e| format!("Failed to read {:?}: {}", self.blocks_dir, e))?;
+
+        let mut blocks = BTreeMap::new();
+        for entry in entries {
+            let path = entry.map_err(|e| e.to_string())?.path();
+
+            let is_block_file = matches!(
+                path.extension().and_then(OsStr::to_str),
+                Some("json") | Some("jsonl")
+            );
+            // Skip files named after slots outside of the epoch without parsing them
+            let outside_epoch = path
+                .file_stem()
+                .and_then(OsStr::to_str)
+                .and_then(|stem| stem.parse::<u64>().ok())
+                .is_some_and(|slot| !in_epoch(&slot));
+            if !is_block_file || outside_epoch {
+                continue;
+            }
+
+            blocks.extend(
+                Self::read_file_blocks(&path)?
+                    .into_iter()
+                    .filter(|(slot, _)| in_epoch(slot)),
+            );
+        }
+
+        Ok(blocks)
+    }
+
+    /// [`Self::read_epoch_blocks`], reusing the blocks of the last epoch read
+    fn epoch_blocks(&self, epoch: u64) -> Result<Arc<EpochBlocks>, String> {
+        let mut epoch_blocks = self
+            .epoch_blocks
+            .lock()
+            .map_err(|_| "Error: Failed to read the ledger blocks".to_string())?;
+
+        match epoch_blocks.as_ref() {
+            Some((read_epoch, blocks)) if *read_epoch == epoch => Ok(Arc::clone(blocks)),
+            _ => {
+                let blocks = Arc::new(self.read_epoch_blocks(epoch)?);
+                *epoch_blocks = Some((epoch, Arc::clone(&blocks)));
+                Ok(blocks)
+            }
+        }
+    }
+}
+
+/// Sum of the rewards of `block`, which can't be negative for a leader
+fn block_rewards(block: &UiConfirmedBlock, slot: u64) -> Result<u64, String> {
+    block
+        .rewards
+        .iter()
+        .flatten()
+        .try_fold(0u64, |total, reward| {
+            let lamports = u64::try_from(reward.lamports).map_err(|_| {
+                format!(
+                    "Error: Negative reward of {} lamports in slot {}",
+                    reward.lamports, slot
+                )
+            })?;
+            total
+                .checked_add(lamports)
+                .ok_or_else(|| format!("Error: Overflow in calculating rewards of slot {}", slot))
+        })
+}
+
+fn is_leader(block: &UiConfirmedBlock, identity_pubkey: &str) -> bool {
+    block.rewards.iter().flatten().any(|reward| {
+        reward.reward_type == Some(RewardType::Fee) && reward.pubkey == identity_pubkey
+    })
+}
+
+#[async_trait]
+impl RewardSource for LedgerRewardSource {
+    fn name(&self) -> &str {
+        "ledger"
+    }
+
+    async fn fetch_leader_slots(
+        &self,
+        identity_pubkey: &Pubkey,
+        epoch: u64,
+    ) -> Result<Vec<u64>, String> {
+        let identity_pubkey = identity_pubkey.to_string();
+
+        Ok(self
+            .epoch_blocks(epoch)?
+            .iter()
+            .filter(|(_, block)| is_leader(block, &identity_pubkey))
+            .map(|(&slot, _)| slot)
+            .collect())
+    }
+
+    async fn fetch_slot_rewards(
+        &self,
+        _identity_pubkey: &Pubkey,
+        epoch: u64,
+        leader_slots: &[u64],
+    ) -> Result<Vec<SlotReward>, String> {
+        let blocks = self.epoch_blocks(epoch)?;
+
+        leader_slots
+            .iter()
+            .map(|&slot| {
+                Ok(match blocks.get(&slot) {
+                    Some(block) => SlotReward {
+                        slot,
+                        lamports: block_rewards(block, slot)?,
+                        skipped: false,
+                        fee_breakdown: match self.detailed {
+                            true => Some(
+                                block_fee_breakdown(block)
+                                    .map_err(|e| format!("{} (slot {})", e, slot))?,
+                            ),
+                            false => None,
+                        },
+                    },
+                    None => SlotReward {
+                        slot,
+                        lamports: 0,
+                        skipped: true,
+                        fee_breakdown: None,
+                    },
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,140 @@
+use async_trait::async_trait;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+mod dune;
+mod ledger;
+mod rpc;
+
+pub use dune::*;
+pub use ledger::*;
+pub use rpc::*;
+
+/// Transaction fees collected in a block, or summed over an epoch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeBreakdown {
+    pub num_transactions: u64,
+    /// Signature fees, including precompile signatures
+    pub base_fees: u64,
+    pub priority_fees: u64,
+    /// Fees that were not paid out to the leader
+    pub burned_fees: u64,
+}
+
+impl FeeBreakdown {
+    pub fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(Self {
+            num_transactions: self.num_transactions.checked_add(other.num_transactions)?,
+            base_fees: self.base_fees.checked_add(other.base_fees)?,
+            priority_fees: self.priority_fees.checked_add(other.priority_fees)?,
+            burned_fees: self.burned_fees.checked_add(other.burned_fees)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlotReward {
+    pub slot: u64,
+    pub lamports: u64,
+    pub skipped: bool,
+    /// Only fetched with `calculate --detailed`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_breakdown: Option<FeeBreakdown>,
+}
+
+/// Contents of a `rewards_<identity>_<epoch>.json` file.
+/// Everything except `total_block_rewards` is missing from files written by older versions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EpochRewards {
+    pub total_block_rewards: u64,
+    /// Name of the [`RewardSource`] the rewards were fetched from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_leader_slots: Option<u64>,
+    /// Per slot rewards, if the source provides them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slot_rewards: Option<Vec<SlotReward>>,
+    /// Sum of the per slot fee breakdowns, if fetched with `calculate --detailed`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_breakdown: Option<FeeBreakdown>,
+    /// The validator's share of Jito MEV tips, if calculated with `--mev` or `--mev-file`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mev_rewards: Option<u64>,
+}
+
+/// A source of block rewards data for a validator identity.
+///
+/// Sources only need to implement [`RewardSource::fetch_slot_rewards`] if they
+/// have per slot data. Sources that only provide epoch totals should override
+/// [`RewardSource::fetch_epoch_rewards`] instead.
+#[async_trait]
+pub trait RewardSource: Send + Sync {
+    /// Short name stored in the rewards file, e.g. `rpc`
+    fn name(&self) -> &str;
+
+    async fn fetch_leader_slots(
+        &self,
+        identity_pubkey: &Pubkey,
+        epoch: u64,
+    ) -> Result<Vec<u64>, String>;
+
+    async fn fetch_slot_rewards(
+        &self,
+        identity_pubkey: &Pubkey,
+        epoch: u64,
+        leader_slots: &[u64],
+    ) -> Result<Vec<SlotReward>, String>;
+
+    async fn fetch_epoch_rewards(
+        &self,
+        identity_pubkey: &Pubkey,
+        epoch: u64,
+        leader_slots: &[u64],
+    ) -> Result<EpochRewards, String> {
+        let slot_rewards = self
+            .fetch_slot_rewards(identity_pubkey, epoch, leader_slots)
+            .await?;
+
+        let total_block_rewards = slot_rewards
+            .iter()
+            .try_fold(0u64, |total, reward| total.checked_add(reward.lamports))
+            .ok_or_else(|| "Error: Overflow in calculating total block rewards".to_string())?;
+
+        let fee_breakdown = match slot_rewards
+            .iter()
+            .any(|reward| reward.fee_breakdown.is_some())
+        {
+            true => Some(
+                slot_rewards
+                    .iter()
+                    .filter_map(|reward| reward.fee_breakdown)
+                    .try_fold(FeeBreakdown::default(), |total, breakdown| {
+                        total.checked_add(&breakdown)
+                    })
+                    .ok_or_else(|| "Error: Overflow in calculating total fees".to_string())?,
+            ),
+            false => None,
+        };
+
+        Ok(EpochRewards {
+            total_block_rewards,
+            source: Some(self.name().to_string()),
+            num_leader_slots: Some(leader_slots.len() as u64),
+            slot_rewards: Some(slot_rewards),
+            fee_breakdown,
+            mev_rewards: None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RewardSourceKind {
+    /// Fetch every leader block over JSON-RPC
+    Rpc,
+    /// Run the Sanctum block rewards query on Dune Analytics
+    Dune,
+    /// Read blocks exported to a local directory, without any network access
+    Ledger,
+}
@@ -0,0 +1,36 @@
+use crate::{get_block_rewards_for_slots, get_leader_slots_for_identity, RewardSource, SlotReward};
+use async_trait::async_trait;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{epoch_schedule::EpochSchedule, pubkey::Pubkey};
+
+/// Fetches every leader block of the epoch over JSON-RPC
+pub struct RpcRewardSource<'a> {
+    pub rpc: &'a RpcClient,
+    pub epoch_schedule: EpochSchedule,
+    /// Also fetch every transaction to break down the fees of each block
+    pub detailed: bool,
+}
+
+#[async_trait]
+impl RewardSource for RpcRewardSource<'_> {
+    fn name(&self) -> &str {
+        "rpc"
+    }
+
+    async fn fetch_leader_slots(
+        &self,
+        identity_pubkey: &Pubkey,
+        epoch: u64,
+    ) -> Result<Vec<u64>, String> {
+        get_leader_slots_for_identity(self.rpc, epoch, &self.epoch_schedule, identity_pubkey).await
+    }
+
+    async fn fetch_slot_rewards(
+        &self,
+        _identity_pubkey: &Pubkey,
+        _epoch: u64,
+        leader_slots: &[u64],
+    ) -> Result<Vec<SlotReward>, String> {
+        get_block_rewards_for_slots(self.rpc, leader_slots, self.detailed).await
+    }
+}
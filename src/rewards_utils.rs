@@ -0,0 +1,876 @@
+use crate::{
+    checked_pct, get_mev_rewards_for_epoch, get_successful_transaction,
+    get_vote_account_for_identity, read_mev_rewards_file,
+    transfer_to_reserve_and_update_stake_pool_balance_ixs_for_account,
+    transfer_to_reserve_and_update_stake_pool_balance_ixs_for_pool, validate_stake_pool,
+    with_auto_cb_ixs, with_fixed_cb_ixs, DuneClient, DuneQuery, DuneRewardSource, EpochRewards,
+    FeeBreakdown, LedgerRewardSource, LstInfo, Pool, PoolBalance, PrintTransferSummaryArgs,
+    RewardSource, RpcRewardSource, SignedTransferFile, MAX_SIGNATURES_PER_PAGE,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config,
+};
+use solana_sdk::{
+    account::Account,
+    epoch_schedule::EpochSchedule,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    message::{v0::Message, VersionedMessage},
+    pubkey::Pubkey,
+    signature::Signature,
+    system_instruction::{advance_nonce_account, SystemInstruction},
+    system_program,
+    transaction::VersionedTransaction,
+};
+use spl_stake_pool_interface::StakePool;
+use std::{path::PathBuf, str::FromStr};
+
+const DISTRIBUTION_MEMO_PREFIX: &str = "sbr";
+
+/// Stake pool transactions searched by default for an earlier distribution
+pub const DEFAULT_MAX_DISTRIBUTION_SIGNATURES: usize = 10_000;
+
+/// SOL the payer keeps by default after a distribution, on top of the rent exempt
+/// minimum and tx fees
+pub const DEFAULT_RESERVE_FLOOR_SOL: &str = "1";
+
+/// Where [`calculate_epoch_rewards`] fetches block rewards from
+#[derive(Debug, Clone)]
+pub enum RewardSourceOpts {
+    Rpc {
+        detailed: bool,
+    },
+    Dune {
+        client: DuneClient,
+        query: DuneQuery,
+        timeout_secs: u64,
+        max_age_secs: Option<u64>,
+        execution_id: Option<String>,
+        allow_stale: bool,
+        allow_partial: bool,
+    },
+    /// Assumes the mainnet epoch schedule
+    Ledger {
+        blocks_dir: PathBuf,
+        detailed: bool,
+    },
+}
+
+/// Where to get the validator's Jito MEV tips from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MevSource {
+    /// The validator's tip distribution account, looking up the vote account from
+    /// the identity if not provided
+    TipDistributionAccount { vote_pubkey: Option<Pubkey> },
+    /// A JSON file of the form `{"epoch": 746, "mev_rewards": <lamports>}`
+    File(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct CalculateOpts {
+    pub source: RewardSourceOpts,
+    pub mev: Option<MevSource>,
+    /// Don't show the Dune source's spinners or print its warnings
+    pub quiet: bool,
+}
+
+pub async fn fetch_mev_rewards(
+    rpc: &RpcClient,
+    identity_pubkey: &Pubkey,
+    mev_source: &MevSource,
+    epoch: u64,
+) -> Result<u64, String> {
+    let vote_pubkey = match mev_source {
+        MevSource::File(path) => return read_mev_rewards_file(path, epoch),
+        MevSource::TipDistributionAccount {
+            vote_pubkey: Some(vote_pubkey),
+        } => *vote_pubkey,
+        MevSource::TipDistributionAccount { vote_pubkey: None } => {
+            get_vote_account_for_identity(rpc, identity_pubkey).await?
+        }
+    };
+
+    get_mev_rewards_for_epoch(rpc, &vote_pubkey, epoch).await
+}
+
+/// Fetches the rewards of `identity_pubkey` in `epoch`, which the caller is
+/// expected to have checked is completed. Nothing is prompted or saved to the
+/// rewards file.
+pub async fn calculate_epoch_rewards(
+    rpc: &RpcClient,
+    identity_pubkey: &Pubkey,
+    epoch: u64,
+    opts: CalculateOpts,
+) -> Result<EpochRewards, String> {
+    let epoch_schedule = match &opts.source {
+        RewardSourceOpts::Ledger { .. } => EpochSchedule::without_warmup(),
+        _ => rpc
+            .get_epoch_schedule()
+            .await
+            .map_err(|e| format!("Error: Failed to fetch epoch schedule: {}", e))?,
+    };
+
+    let reward_source: Box<dyn RewardSource + '_> = match opts.source {
+        RewardSourceOpts::Rpc { detailed } => Box::new(RpcRewardSource {
+            rpc,
+            epoch_schedule,
+            detailed,
+        }),
+        RewardSourceOpts::Dune {
+            client,
+            query,
+            timeout_secs,
+            max_age_secs,
+            execution_id,
+            allow_stale,
+            allow_partial,
+        } => Box::new(DuneRewardSource {
+            rpc,
+            epoch_schedule,
+            client,
+            query,
+            timeout_secs,
+            max_age_secs,
+            execution_id,
+            allow_stale,
+            allow_partial,
+            quiet: opts.quiet,
+        }),
+        RewardSourceOpts::Ledger {
+            blocks_dir,
+            detailed,
+        } => Box::new(LedgerRewardSource::new(
+            blocks_dir,
+            epoch_schedule,
+            detailed,
+        )),
+    };
+
+    let mev_rewards = match &opts.mev {
+        Some(mev_source) => Some(fetch_mev_rewards(rpc, identity_pubkey, mev_source, epoch).await?),
+        None => None,
+    };
+
+    let leader_slots = reward_source
+        .fetch_leader_slots(identity_pubkey, epoch)
+        .await?;
+
+    let rewards = reward_source
+        .fetch_epoch_rewards(identity_pubkey, epoch, &leader_slots)
+        .await?;
+
+    Ok(EpochRewards {
+        mev_rewards,
+        ..rewards
+    })
+}
+
+/// Percentages of an epoch's rewards to distribute, in bps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RewardsSplitBps {
+    /// Share of the rewards attributed to the stake pool's stake
+    pub total_rewards_bps: u64,
+    /// Share of the stake pool's block rewards given to LST holders
+    pub lst_rewards_bps: u64,
+    /// Share of the stake pool's MEV rewards given to LST holders
+    pub mev_rewards_bps: u64,
+}
+
+/// How an epoch's rewards are split between the stake pool and LST holders, in lamports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RewardsSplit {
+    pub total_block_rewards: u64,
+    pub stake_pool_rewards: u64,
+    pub lst_rewards: u64,
+    pub total_mev_rewards: u64,
+    pub stake_pool_mev_rewards: u64,
+    pub lst_mev_rewards: u64,
+    /// Lamports to transfer to the stake pool reserve
+    pub transfer_lamports: u64,
+}
+
+/// MEV rewards are split with the same stake share as block rewards, but LST
+/// holders can be given a different percentage of them. Every share is rounded
+/// down, so the lamports lost to rounding stay with the validator.
+pub fn calculate_rewards_split(
+    rewards: &EpochRewards,
+    bps: &RewardsSplitBps,
+) -> Result<RewardsSplit, String> {
+    let total_block_rewards = rewards.total_block_rewards;
+    let total_mev_rewards = rewards.mev_rewards.unwrap_or(0);
+
+    let stake_pool_rewards = checked_pct(total_block_rewards, bps.total_rewards_bps)
+        .ok_or_else(|| "Error: Error in calculating stake pool rewards".to_string())?;
+    let lst_rewards = checked_pct(stake_pool_rewards, bps.lst_rewards_bps)
+        .ok_or_else(|| "Error: Overflow in calculating LST rewards".to_string())?;
+
+    let stake_pool_mev_rewards = checked_pct(total_mev_rewards, bps.total_rewards_bps)
+        .ok_or_else(|| "Error: Error in calculating stake pool MEV rewards".to_string())?;
+    let lst_mev_rewards = checked_pct(stake_pool_mev_rewards, bps.mev_rewards_bps)
+        .ok_or_else(|| "Error: Overflow in calculating LST MEV rewards".to_string())?;
+
+    let transfer_lamports = lst_rewards
+        .checked_add(lst_mev_rewards)
+        .ok_or_else(|| "Error: Overflow in calculating total LST rewards".to_string())?;
+
+    Ok(RewardsSplit {
+        total_block_rewards,
+        stake_pool_rewards,
+        lst_rewards,
+        total_mev_rewards,
+        stake_pool_mev_rewards,
+        lst_mev_rewards,
+        transfer_lamports,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistributionTxArgs {
+    pub payer_pubkey: Pubkey,
+    pub identity_pubkey: Pubkey,
+    pub stake_pool_pubkey: Pubkey,
+    pub epoch: u64,
+    pub transfer_lamports: u64,
+    /// Max priority fee in lamports, 0 to not prepend compute budget instructions
+    /// and so build the tx without any RPC call
+    pub fee_limit_cb: u64,
+    /// Durable nonce account and its authority, to sign against the nonce's blockhash
+    pub nonce: Option<(Pubkey, Pubkey)>,
+}
+
+/// Instructions that transfer `transfer_lamports` to the stake pool reserve and
+/// update the pool's balance, built from the account returned by [`validate_stake_pool`].
+/// The only RPC call is the simulation that sizes the compute budget instructions,
+/// which is skipped if `args.fee_limit_cb` is 0.
+pub async fn distribution_ixs(
+    rpc: &RpcClient,
+    args: &DistributionTxArgs,
+    stake_pool_account: &Account,
+) -> Result<Vec<Instruction>, String> {
+    let mut ixs = transfer_to_reserve_and_update_stake_pool_balance_ixs_for_account(
+        stake_pool_account,
+        &args.payer_pubkey,
+        &args.identity_pubkey,
+        &args.stake_pool_pubkey,
+        args.transfer_lamports,
+        args.epoch,
+    )?;
+
+    let ixs =
+        with_nonce_and_auto_cb_ixs(rpc, &args.payer_pubkey, ixs, args.nonce, args.fee_limit_cb)
+            .await;
+    Ok(ixs)
+}
+
+/// [`with_auto_cb_ixs`] for a tx that may be signed against a durable nonce, given
+/// as the nonce account and its authority
+pub async fn with_nonce_and_auto_cb_ixs(
+    rpc: &RpcClient,
+    payer_pubkey: &Pubkey,
+    mut ixs: Vec<Instruction>,
+    nonce: Option<(Pubkey, Pubkey)>,
+    fee_limit_cb: u64,
+) -> Vec<Instruction> {
+    // The nonce ix is added before simulating so its compute units are accounted for
+    if let Some((nonce_account, nonce_authority)) = &nonce {
+        ixs.insert(0, advance_nonce_account(nonce_account, nonce_authority));
+    }
+    let num_ixs = ixs.len();
+
+    let mut ixs = with_auto_cb_ixs(rpc, payer_pubkey, ixs, &[], fee_limit_cb).await;
+
+    if nonce.is_some() {
+        // AdvanceNonceAccount must be the first instruction of a durable nonce tx,
+        // ahead of the prepended compute budget instructions
+        let nonce_ix = ixs.remove(ixs.len() - num_ixs);
+        ixs.insert(0, nonce_ix);
+    }
+
+    ixs
+}
+
+/// [`with_nonce_and_auto_cb_ixs`] with a fixed compute unit limit, for signing
+/// without RPC access. The limit must cover the nonce ix too
+pub fn with_nonce_and_fixed_cb_ixs(
+    ixs: Vec<Instruction>,
+    nonce: Option<(Pubkey, Pubkey)>,
+    compute_unit_limit: u32,
+    fee_limit_cb: u64,
+) -> Vec<Instruction> {
+    let mut ixs = with_fixed_cb_ixs(ixs, compute_unit_limit, fee_limit_cb);
+    // AdvanceNonceAccount must be the first instruction of a durable nonce tx
+    if let Some((nonce_account, nonce_authority)) = &nonce {
+        ixs.insert(0, advance_nonce_account(nonce_account, nonce_authority));
+    }
+    ixs
+}
+
+/// Unsigned message of the distribution tx against `blockhash`, which must be the
+/// nonce's blockhash if `args.nonce` is set. See [`distribution_ixs`] for its RPC use.
+pub async fn build_distribution_tx(
+    rpc: &RpcClient,
+    args: &DistributionTxArgs,
+    stake_pool_account: &Account,
+    blockhash: Hash,
+) -> Result<VersionedMessage, String> {
+    let ixs = distribution_ixs(rpc, args, stake_pool_account).await?;
+    let message = Message::try_compile(&args.payer_pubkey, &ixs, &[], blockhash)
+        .map_err(|e| format!("Error: Failed to compile transaction message: {}", e))?;
+    Ok(VersionedMessage::V0(message))
+}
+
+/// An [`Instruction`] with base58 pubkeys and data, for review in plan files
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanInstruction {
+    pub program_id: String,
+    pub accounts: Vec<PlanAccountMeta>,
+    pub data: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanAccountMeta {
+    pub pubkey: String,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl From<&Instruction> for PlanInstruction {
+    fn from(ix: &Instruction) -> Self {
+        Self {
+            program_id: ix.program_id.to_string(),
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|meta| PlanAccountMeta {
+                    pubkey: meta.pubkey.to_string(),
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: bs58::encode(&ix.data).into_string(),
+        }
+    }
+}
+
+impl PlanInstruction {
+    pub fn to_instruction(&self) -> Result<Instruction, String> {
+        let parse_pubkey = |pubkey: &str| {
+            Pubkey::from_str(pubkey)
+                .map_err(|_| format!("Error: Invalid pubkey {} in plan instruction", pubkey))
+        };
+        Ok(Instruction {
+            program_id: parse_pubkey(&self.program_id)?,
+            accounts: self
+                .accounts
+                .iter()
+                .map(|meta| {
+                    Ok(AccountMeta {
+                        pubkey: parse_pubkey(&meta.pubkey)?,
+                        is_signer: meta.is_signer,
+                        is_writable: meta.is_writable,
+                    })
+                })
+                .collect::<Result<_, String>>()?,
+            data: bs58::decode(&self.data)
+                .into_vec()
+                .map_err(|_| "Error: Invalid data in plan instruction".to_string())?,
+        })
+    }
+}
+
+/// Balances the distribution is expected to leave, as of when it was planned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpectedBalances {
+    pub reserve_pre: u64,
+    pub reserve_post: u64,
+    pub pool_balance_pre: PoolBalance,
+    pub pool_balance_post: PoolBalance,
+}
+
+/// Contents of a `plan_<identity>_<epoch>.json` file: a distribution computed by
+/// `plan` for review before `execute` sends it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DistributionPlan {
+    pub identity_pubkey: String,
+    pub stake_pool_pubkey: String,
+    pub payer_pubkey: String,
+    pub epoch: u64,
+    pub bps: RewardsSplitBps,
+    pub split: RewardsSplit,
+    pub expected_balances: ExpectedBalances,
+    /// Instructions of the distribution tx, without compute budget instructions
+    pub instructions: Vec<PlanInstruction>,
+    pub lst_info: LstInfo,
+    /// Projected epoch duration used to annualize the yield boost
+    pub epoch_duration_secs: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_breakdown: Option<FeeBreakdown>,
+    /// RFC 3339 timestamp of when the plan was computed
+    pub created_at: String,
+}
+
+impl DistributionPlan {
+    pub fn summary(&self, payer_balance: u64) -> PrintTransferSummaryArgs {
+        PrintTransferSummaryArgs {
+            epoch: self.epoch,
+            payer_balance,
+            total_block_rewards: self.split.total_block_rewards,
+            total_rewards_bps: self.bps.total_rewards_bps,
+            stake_pool_rewards: self.split.stake_pool_rewards,
+            lst_rewards_bps: self.bps.lst_rewards_bps,
+            lst_rewards: self.split.lst_rewards,
+            total_mev_rewards: self.split.total_mev_rewards,
+            mev_rewards_bps: self.bps.mev_rewards_bps,
+            stake_pool_mev_rewards: self.split.stake_pool_mev_rewards,
+            lst_mev_rewards: self.split.lst_mev_rewards,
+            lst_info: self.lst_info.clone(),
+            pool_balance_pre: self.expected_balances.pool_balance_pre,
+            pool_balance_post: self.expected_balances.pool_balance_post,
+            epoch_duration_secs: self.epoch_duration_secs,
+            fee_breakdown: self.fee_breakdown,
+        }
+    }
+}
+
+/// Shown in place of the LST list entry when the list can't be loaded. SPL stake
+/// pool mints are created with the same decimals as SOL
+pub fn fallback_lst_info(stake_pool: &StakePool, stake_pool_pubkey: &Pubkey) -> LstInfo {
+    LstInfo {
+        name: stake_pool.pool_mint.to_string(),
+        symbol: "unknown".to_string(),
+        mint: stake_pool.pool_mint.to_string(),
+        decimals: 9,
+        logo_uri: None,
+        pool: Pool {
+            program: None,
+            pool: Some(stake_pool_pubkey.to_string()),
+        },
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistributionPlanArgs {
+    pub identity_pubkey: Pubkey,
+    pub stake_pool_pubkey: Pubkey,
+    pub payer_pubkey: Pubkey,
+    pub epoch: u64,
+    pub bps: RewardsSplitBps,
+    pub lst_info: LstInfo,
+    /// Current balance of the stake pool reserve
+    pub reserve_balance: u64,
+    /// Projected epoch duration used to annualize the yield boost
+    pub epoch_duration_secs: f64,
+}
+
+/// Splits `rewards` and computes the expected balances and instructions of their
+/// distribution, from the stake pool state returned by [`validate_stake_pool`]
+pub fn build_distribution_plan(
+    args: DistributionPlanArgs,
+    rewards: &EpochRewards,
+    stake_pool: &StakePool,
+    stake_pool_account: &Account,
+) -> Result<DistributionPlan, String> {
+    let DistributionPlanArgs {
+        identity_pubkey,
+        stake_pool_pubkey,
+        payer_pubkey,
+        epoch,
+        bps,
+        lst_info,
+        reserve_balance,
+        epoch_duration_secs,
+    } = args;
+
+    let split = calculate_rewards_split(rewards, &bps)?;
+    let transfer_lamports = split.transfer_lamports;
+
+    let pool_balance_pre = PoolBalance::from_stake_pool(stake_pool);
+    let pool_balance_post = pool_balance_pre
+        .after_reward(transfer_lamports, &stake_pool.epoch_fee)
+        .ok_or_else(|| {
+            "Error: Overflow in calculating the post transfer stake pool balance".to_string()
+        })?;
+    let reserve_post = reserve_balance
+        .checked_add(transfer_lamports)
+        .ok_or_else(|| {
+            "Error: Overflow in calculating the post transfer reserve balance".to_string()
+        })?;
+
+    let ixs = transfer_to_reserve_and_update_stake_pool_balance_ixs_for_pool(
+        &stake_pool_account.owner,
+        stake_pool,
+        &payer_pubkey,
+        &identity_pubkey,
+        &stake_pool_pubkey,
+        transfer_lamports,
+        epoch,
+    )?;
+
+    Ok(DistributionPlan {
+        identity_pubkey: identity_pubkey.to_string(),
+        stake_pool_pubkey: stake_pool_pubkey.to_string(),
+        payer_pubkey: payer_pubkey.to_string(),
+        epoch,
+        bps,
+        split,
+        expected_balances: ExpectedBalances {
+            reserve_pre: reserve_balance,
+            reserve_post,
+            pool_balance_pre,
+            pool_balance_post,
+        },
+        instructions: ixs.iter().map(PlanInstruction::from).collect(),
+        lst_info,
+        epoch_duration_secs,
+        fee_breakdown: rewards.fee_breakdown,
+        created_at: Utc::now().to_rfc3339(),
+    })
+}
+
+fn parse_plan_pubkey(pubkey: &str, name: &str) -> Result<Pubkey, String> {
+    Pubkey::from_str(pubkey).map_err(|_| format!("Error: Invalid {} pubkey in plan", name))
+}
+
+/// Checks `plan` against the current chain state: the split must follow from its
+/// bps settings, the stake pool must be valid in `current_epoch`, and rebuilding
+/// the distribution instructions must give the reviewed ones.
+/// Returns the instructions to send and the current state of the stake pool.
+pub async fn validate_distribution_plan(
+    rpc: &RpcClient,
+    plan: &DistributionPlan,
+    current_epoch: u64,
+) -> Result<(Vec<Instruction>, StakePool), String> {
+    let identity_pubkey = parse_plan_pubkey(&plan.identity_pubkey, "identity")?;
+    let stake_pool_pubkey = parse_plan_pubkey(&plan.stake_pool_pubkey, "stake pool")?;
+    let payer_pubkey = parse_plan_pubkey(&plan.payer_pubkey, "payer")?;
+
+    let rewards = EpochRewards {
+        total_block_rewards: plan.split.total_block_rewards,
+        source: None,
+        num_leader_slots: None,
+        slot_rewards: None,
+        fee_breakdown: None,
+        mev_rewards: Some(plan.split.total_mev_rewards),
+    };
+    if calculate_rewards_split(&rewards, &plan.bps)? != plan.split {
+        return Err("Error: The plan's lamport amounts don't match its percentages".to_string());
+    }
+
+    let (stake_pool, stake_pool_account) =
+        validate_stake_pool(rpc, &stake_pool_pubkey, current_epoch).await?;
+
+    let ixs = transfer_to_reserve_and_update_stake_pool_balance_ixs_for_pool(
+        &stake_pool_account.owner,
+        &stake_pool,
+        &payer_pubkey,
+        &identity_pubkey,
+        &stake_pool_pubkey,
+        plan.split.transfer_lamports,
+        plan.epoch,
+    )?;
+
+    if ixs.iter().map(PlanInstruction::from).collect::<Vec<_>>() != plan.instructions {
+        return Err(
+            "Error: The plan's instructions don't match the current state of the stake pool"
+                .to_string(),
+        );
+    }
+
+    Ok((ixs, stake_pool))
+}
+
+/// Checks that `plan` splits the rewards recorded for its identity and epoch
+pub fn check_plan_rewards(plan: &DistributionPlan, rewards: &EpochRewards) -> Result<(), String> {
+    if plan.split.total_block_rewards != rewards.total_block_rewards {
+        return Err(format!(
+            "Error: The plan splits {} lamports of block rewards but {} were calculated for epoch {}",
+            plan.split.total_block_rewards, rewards.total_block_rewards, plan.epoch
+        ));
+    }
+
+    let mev_rewards = rewards.mev_rewards.unwrap_or(0);
+    if plan.split.total_mev_rewards != mev_rewards {
+        return Err(format!(
+            "Error: The plan splits {} lamports of MEV rewards but {} were calculated for epoch {}",
+            plan.split.total_mev_rewards, mev_rewards, plan.epoch
+        ));
+    }
+
+    Ok(())
+}
+
+/// Finds a successful distribution of `identity_pubkey`'s rewards of `epoch` to
+/// `stake_pool_pubkey` among the pool's transactions, back to `min_slot`. Since the
+/// rewards are only known once the epoch is over, that can be the first slot of the
+/// next epoch. Searches at most `max_signatures` transactions, and errors if they
+/// don't reach back to `min_slot` since a distribution could be missed.
+pub async fn find_distribution(
+    rpc: &RpcClient,
+    epoch: u64,
+    identity_pubkey: &Pubkey,
+    stake_pool_pubkey: &Pubkey,
+    min_slot: u64,
+    max_signatures: usize,
+) -> Result<Option<Signature>, String> {
+    // The memo is in the signature list, which spares fetching every transaction
+    let memo_prefix = format!(
+        "{}-{}-{}-{}-",
+        DISTRIBUTION_MEMO_PREFIX, epoch, identity_pubkey, stake_pool_pubkey
+    );
+    let mut before = None;
+    let mut num_searched = 0;
+
+    while num_searched < max_signatures {
+        let page = rpc
+            .get_signatures_for_address_with_config(
+                stake_pool_pubkey,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until: None,
+                    limit: Some(MAX_SIGNATURES_PER_PAGE.min(max_signatures - num_searched)),
+                    commitment: Some(rpc.commitment()),
+                },
+            )
+            .await
+            .map_err(|e| format!("Error: Failed to fetch stake pool transactions: {}", e))?;
+
+        for status in page
+            .iter()
+            .take_while(|status| status.slot >= min_slot)
+            .filter(|status| {
+                status.err.is_none()
+                    && status
+                        .memo
+                        .as_deref()
+                        .is_some_and(|memo| memo.contains(&memo_prefix))
+            })
+        {
+            let signature = Signature::from_str(&status.signature)
+                .map_err(|_| "Error: Invalid signature returned by RPC".to_string())?;
+            let Some(tx) = get_successful_transaction(rpc, &signature).await? else {
+                continue;
+            };
+            if parse_distribution_tx(&tx).is_ok_and(|transfer| {
+                transfer.memo.epoch == epoch
+                    && transfer.memo.identity_pubkey == *identity_pubkey
+                    && transfer.memo.stake_pool_pubkey == *stake_pool_pubkey
+            }) {
+                return Ok(Some(signature));
+            }
+        }
+
+        // Signatures are newest first, so the rest are from before the epoch ended
+        match page.last() {
+            Some(last) if last.slot >= min_slot => {
+                before = Some(
+                    Signature::from_str(&last.signature)
+                        .map_err(|_| "Error: Invalid signature returned by RPC".to_string())?,
+                );
+                num_searched += page.len();
+            }
+            _ => return Ok(None),
+        }
+    }
+
+    Err(format!(
+        "Error: The stake pool's last {} transactions are all from after epoch {} ended, so an earlier distribution could not be ruled out. Pass a higher --max-signatures to search further",
+        max_signatures, epoch
+    ))
+}
+
+/// Memo of the distribution tx, for easy indexing
+pub fn distribution_memo(
+    epoch: u64,
+    identity_pubkey: &Pubkey,
+    stake_pool_pubkey: &Pubkey,
+    transfer_lamports: u64,
+) -> String {
+    format!(
+        "{}-{}-{}-{}-{}",
+        DISTRIBUTION_MEMO_PREFIX, epoch, identity_pubkey, stake_pool_pubkey, transfer_lamports
+    )
+}
+
+/// Contents of a `sbr-<epoch>-<identity>-<stake pool>-<lamports>` memo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DistributionMemo {
+    pub epoch: u64,
+    pub identity_pubkey: Pubkey,
+    pub stake_pool_pubkey: Pubkey,
+    pub transfer_lamports: u64,
+}
+
+pub fn parse_distribution_memo(memo: &str) -> Result<DistributionMemo, String> {
+    let invalid = || format!("Error: Invalid distribution memo `{}`", memo);
+
+    let [prefix, epoch, identity_pubkey, stake_pool_pubkey, transfer_lamports] =
+        memo.split('-').collect::<Vec<_>>()[..]
+    else {
+        return Err(invalid());
+    };
+    if prefix != DISTRIBUTION_MEMO_PREFIX {
+        return Err(invalid());
+    }
+
+    Ok(DistributionMemo {
+        epoch: epoch.parse().map_err(|_| invalid())?,
+        identity_pubkey: Pubkey::from_str(identity_pubkey).map_err(|_| invalid())?,
+        stake_pool_pubkey: Pubkey::from_str(stake_pool_pubkey).map_err(|_| invalid())?,
+        transfer_lamports: transfer_lamports.parse().map_err(|_| invalid())?,
+    })
+}
+
+/// The rewards transfer and memo of a sent distribution tx
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DistributionTransfer {
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub lamports: u64,
+    pub memo: DistributionMemo,
+}
+
+/// Finds the single system transfer and `sbr-` memo of a distribution tx
+pub fn parse_distribution_tx(tx: &VersionedTransaction) -> Result<DistributionTransfer, String> {
+    let account_keys = tx.message.static_account_keys();
+    let key = |index: u8| {
+        account_keys
+            .get(usize::from(index))
+            .copied()
+            .ok_or_else(|| "Error: Transaction uses address lookup tables".to_string())
+    };
+
+    let mut transfers = Vec::new();
+    let mut memos = Vec::new();
+    for ix in tx.message.instructions() {
+        let program_id = key(ix.program_id_index)?;
+        if program_id == system_program::id() {
+            if let Ok(SystemInstruction::Transfer { lamports }) = bincode::deserialize(&ix.data) {
+                let [from, to, ..] = ix.accounts[..] else {
+                    return Err("Error: Invalid system transfer instruction".to_string());
+                };
+                transfers.push((key(from)?, key(to)?, lamports));
+            }
+        } else if program_id == spl_memo::id() || program_id == spl_memo::v1::id() {
+            if let Some(memo) = std::str::from_utf8(&ix.data)
+                .ok()
+                .filter(|memo| memo.starts_with(DISTRIBUTION_MEMO_PREFIX))
+            {
+                memos.push(parse_distribution_memo(memo)?);
+            }
+        }
+    }
+
+    let [(from, to, lamports)] = transfers[..] else {
+        return Err(format!(
+            "Error: Expected 1 system transfer in the transaction, found {}",
+            transfers.len()
+        ));
+    };
+    let [memo] = memos[..] else {
+        return Err(format!(
+            "Error: Expected 1 `{}-` memo in the transaction, found {}",
+            DISTRIBUTION_MEMO_PREFIX,
+            memos.len()
+        ));
+    };
+
+    Ok(DistributionTransfer {
+        from,
+        to,
+        lamports,
+        memo,
+    })
+}
+
+/// Checks that `transfer` sent `split` to the reserve of the stake pool named in its memo
+pub fn verify_distribution_transfer(
+    transfer: &DistributionTransfer,
+    stake_pool: &StakePool,
+    split: &RewardsSplit,
+) -> Result<(), String> {
+    if transfer.to != stake_pool.reserve_stake {
+        return Err(format!(
+            "Error: The transfer went to {}, not to the reserve {} of stake pool {}",
+            transfer.to, stake_pool.reserve_stake, transfer.memo.stake_pool_pubkey
+        ));
+    }
+
+    if transfer.lamports != transfer.memo.transfer_lamports {
+        return Err(format!(
+            "Error: The transfer sent {} lamports but its memo says {}",
+            transfer.lamports, transfer.memo.transfer_lamports
+        ));
+    }
+
+    if transfer.lamports != split.transfer_lamports {
+        return Err(format!(
+            "Error: The transfer sent {} lamports but the rewards record gives {}",
+            transfer.lamports, split.transfer_lamports
+        ));
+    }
+
+    Ok(())
+}
+
+/// Checks that the tx of a `transfer --sign-only` file does what its summary says:
+/// the payer pays the fees and transfers the summary's LST rewards to `reserve_stake`,
+/// with a memo naming the file's identity, stake pool and epoch
+pub fn verify_signed_transfer(
+    tx: &VersionedTransaction,
+    signed_transfer: &SignedTransferFile,
+    reserve_stake: &Pubkey,
+) -> Result<DistributionTransfer, String> {
+    let payer_pubkey = Pubkey::from_str(&signed_transfer.payer_pubkey)
+        .map_err(|_| "Error: Invalid payer pubkey in signed transfer file".to_string())?;
+
+    let fee_payer = tx.message.static_account_keys().first();
+    if fee_payer != Some(&payer_pubkey) {
+        return Err(format!(
+            "Error: The transaction's fee payer is not the payer {} of the signed transfer file",
+            payer_pubkey
+        ));
+    }
+
+    let transfer = parse_distribution_tx(tx)?;
+    let summary = &signed_transfer.summary;
+
+    if transfer.from != payer_pubkey {
+        return Err(format!(
+            "Error: The transaction transfers from {}, not from the payer {}",
+            transfer.from, payer_pubkey
+        ));
+    }
+
+    if transfer.to != *reserve_stake {
+        return Err(format!(
+            "Error: The transaction transfers to {}, not to the stake pool reserve {}",
+            transfer.to, reserve_stake
+        ));
+    }
+
+    let lst_rewards = summary
+        .lst_rewards
+        .checked_add(summary.lst_mev_rewards)
+        .ok_or_else(|| "Error: Overflow in calculating total LST rewards".to_string())?;
+    if transfer.lamports != lst_rewards {
+        return Err(format!(
+            "Error: The transaction transfers {} lamports but the summary gives {}",
+            transfer.lamports, lst_rewards
+        ));
+    }
+
+    let memo = &transfer.memo;
+    if memo.identity_pubkey.to_string() != signed_transfer.identity_pubkey
+        || memo.stake_pool_pubkey.to_string() != signed_transfer.stake_pool_pubkey
+        || memo.epoch != summary.epoch
+        || memo.transfer_lamports != transfer.lamports
+    {
+        return Err(
+            "Error: The transaction's memo doesn't match the signed transfer file".to_string(),
+        );
+    }
+
+    Ok(transfer)
+}
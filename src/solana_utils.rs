@@ -1,4 +1,4 @@
-use borsh::BorshDeserialize;
+use crate::{distribution_memo, FeeBreakdown, SlotReward, LAMPORTS_PER_SIGNATURE};
 use indicatif::{ProgressBar, ProgressState, ProgressStyle};
 use sanctum_solana_cli_utils::{
     HandleTxArgs, RecentBlockhash, TxSendMode, TxSendingNonblockingRpcClient,
@@ -11,21 +11,36 @@ use sanctum_spl_stake_pool_lib::{deserialize_stake_pool_checked, FindWithdrawAut
 use solana_client::{
     client_error::ClientErrorKind,
     nonblocking::rpc_client::RpcClient,
-    rpc_config::{RpcBlockConfig, RpcLeaderScheduleConfig},
+    nonce_utils::nonblocking as nonce_utils,
+    rpc_config::{RpcBlockConfig, RpcLeaderScheduleConfig, RpcTransactionConfig},
     rpc_request::RpcError,
 };
 use solana_sdk::{
-    account::ReadableAccount,
+    account::{Account, ReadableAccount},
     address_lookup_table::AddressLookupTableAccount,
     compute_budget::ComputeBudgetInstruction,
+    ed25519_program,
     epoch_schedule::{EpochSchedule, MINIMUM_SLOTS_PER_EPOCH},
+    hash::Hash,
     instruction::Instruction,
     message::{v0::Message, VersionedMessage},
+    pubkey,
     pubkey::Pubkey,
+    reward_type::RewardType,
+    secp256k1_program,
+    signature::Signature,
     signer::Signer,
+    stake::{
+        self,
+        state::{Meta, StakeStateV2},
+    },
     system_instruction::transfer,
     transaction::VersionedTransaction,
 };
+use solana_transaction_status::{
+    EncodedTransaction, TransactionDetails, UiConfirmedBlock, UiMessage, UiTransaction,
+    UiTransactionEncoding,
+};
 use spl_stake_pool_interface::{
     update_stake_pool_balance_ix_with_program_id, StakePool, UpdateStakePoolBalanceKeys,
 };
@@ -37,6 +52,9 @@ const CUS_REQUIRED_FOR_SET_CU_LIMIT_IXS: u32 = 300;
 // https://github.com/solana-labs/solana/blob/27eff8408b7223bb3c4ab70523f8a8dca3ca6645/rpc-client-api/src/custom_error.rs#L17C1-L17C60
 const JSON_RPC_ERROR_CODE_SKIPPED_SLOT: i64 = -32007;
 
+// Samples are taken every 60s, this covers the last 12 hours
+const MAX_PERFORMANCE_SAMPLES: usize = 720;
+
 pub async fn with_auto_cb_ixs(
     rpc: &RpcClient,
     payer_pk: &Pubkey,
@@ -65,27 +83,121 @@ pub async fn with_auto_cb_ixs(
     ixs
 }
 
+/// [`with_auto_cb_ixs`] with a fixed compute unit limit instead of a simulated one,
+/// so no RPC call is made. The compute unit price is only set if `fee_limit_cb_lamports`
+/// isn't 0
+pub fn with_fixed_cb_ixs(
+    mut ixs: Vec<Instruction>,
+    compute_unit_limit: u32,
+    fee_limit_cb_lamports: u64,
+) -> Vec<Instruction> {
+    ixs.insert(
+        0,
+        ComputeBudgetInstruction::set_compute_unit_limit(compute_unit_limit),
+    );
+    if fee_limit_cb_lamports != 0 {
+        let microlamports_per_cu =
+            calc_compute_unit_price(compute_unit_limit, fee_limit_cb_lamports);
+        ixs.insert(
+            0,
+            ComputeBudgetInstruction::set_compute_unit_price(microlamports_per_cu),
+        );
+    }
+    ixs
+}
+
+/// Compiles and signs a v0 transaction against the given blockhash.
+/// The first signer is the fee payer.
+pub fn sign_tx_full(
+    ixs: &[Instruction],
+    luts: &[AddressLookupTableAccount],
+    hash: Hash,
+    signers: &mut [&dyn Signer],
+) -> Result<VersionedTransaction, String> {
+    let payer_pk = signers[0].pubkey();
+    signers.sort_by_key(|s| s.pubkey());
+    let message = Message::try_compile(&payer_pk, ixs, luts, hash)
+        .map_err(|e| format!("Error: Failed to compile transaction message: {}", e))?;
+    VersionedTransaction::try_new(VersionedMessage::V0(message), &SortedSigners(signers))
+        .map_err(|e| format!("Error: Failed to sign transaction: {}", e))
+}
+
+/// Returns the signature of the handled transaction
 pub async fn handle_tx_full(
     rpc: &RpcClient,
     send_mode: TxSendMode,
     ixs: &[Instruction],
     luts: &[AddressLookupTableAccount],
     signers: &mut [&dyn Signer],
-) {
-    let payer_pk = signers[0].pubkey();
-    signers.sort_by_key(|s| s.pubkey());
+) -> Signature {
     let RecentBlockhash { hash, .. } = rpc.get_confirmed_blockhash().await.unwrap();
-    rpc.handle_tx(
-        &VersionedTransaction::try_new(
-            VersionedMessage::V0(Message::try_compile(&payer_pk, ixs, luts, hash).unwrap()),
-            &SortedSigners(signers),
+    let tx = sign_tx_full(ixs, luts, hash, signers).unwrap();
+    rpc.handle_tx(&tx, send_mode, HandleTxArgs::cli_default())
+        .await
+        .unwrap();
+    tx.signatures[0]
+}
+
+/// Returns the blockhash currently stored in a durable nonce account
+pub async fn get_durable_nonce_blockhash(
+    rpc: &RpcClient,
+    nonce_pubkey: &Pubkey,
+) -> Result<Hash, String> {
+    let nonce_account =
+        nonce_utils::get_account_with_commitment(rpc, nonce_pubkey, rpc.commitment())
+            .await
+            .map_err(|e| format!("Error: Failed to fetch nonce account: {}", e))?;
+
+    nonce_utils::data_from_account(&nonce_account)
+        .map(|data| data.blockhash())
+        .map_err(|e| format!("Error: Invalid nonce account: {}", e))
+}
+
+pub fn encode_tx(tx: &VersionedTransaction) -> Result<String, String> {
+    bincode::serialize(tx)
+        .map(|bytes| bs58::encode(bytes).into_string())
+        .map_err(|e| format!("Error: Failed to serialize transaction: {}", e))
+}
+
+pub fn decode_tx(encoded: &str) -> Result<VersionedTransaction, String> {
+    let bytes = bs58::decode(encoded)
+        .into_vec()
+        .map_err(|e| format!("Error: Invalid base58 transaction: {}", e))?;
+    bincode::deserialize(&bytes)
+        .map_err(|e| format!("Error: Failed to deserialize transaction: {}", e))
+}
+
+/// The transaction `signature`, or `None` if it failed
+pub async fn get_successful_transaction(
+    rpc: &RpcClient,
+    signature: &Signature,
+) -> Result<Option<VersionedTransaction>, String> {
+    let tx = rpc
+        .get_transaction_with_config(
+            signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Base64),
+                commitment: Some(rpc.commitment()),
+                max_supported_transaction_version: Some(0),
+            },
         )
-        .unwrap(),
-        send_mode,
-        HandleTxArgs::cli_default(),
-    )
-    .await
-    .unwrap()
+        .await
+        .map_err(|e| format!("Error: Failed to fetch transaction {}: {}", signature, e))?;
+
+    if tx
+        .transaction
+        .meta
+        .as_ref()
+        .is_some_and(|meta| meta.err.is_some())
+    {
+        return Ok(None);
+    }
+
+    tx.transaction
+        .transaction
+        .decode()
+        .map(Some)
+        .ok_or_else(|| format!("Error: Failed to decode transaction {}", signature))
 }
 
 pub fn get_first_slot_of_epoch(epoch: u64, epoch_schedule: &EpochSchedule) -> u64 {
@@ -97,6 +209,27 @@ pub fn get_first_slot_of_epoch(epoch: u64, epoch_schedule: &EpochSchedule) -> u6
     }
 }
 
+/// How far back to look for a confirmed block when the requested slot was skipped
+const MAX_SKIPPED_SLOTS_LOOKBACK: u64 = 100;
+
+/// Block time of `slot`, or of the closest confirmed block before it if it was skipped
+pub async fn get_block_time_at_or_before(rpc: &RpcClient, slot: u64) -> Result<i64, String> {
+    let last_block = rpc
+        .get_blocks(slot.saturating_sub(MAX_SKIPPED_SLOTS_LOOKBACK), Some(slot))
+        .await
+        .map_err(|e| format!("Error: Failed to fetch blocks before slot {}: {}", slot, e))?
+        .last()
+        .copied()
+        .ok_or_else(|| format!("Error: No confirmed blocks found before slot {}", slot))?;
+
+    rpc.get_block_time(last_block).await.map_err(|e| {
+        format!(
+            "Error: Failed to fetch block time of slot {}: {}",
+            last_block, e
+        )
+    })
+}
+
 pub async fn get_leader_slots_for_identity(
     rpc: &RpcClient,
     epoch: u64,
@@ -136,11 +269,135 @@ pub async fn get_leader_slots_for_identity(
     Ok(leader_slots)
 }
 
-pub async fn get_total_block_rewards_for_slots(
+/// Average slot duration over the last few hours of performance samples
+pub async fn get_recent_slot_duration_secs(rpc: &RpcClient) -> Result<f64, String> {
+    let samples = rpc
+        .get_recent_performance_samples(Some(MAX_PERFORMANCE_SAMPLES))
+        .await
+        .map_err(|e| format!("Error: Failed to fetch recent performance samples: {}", e))?;
+
+    let (num_slots, num_secs) = samples.iter().fold((0u64, 0u64), |(slots, secs), sample| {
+        (
+            slots.saturating_add(sample.num_slots),
+            secs.saturating_add(sample.sample_period_secs.into()),
+        )
+    });
+
+    if num_slots == 0 {
+        return Err("Error: No recent performance samples available".to_string());
+    }
+
+    Ok(num_secs as f64 / num_slots as f64)
+}
+
+/// Precompiles charge the signature fee for every signature they verify
+const PRECOMPILE_PROGRAM_IDS: [Pubkey; 2] = [ed25519_program::ID, secp256k1_program::ID];
+
+/// Number of signatures `transaction` pays the base fee for. Returns `None` for
+/// `jsonParsed` transactions, which don't keep the raw instruction data.
+fn num_fee_signatures(transaction: &EncodedTransaction) -> Option<u64> {
+    // The first byte of a precompile instruction's data is its number of signatures
+    let precompile_signatures = |program_id: Option<Pubkey>, data: Option<u8>| match program_id {
+        Some(program_id) if PRECOMPILE_PROGRAM_IDS.contains(&program_id) => {
+            u64::from(data.unwrap_or_default())
+        }
+        _ => 0,
+    };
+
+    if let Some(transaction) = transaction.decode() {
+        let account_keys = transaction.message.static_account_keys();
+        let num_precompile_signatures: u64 = transaction
+            .message
+            .instructions()
+            .iter()
+            .map(|ix| {
+                precompile_signatures(
+                    account_keys.get(usize::from(ix.program_id_index)).copied(),
+                    ix.data.first().copied(),
+                )
+            })
+            .sum();
+        return Some(transaction.signatures.len() as u64 + num_precompile_signatures);
+    }
+
+    match transaction {
+        EncodedTransaction::Json(UiTransaction {
+            signatures,
+            message: UiMessage::Raw(message),
+        }) => {
+            let num_precompile_signatures: u64 = message
+                .instructions
+                .iter()
+                .map(|ix| {
+                    precompile_signatures(
+                        message
+                            .account_keys
+                            .get(usize::from(ix.program_id_index))
+                            .and_then(|key| key.parse().ok()),
+                        bs58::decode(&ix.data)
+                            .into_vec()
+                            .ok()
+                            .and_then(|data| data.first().copied()),
+                    )
+                })
+                .sum();
+            Some(signatures.len() as u64 + num_precompile_signatures)
+        }
+        _ => None,
+    }
+}
+
+/// Splits the fees paid by the block's transactions into base and priority fees.
+/// The burned portion is whatever was not paid out to the leader as the fee reward,
+/// so it is correct both before and after SIMD-0096.
+pub fn block_fee_breakdown(block: &UiConfirmedBlock) -> Result<FeeBreakdown, String> {
+    let transactions = block
+        .transactions
+        .as_ref()
+        .ok_or_else(|| "Error: Block has no transaction details".to_string())?;
+
+    let mut breakdown = FeeBreakdown::default();
+    for transaction in transactions {
+        let fee = transaction
+            .meta
+            .as_ref()
+            .ok_or_else(|| "Error: Transaction has no status meta".to_string())?
+            .fee;
+        let num_signatures = num_fee_signatures(&transaction.transaction).ok_or_else(|| {
+            "Error: Unsupported transaction encoding, use json or base64".to_string()
+        })?;
+        let base_fee = num_signatures
+            .saturating_mul(LAMPORTS_PER_SIGNATURE)
+            .min(fee);
+
+        breakdown.num_transactions += 1;
+        breakdown.base_fees = breakdown.base_fees.saturating_add(base_fee);
+        breakdown.priority_fees = breakdown.priority_fees.saturating_add(fee - base_fee);
+    }
+
+    let leader_fee_reward: u64 = block
+        .rewards
+        .iter()
+        .flatten()
+        .filter(|reward| reward.reward_type == Some(RewardType::Fee))
+        .map(|reward| reward.lamports.max(0) as u64)
+        .sum();
+    breakdown.burned_fees = breakdown
+        .base_fees
+        .saturating_add(breakdown.priority_fees)
+        .saturating_sub(leader_fee_reward);
+
+    Ok(breakdown)
+}
+
+/// Fetches the rewards of every slot in `slots`. With `detailed`, full transactions
+/// are fetched too to break down each block's fees.
+pub async fn get_block_rewards_for_slots(
     rpc: &RpcClient,
     slots: &[u64],
-) -> Result<u64, String> {
-    let mut total_rewards = 0u64;
+    detailed: bool,
+) -> Result<Vec<SlotReward>, String> {
+    let mut slot_rewards = Vec::with_capacity(slots.len());
 
     let pb = ProgressBar::new(u64::try_from(slots.len()).map_err(|e| e.to_string())?);
     pb.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len} slots ({eta})")
@@ -156,8 +413,11 @@ pub async fn get_total_block_rewards_for_slots(
                     rewards: Some(true),
                     commitment: Some(rpc.commitment()),
                     max_supported_transaction_version: Some(0),
-                    transaction_details: None,
-                    ..Default::default()
+                    transaction_details: Some(match detailed {
+                        true => TransactionDetails::Full,
+                        false => TransactionDetails::None,
+                    }),
+                    encoding: Some(UiTransactionEncoding::Base64),
                 },
             )
             .await
@@ -178,17 +438,178 @@ pub async fn get_total_block_rewards_for_slots(
             },
         };
 
-        if let Some(block) = block {
-            if let Some(rewards) = block.rewards {
-                let slot_rewards: u64 = rewards.iter().map(|reward| reward.lamports as u64).sum();
-                total_rewards += slot_rewards;
-            }
-        }
+        slot_rewards.push(match block {
+            Some(block) => SlotReward {
+                slot,
+                lamports: block
+                    .rewards
+                    .iter()
+                    .flatten()
+                    .map(|reward| reward.lamports as u64)
+                    .sum(),
+                skipped: false,
+                fee_breakdown: match detailed {
+                    true => Some(
+                        block_fee_breakdown(&block)
+                            .map_err(|e| format!("{} (slot {})", e, slot))?,
+                    ),
+                    false => None,
+                },
+            },
+            None => SlotReward {
+                slot,
+                lamports: 0,
+                skipped: true,
+                fee_breakdown: None,
+            },
+        });
 
         pb.inc(1);
     }
 
-    Ok(total_rewards)
+    Ok(slot_rewards)
+}
+
+pub async fn get_total_block_rewards_for_slots(
+    rpc: &RpcClient,
+    slots: &[u64],
+) -> Result<u64, String> {
+    Ok(get_block_rewards_for_slots(rpc, slots, false)
+        .await?
+        .iter()
+        .map(|slot_reward| slot_reward.lamports)
+        .sum())
+}
+
+/// Stake pool programs whose pools we are willing to transfer rewards to
+pub const KNOWN_STAKE_POOL_PROGRAM_IDS: [Pubkey; 3] = [
+    // SPL
+    pubkey!("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy"),
+    // Sanctum SPL
+    pubkey!("SP12tWFxD9oJsVWNavTTBZvMbA6gkAmxtVgxdqvyvhY"),
+    // Sanctum SPL multi-validator
+    pubkey!("SPMBzsVUuoHA4Jm6KunbsotaahvVikZs1JyTW6iJvbn"),
+];
+
+/// Deserializes a stake pool account owned by a known stake pool program
+pub fn stake_pool_from_account(stake_pool_account: &Account) -> Result<StakePool, String> {
+    if !KNOWN_STAKE_POOL_PROGRAM_IDS.contains(&stake_pool_account.owner) {
+        return Err(format!(
+            "Error: Stake pool account is owned by {}, which is not a known stake pool program",
+            stake_pool_account.owner
+        ));
+    }
+
+    deserialize_stake_pool_checked(stake_pool_account.data())
+        .map_err(|e| format!("Error: Failed to deserialize stake pool: {}", e))
+}
+
+/// Fetches a stake pool owned by a known stake pool program, without checking
+/// that it is up to date
+pub async fn get_stake_pool(
+    rpc: &RpcClient,
+    stake_pool_pubkey: &Pubkey,
+) -> Result<StakePool, String> {
+    let stake_pool_account = rpc
+        .get_account(stake_pool_pubkey)
+        .await
+        .map_err(|e| format!("Error: Failed to fetch stake pool account: {}", e))?;
+
+    stake_pool_from_account(&stake_pool_account)
+}
+
+/// Checks that an already deserialized stake pool of `stake_pool_program_id` has
+/// been updated for `current_epoch` and that its reserve is an initialized stake
+/// account controlled by the pool's withdraw authority.
+pub fn validate_stake_pool_state(
+    stake_pool_pubkey: &Pubkey,
+    stake_pool_program_id: &Pubkey,
+    stake_pool: &StakePool,
+    reserve_account: &Account,
+    current_epoch: u64,
+) -> Result<(), String> {
+    if stake_pool.last_update_epoch != current_epoch {
+        return Err(format!(
+            "Error: Stake pool was last updated in epoch {} but the current epoch is {}. Please update the stake pool first",
+            stake_pool.last_update_epoch, current_epoch
+        ));
+    }
+
+    if reserve_account.owner != stake::program::ID {
+        return Err(format!(
+            "Error: Reserve account {} is not a stake account",
+            stake_pool.reserve_stake
+        ));
+    }
+
+    let (withdraw_authority, _bump) = FindWithdrawAuthority {
+        pool: *stake_pool_pubkey,
+    }
+    .run_for_prog(stake_pool_program_id);
+
+    match bincode::deserialize::<StakeStateV2>(reserve_account.data()) {
+        Ok(StakeStateV2::Initialized(Meta { authorized, .. }))
+            if authorized.withdrawer == withdraw_authority =>
+        {
+            Ok(())
+        }
+        _ => Err(format!(
+            "Error: Reserve account {} is not an initialized stake account owned by the stake pool",
+            stake_pool.reserve_stake
+        )),
+    }
+}
+
+/// Checks that the stake pool is owned by a known stake pool program, has been
+/// updated for `current_epoch` and that its reserve is an initialized stake account
+/// controlled by the pool's withdraw authority.
+pub fn validate_stake_pool_accounts(
+    stake_pool_pubkey: &Pubkey,
+    stake_pool_account: &Account,
+    reserve_account: &Account,
+    current_epoch: u64,
+) -> Result<StakePool, String> {
+    let stake_pool = stake_pool_from_account(stake_pool_account)?;
+
+    validate_stake_pool_state(
+        stake_pool_pubkey,
+        &stake_pool_account.owner,
+        &stake_pool,
+        reserve_account,
+        current_epoch,
+    )?;
+
+    Ok(stake_pool)
+}
+
+/// Fetches and validates the stake pool, returning it along with the fetched
+/// account so instructions can be built from the same state that was validated
+pub async fn validate_stake_pool(
+    rpc: &RpcClient,
+    stake_pool_pubkey: &Pubkey,
+    current_epoch: u64,
+) -> Result<(StakePool, Account), String> {
+    let stake_pool_account = rpc
+        .get_account(stake_pool_pubkey)
+        .await
+        .map_err(|e| format!("Error: Failed to fetch stake pool account: {}", e))?;
+
+    let stake_pool = stake_pool_from_account(&stake_pool_account)?;
+
+    let reserve_account = rpc
+        .get_account(&stake_pool.reserve_stake)
+        .await
+        .map_err(|e| format!("Error: Failed to fetch reserve stake account: {}", e))?;
+
+    validate_stake_pool_state(
+        stake_pool_pubkey,
+        &stake_pool_account.owner,
+        &stake_pool,
+        &reserve_account,
+        current_epoch,
+    )?;
+
+    Ok((stake_pool, stake_pool_account))
 }
 
 pub async fn transfer_to_reserve_and_update_stake_pool_balance_ixs(
@@ -204,11 +625,52 @@ pub async fn transfer_to_reserve_and_update_stake_pool_balance_ixs(
         .await
         .map_err(|e| format!("Error: Failed to fetch stake pool account: {}", e))?;
 
-    let stake_pool_program_id = stake_pool_account.owner;
+    transfer_to_reserve_and_update_stake_pool_balance_ixs_for_account(
+        &stake_pool_account,
+        payer_pubkey,
+        identity_pubkey,
+        stake_pool_pubkey,
+        lst_rewards,
+        epoch,
+    )
+}
 
-    let stake_pool = StakePool::deserialize(&mut stake_pool_account.data.as_slice())
+/// Builds the instructions of [`transfer_to_reserve_and_update_stake_pool_balance_ixs`]
+/// from an already fetched stake pool account
+pub fn transfer_to_reserve_and_update_stake_pool_balance_ixs_for_account(
+    stake_pool_account: &Account,
+    payer_pubkey: &Pubkey,
+    identity_pubkey: &Pubkey,
+    stake_pool_pubkey: &Pubkey,
+    lst_rewards: u64,
+    epoch: u64,
+) -> Result<Vec<Instruction>, String> {
+    let stake_pool = deserialize_stake_pool_checked(stake_pool_account.data())
         .map_err(|e| format!("Error: Failed to deserialize stake pool: {}", e))?;
 
+    transfer_to_reserve_and_update_stake_pool_balance_ixs_for_pool(
+        &stake_pool_account.owner,
+        &stake_pool,
+        payer_pubkey,
+        identity_pubkey,
+        stake_pool_pubkey,
+        lst_rewards,
+        epoch,
+    )
+}
+
+/// Builds the instructions of [`transfer_to_reserve_and_update_stake_pool_balance_ixs`]
+/// from an already deserialized stake pool of `stake_pool_program_id`, e.g. the one
+/// returned by [`validate_stake_pool`]
+pub fn transfer_to_reserve_and_update_stake_pool_balance_ixs_for_pool(
+    stake_pool_program_id: &Pubkey,
+    stake_pool: &StakePool,
+    payer_pubkey: &Pubkey,
+    identity_pubkey: &Pubkey,
+    stake_pool_pubkey: &Pubkey,
+    lst_rewards: u64,
+    epoch: u64,
+) -> Result<Vec<Instruction>, String> {
     let StakePool {
         validator_list,
         reserve_stake,
@@ -216,20 +678,19 @@ pub async fn transfer_to_reserve_and_update_stake_pool_balance_ixs(
         manager_fee_account,
         token_program,
         ..
-    } = deserialize_stake_pool_checked(stake_pool_account.data())
-        .map_err(|e| format!("Error: Failed to deserialize stake pool: {}", e))?;
+    } = *stake_pool;
 
     let (withdraw_authority, _bump) = FindWithdrawAuthority {
         pool: *stake_pool_pubkey,
     }
-    .run_for_prog(&stake_pool_program_id);
+    .run_for_prog(stake_pool_program_id);
 
     let final_ixs = vec![
         // Transfer rewards to Stake Pool reserve
-        transfer(payer_pubkey, &stake_pool.reserve_stake, lst_rewards),
+        transfer(payer_pubkey, &reserve_stake, lst_rewards),
         // Update stake pool balance
         update_stake_pool_balance_ix_with_program_id(
-            stake_pool_program_id,
+            *stake_pool_program_id,
             UpdateStakePoolBalanceKeys {
                 stake_pool: *stake_pool_pubkey,
                 withdraw_authority,
@@ -240,14 +701,15 @@ pub async fn transfer_to_reserve_and_update_stake_pool_balance_ixs(
                 token_program,
             },
         )
-        .unwrap(),
-        // Memo ix for easy indexing
-        spl_memo::build_memo(
+        .map_err(|e| {
             format!(
-                "sbr-{epoch}-{}-{}-{lst_rewards}",
-                identity_pubkey, stake_pool_pubkey
+                "Error: Failed to build UpdateStakePoolBalance instruction: {}",
+                e
             )
-            .as_ref(),
+        })?,
+        // Memo ix for easy indexing
+        spl_memo::build_memo(
+            distribution_memo(epoch, identity_pubkey, stake_pool_pubkey, lst_rewards).as_bytes(),
             &[payer_pubkey],
         ),
     ];
@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use spl_stake_pool_interface::{Fee, StakePool};
+
+const SECONDS_PER_YEAR: f64 = 365.25 * 24.0 * 60.0 * 60.0;
+
+/// The parts of a stake pool's state that determine its LST/SOL exchange rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolBalance {
+    pub total_lamports: u64,
+    pub pool_token_supply: u64,
+}
+
+impl PoolBalance {
+    pub fn from_stake_pool(stake_pool: &StakePool) -> Self {
+        Self {
+            total_lamports: stake_pool.total_lamports,
+            pool_token_supply: stake_pool.pool_token_supply,
+        }
+    }
+
+    /// SOL value of one whole LST with `decimals` decimals
+    pub fn sol_value(&self, decimals: u8) -> f64 {
+        if self.pool_token_supply == 0 {
+            return 1.0;
+        }
+        let sol = self.total_lamports as f64 / 1e9;
+        let lst = self.pool_token_supply as f64 / 10f64.powi(decimals.into());
+        sol / lst
+    }
+
+    /// Pool balance after `reward_lamports` are added to the reserve and
+    /// `UpdateStakePoolBalance` is run, which mints the manager's epoch fee on
+    /// the increase in total lamports.
+    ///
+    /// Mirrors `StakePool::calc_epoch_fee_amount` of the SPL stake pool program.
+    pub fn after_reward(&self, reward_lamports: u64, epoch_fee: &Fee) -> Option<Self> {
+        let total_lamports = self.total_lamports.checked_add(reward_lamports)?;
+
+        let fee_pool_tokens = if reward_lamports == 0 {
+            0
+        } else if self.pool_token_supply == 0 {
+            // If the pool has no tokens, all rewards go to the manager
+            reward_lamports
+        } else if epoch_fee.denominator == 0 {
+            0
+        } else {
+            let fee_lamports = u128::from(reward_lamports)
+                .checked_mul(epoch_fee.numerator.into())?
+                .checked_div(epoch_fee.denominator.into())?;
+            let total_lamports = u128::from(total_lamports);
+            if total_lamports == fee_lamports {
+                reward_lamports
+            } else {
+                u64::try_from(
+                    u128::from(self.pool_token_supply)
+                        .checked_mul(fee_lamports)?
+                        .checked_div(total_lamports.checked_sub(fee_lamports)?)?,
+                )
+                .ok()?
+            }
+        };
+
+        Some(Self {
+            total_lamports,
+            pool_token_supply: self.pool_token_supply.checked_add(fee_pool_tokens)?,
+        })
+    }
+}
+
+/// Relative increase of the LST/SOL exchange rate going from `pre` to `post`, in bps
+pub fn exchange_rate_increase_bps(pre: &PoolBalance, post: &PoolBalance) -> f64 {
+    // decimals cancel out in the ratio
+    let pre_rate = pre.sol_value(9);
+    let post_rate = post.sol_value(9);
+    (post_rate / pre_rate - 1.0) * 10_000.0
+}
+
+/// Annualized, compounded APY contribution in percent of a per epoch yield of
+/// `per_epoch_bps`, assuming every epoch lasts `epoch_duration_secs`
+pub fn annualized_apy_pct(per_epoch_bps: f64, epoch_duration_secs: f64) -> f64 {
+    if epoch_duration_secs <= 0.0 {
+        return 0.0;
+    }
+    let epochs_per_year = SECONDS_PER_YEAR / epoch_duration_secs;
+    ((1.0 + per_epoch_bps / 10_000.0).powf(epochs_per_year) - 1.0) * 100.0
+}
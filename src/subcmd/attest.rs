@@ -0,0 +1,140 @@
+use crate::{
+    get_attestation_file_path, get_rewards_file_path, input_with_validation, read_rewards_file,
+    sign_attestation, subcmd::Subcmd, write_json_file,
+};
+use clap::{command, Args};
+use colored::Colorize;
+use sanctum_solana_cli_utils::{parse_named_signer, ParseNamedSigner};
+use solana_sdk::signer::Signer;
+
+#[derive(Args, Debug)]
+#[command(
+    long_about = "Sign the rewards calculated for an epoch with the validator identity, so they can be verified by others with the `verify` command"
+)]
+pub struct AttestArgs {
+    #[arg(long, help = "Path to the validator identity keypair")]
+    pub identity_keypair: String,
+
+    #[arg(long, help = "The epoch of the rewards to attest")]
+    pub epoch: Option<u64>,
+
+    #[arg(long, help = "Path to write the attestation to")]
+    pub out_file: Option<String>,
+}
+
+impl AttestArgs {
+    pub async fn run(args: crate::Args) {
+        let Self {
+            identity_keypair,
+            epoch,
+            out_file,
+        } = match args.subcmd {
+            Subcmd::Attest(a) => a,
+            _ => unreachable!(),
+        };
+
+        let identity_keypair = match parse_named_signer(ParseNamedSigner {
+            name: "identity",
+            arg: &identity_keypair,
+        }) {
+            Ok(keypair) => keypair,
+            Err(_) => {
+                println!("{}", "Error: Invalid identity keypair".red());
+                return;
+            }
+        };
+
+        let identity_pubkey = identity_keypair.pubkey();
+
+        let epoch = match input_with_validation(
+            "Enter the epoch of the rewards to attest:",
+            "Epoch",
+            None,
+            epoch.map(|e| e.to_string()),
+            |input| {
+                input
+                    .parse::<u64>()
+                    .map_err(|_| "Error: Please enter a valid epoch".to_string())
+            },
+        ) {
+            Ok(e) => e,
+            Err(_) => {
+                println!("{}", "Error: Invalid epoch".red());
+                return;
+            }
+        };
+
+        println!("{}", "=".repeat(80));
+
+        let rewards_file_path = match get_rewards_file_path(&identity_pubkey, epoch) {
+            Ok(path) => path,
+            Err(err) => {
+                println!("{}", format!("Error: {}", err).red());
+                return;
+            }
+        };
+
+        let rewards = match read_rewards_file(&rewards_file_path) {
+            Ok(Some(rewards)) => rewards,
+            Ok(None) => {
+                println!(
+                    "{}",
+                    format!("Failed to find rewards at {}", rewards_file_path).blue()
+                );
+                println!(
+                    "{}",
+                    "Please run the calculate command first to generate the rewards file."
+                        .blue()
+                        .bold()
+                );
+
+                println!("{}", "=".repeat(80));
+                return;
+            }
+            Err(err) => {
+                println!("{}", format!("Error: {}", err).red());
+                return;
+            }
+        };
+
+        let attestation = match sign_attestation(identity_keypair.as_ref(), epoch, &rewards) {
+            Ok(attestation) => attestation,
+            Err(err) => {
+                println!("{}", err.red());
+                return;
+            }
+        };
+
+        let out_file =
+            match out_file.map_or_else(|| get_attestation_file_path(&identity_pubkey, epoch), Ok) {
+                Ok(path) => path,
+                Err(err) => {
+                    println!("{}", format!("Error: {}", err).red());
+                    return;
+                }
+            };
+
+        if let Err(err) = write_json_file(&out_file, &attestation) {
+            println!("{}", format!("Error: {}", err).red());
+            return;
+        }
+
+        println!(
+            "{}{}",
+            "Message: ".blue().bold(),
+            attestation.message.bold()
+        );
+        println!(
+            "{}{}",
+            "Signature: ".blue().bold(),
+            attestation.signature.bold()
+        );
+        println!(
+            "{}",
+            format!("✓ Saved attestation to {}", out_file)
+                .green()
+                .bold()
+        );
+        println!("{}", "=".repeat(80));
+    }
+}
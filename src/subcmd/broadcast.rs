@@ -0,0 +1,293 @@
+use crate::{
+    decode_tx, find_distribution, get_stake_pool, input_with_validation, print_transfer_summary,
+    read_json_file, record_in_ledger, subcmd::Subcmd, validate_pubkey, validate_rpc_url,
+    verify_signed_transfer, SignedTransferFile, TransferRecord, TransferStatus,
+    DEFAULT_MAX_DISTRIBUTION_SIGNATURES, SOLANA_PUBLIC_RPC,
+};
+use clap::{command, Args};
+use colored::Colorize;
+use inquire::Confirm;
+use sanctum_solana_cli_utils::{HandleTxArgs, TxSendMode, TxSendingNonblockingRpcClient};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, hash::Hash};
+use spinners::{Spinner, Spinners};
+use std::str::FromStr;
+
+#[derive(Args, Debug)]
+#[command(
+    long_about = "Broadcast a transaction previously signed with `transfer --sign-only` and wait for confirmation"
+)]
+pub struct BroadcastArgs {
+    #[arg(help = "Path to the signed transaction file written by `transfer --sign-only`")]
+    pub file: String,
+
+    #[arg(
+        long,
+        help = "Number of the stake pool's most recent transactions to search for an earlier distribution",
+        default_value_t = DEFAULT_MAX_DISTRIBUTION_SIGNATURES
+    )]
+    pub max_signatures: usize,
+}
+
+impl BroadcastArgs {
+    pub async fn run(args: crate::Args) {
+        let Self {
+            file,
+            max_signatures,
+        } = match args.subcmd {
+            Subcmd::Broadcast(a) => a,
+            _ => unreachable!(),
+        };
+
+        let signed_transfer: SignedTransferFile = match read_json_file(&file) {
+            Ok(signed_transfer) => signed_transfer,
+            Err(err) => {
+                println!("{}", format!("Error: {}", err).red());
+                return;
+            }
+        };
+
+        let transfer =
+            match TransferRecord::from_signed_transfer(&signed_transfer, TransferStatus::Sent) {
+                Ok(transfer) => transfer,
+                Err(err) => {
+                    println!("{}", err.red());
+                    return;
+                }
+            };
+
+        let transaction = match decode_tx(&signed_transfer.transaction) {
+            Ok(tx) => tx,
+            Err(err) => {
+                println!("{}", err.red());
+                return;
+            }
+        };
+
+        let payer_pubkey = match validate_pubkey(&signed_transfer.payer_pubkey) {
+            Ok(pubkey) => pubkey,
+            Err(_) => {
+                println!(
+                    "{}",
+                    "Error: Invalid payer pubkey in signed transfer file".red()
+                );
+                return;
+            }
+        };
+
+        let identity_pubkey = match validate_pubkey(&signed_transfer.identity_pubkey) {
+            Ok(pubkey) => pubkey,
+            Err(_) => {
+                println!(
+                    "{}",
+                    "Error: Invalid identity pubkey in signed transfer file".red()
+                );
+                return;
+            }
+        };
+
+        let stake_pool_pubkey = match validate_pubkey(&signed_transfer.stake_pool_pubkey) {
+            Ok(pubkey) => pubkey,
+            Err(_) => {
+                println!(
+                    "{}",
+                    "Error: Invalid stake pool pubkey in signed transfer file".red()
+                );
+                return;
+            }
+        };
+
+        let rpc_url = match input_with_validation(
+            "Enter the RPC URL:",
+            "RPC URL",
+            Some(SOLANA_PUBLIC_RPC.to_string()),
+            args.rpc_url,
+            validate_rpc_url,
+        ) {
+            Ok(url) => url,
+            Err(_) => {
+                println!("{}", "Error: Invalid RPC URL".red());
+                return;
+            }
+        };
+
+        let rpc = RpcClient::new_with_commitment(
+            rpc_url,
+            args.commitment.unwrap_or(CommitmentConfig::confirmed()),
+        );
+
+        let stake_pool = match get_stake_pool(&rpc, &stake_pool_pubkey).await {
+            Ok(stake_pool) => stake_pool,
+            Err(err) => {
+                println!("{}", err.red());
+                return;
+            }
+        };
+
+        // The summary is what gets reviewed, so the tx must do exactly what it says
+        if let Err(err) =
+            verify_signed_transfer(&transaction, &signed_transfer, &stake_pool.reserve_stake)
+        {
+            println!("{}", err.red());
+            return;
+        }
+
+        // `transfer --sign-only` signs plans offline, so this is the first chance to
+        // catch a distribution sent since the plan was made
+        let epoch = signed_transfer.summary.epoch;
+        let epoch_schedule = match rpc.get_epoch_schedule().await {
+            Ok(epoch_schedule) => epoch_schedule,
+            Err(_) => {
+                println!("{}", "Error: Failed to fetch data from RPC".red());
+                return;
+            }
+        };
+
+        let mut sp = Spinner::new(
+            Spinners::Dots,
+            "Checking for an earlier distribution...".to_string(),
+        );
+
+        match find_distribution(
+            &rpc,
+            epoch,
+            &identity_pubkey,
+            &stake_pool_pubkey,
+            epoch_schedule.get_first_slot_in_epoch(epoch + 1),
+            max_signatures,
+        )
+        .await
+        {
+            Ok(None) => {
+                sp.stop_with_message("✓ Not distributed yet".green().bold().to_string());
+            }
+            Ok(Some(signature)) => {
+                sp.stop_with_message(
+                    format!(
+                        "Error: The rewards of epoch {} were already distributed to this stake pool in transaction {}",
+                        epoch, signature
+                    )
+                    .red()
+                    .to_string(),
+                );
+                return;
+            }
+            Err(err) => {
+                sp.stop_with_message(err.red().to_string());
+                return;
+            }
+        }
+
+        let SignedTransferFile {
+            identity_pubkey,
+            stake_pool_pubkey,
+            mut summary,
+            nonce_account,
+            blockhash,
+            ..
+        } = signed_transfer;
+
+        // Only plain blockhash txs expire, durable nonce txs stay valid until the nonce is advanced
+        if nonce_account.is_none() {
+            let hash = match Hash::from_str(&blockhash) {
+                Ok(hash) => hash,
+                Err(_) => {
+                    println!(
+                        "{}",
+                        "Error: Invalid blockhash in signed transfer file".red()
+                    );
+                    return;
+                }
+            };
+
+            match rpc.is_blockhash_valid(&hash, rpc.commitment()).await {
+                Ok(true) => (),
+                Ok(false) => {
+                    println!(
+                        "{}",
+                        "Error: The transaction's blockhash has expired. Please sign it again, preferably with --nonce-account."
+                            .red()
+                    );
+                    return;
+                }
+                Err(_) => {
+                    println!("{}", "Error: Failed to fetch data from RPC".red());
+                    return;
+                }
+            }
+        }
+
+        let lst_label = format!("{} ({})", summary.lst_info.name, summary.lst_info.symbol);
+
+        // Show the payer's current balance rather than the one at signing time
+        match rpc.get_balance(&payer_pubkey).await {
+            Ok(balance) => summary.payer_balance = balance,
+            Err(_) => {
+                println!("{}", "Error: Failed to fetch data from RPC".red());
+                return;
+            }
+        }
+
+        println!("{}", "=".repeat(80));
+
+        println!("{}{}", "Identity: ".blue().bold(), identity_pubkey.bold());
+        println!(
+            "{}{}",
+            "Stake pool: ".blue().bold(),
+            format!("{} - {}", stake_pool_pubkey, lst_label).bold()
+        );
+        println!(
+            "{}{}",
+            "Payer: ".blue().bold(),
+            payer_pubkey.to_string().bold()
+        );
+        if let Some(nonce_account) = nonce_account.as_ref() {
+            println!(
+                "{}{}",
+                "Nonce account: ".blue().bold(),
+                nonce_account.bold()
+            );
+        }
+
+        print_transfer_summary(summary);
+
+        println!("{}", "=".repeat(80));
+
+        let confirm_message = format!(
+            "Do you wish to broadcast this transfer to {}?",
+            lst_label.magenta()
+        );
+
+        let ans = Confirm::new(&confirm_message.blue().bold())
+            .with_default(true)
+            .prompt();
+
+        match ans {
+            Ok(false) => {
+                return;
+            }
+            Err(_) => {
+                println!("Error: Something went wrong.");
+                return;
+            }
+            _ => (),
+        }
+
+        println!("{}", "=".repeat(80));
+
+        if let Err(err) = rpc
+            .handle_tx(&transaction, args.send_mode, HandleTxArgs::cli_default())
+            .await
+        {
+            println!(
+                "{}",
+                format!("Error: Failed to send transaction: {}", err).red()
+            );
+            return;
+        }
+
+        if args.send_mode == TxSendMode::SendActual {
+            record_in_ledger(|ledger| ledger.record_transfer(&transfer));
+        }
+    }
+}
@@ -1,17 +1,115 @@
 use crate::{
-    get_leader_slots_for_identity, get_rewards_file_path, get_total_block_rewards_for_slots,
-    input_string, input_with_validation, subcmd::Subcmd, validate_epoch, validate_rpc_url,
-    SOLANA_PUBLIC_RPC,
+    calculate_epoch_rewards, fetch_mev_rewards, get_rewards_file_path, input_string,
+    input_with_validation, parse_dune_param, print_fee_breakdown, read_rewards_file,
+    record_in_ledger, subcmd::Subcmd, validate_epoch, validate_rpc_url, write_json_file,
+    CalculateOpts, DuneClient, DuneQuery, DuneRewardSource, MevSource, RewardSourceKind,
+    RewardSourceOpts, DEFAULT_DUNE_EPOCH_COLUMN, DEFAULT_DUNE_LAST_BLOCK_TIME_COLUMN,
+    DEFAULT_DUNE_LAST_SLOT_COLUMN, DEFAULT_DUNE_REWARDS_COLUMN, DEFAULT_DUNE_TIMEOUT_SECS,
+    DUNE_API_BASE_URL, DUNE_QUERY_ID, SOLANA_PUBLIC_RPC,
 };
 use clap::{command, Args};
 use colored::Colorize;
 use inquire::Confirm;
 use sanctum_solana_cli_utils::TokenAmt;
-use serde_json::{json, Value};
 use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
-use spinners::{Spinner, Spinners};
-use std::{fs::File, path::Path, str::FromStr};
+use solana_sdk::{
+    commitment_config::CommitmentConfig, epoch_schedule::EpochSchedule, pubkey::Pubkey,
+};
+use std::{ops::RangeInclusive, path::PathBuf, str::FromStr};
+
+/// Options of the Dune source, shared by `calculate` and `calculate-with-dune`
+#[derive(Args, Debug)]
+#[command(next_help_heading = "Dune options")]
+pub struct DuneOpts {
+    #[arg(
+        long,
+        help = "Calculate every epoch from --epoch to this epoch (inclusive) with a single query"
+    )]
+    pub end_epoch: Option<u64>,
+
+    #[arg(long, help = "Dune API key")]
+    pub dune_api_key: Option<String>,
+
+    #[arg(
+        long,
+        help = "Base URL of the Dune API",
+        default_value = DUNE_API_BASE_URL
+    )]
+    pub dune_api_url: String,
+
+    #[arg(
+        long,
+        help = "ID of the Dune query to run. The query must return an epoch and a total block rewards (in lamports) column",
+        default_value_t = DUNE_QUERY_ID
+    )]
+    pub dune_query_id: u32,
+
+    #[arg(
+        long,
+        help = "Extra key=value parameter to pass to the Dune query, on top of epoch and identity_pubkey. Can be repeated",
+        value_parser = parse_dune_param
+    )]
+    pub dune_param: Vec<(String, String)>,
+
+    #[arg(
+        long,
+        help = "Name of the epoch column in the Dune query results",
+        default_value = DEFAULT_DUNE_EPOCH_COLUMN
+    )]
+    pub dune_epoch_column: String,
+
+    #[arg(
+        long,
+        help = "Name of the total block rewards column in the Dune query results",
+        default_value = DEFAULT_DUNE_REWARDS_COLUMN
+    )]
+    pub dune_rewards_column: String,
+
+    #[arg(
+        long,
+        help = "Name of the optional column with the latest slot Dune had indexed, used to check that the whole epoch is indexed",
+        default_value = DEFAULT_DUNE_LAST_SLOT_COLUMN
+    )]
+    pub dune_last_slot_column: String,
+
+    #[arg(
+        long,
+        help = "Name of the optional column with the block time of the latest slot Dune had indexed",
+        default_value = DEFAULT_DUNE_LAST_BLOCK_TIME_COLUMN
+    )]
+    pub dune_last_block_time_column: String,
+
+    #[arg(
+        long,
+        help = "Save Dune results even if Dune had not indexed the whole epoch yet"
+    )]
+    pub allow_stale: bool,
+
+    #[arg(
+        long,
+        help = "Save Dune results even if the execution only partially completed, so its rows may be truncated"
+    )]
+    pub allow_partial: bool,
+
+    #[arg(
+        long,
+        help = "Timeout in seconds for waiting for Dune query results",
+        default_value_t = DEFAULT_DUNE_TIMEOUT_SECS
+    )]
+    pub timeout: u64,
+
+    #[arg(
+        long,
+        help = "Reuse the Dune query's latest results if they are at most this many seconds old, instead of executing it again"
+    )]
+    pub dune_max_age: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Wait for an existing Dune execution, e.g. one that timed out, instead of executing the query again"
+    )]
+    pub execution_id: Option<String>,
+}
 
 #[derive(Args, Debug)]
 #[command(
@@ -20,8 +118,206 @@ use std::{fs::File, path::Path, str::FromStr};
 pub struct CalculateArgs {
     #[arg(long, help = "The identity pubkey of your validator")]
     pub identity_pubkey: Option<String>,
+
     #[arg(long, help = "The epoch to calculate rewards for")]
     pub epoch: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Where to fetch block rewards data from",
+        default_value = "rpc",
+        value_enum
+    )]
+    pub source: RewardSourceKind,
+
+    #[command(flatten)]
+    pub dune: DuneOpts,
+
+    #[arg(
+        long,
+        help = "Directory of exported blocks in the getBlock JSON format. Only used with --source ledger"
+    )]
+    pub ledger_dir: Option<String>,
+
+    #[arg(
+        long,
+        help = "Fetch every transaction to break down each block's fees into base, priority and burned fees. Not supported with --source dune"
+    )]
+    pub detailed: bool,
+
+    #[arg(
+        long,
+        help = "Also calculate the validator's Jito MEV tips from its tip distribution account"
+    )]
+    pub mev: bool,
+
+    #[arg(
+        long,
+        help = "Read the validator's MEV tips from a JSON file of the form {\"epoch\": 746, \"mev_rewards\": <lamports>} instead"
+    )]
+    pub mev_file: Option<String>,
+
+    #[arg(
+        long,
+        help = "The vote account of your validator, used with --mev. Looked up from the identity if not provided"
+    )]
+    pub vote_pubkey: Option<String>,
+
+    #[arg(
+        long,
+        help = "Skip the confirmation prompt before fetching block rewards"
+    )]
+    pub yes: bool,
+}
+
+/// Saves a rewards file for every epoch in `epochs` that doesn't have one yet,
+/// using a single Dune execution
+async fn calculate_epoch_range(
+    source: &DuneRewardSource<'_>,
+    identity_pubkey: &Pubkey,
+    epochs: RangeInclusive<u64>,
+    yes: bool,
+) {
+    println!("{}", "=".repeat(80));
+
+    let mut missing_epochs = Vec::new();
+    for epoch in epochs {
+        let rewards_file_path = match get_rewards_file_path(identity_pubkey, epoch) {
+            Ok(path) => path,
+            Err(err) => {
+                println!("{}", format!("Error: {}", err).red());
+                return;
+            }
+        };
+
+        match read_rewards_file(&rewards_file_path) {
+            Ok(Some(_)) => println!(
+                "{}",
+                format!(
+                    "Rewards file for epoch {} found at {}, skipping",
+                    epoch, rewards_file_path
+                )
+                .blue()
+            ),
+            Ok(None) => missing_epochs.push((epoch, rewards_file_path)),
+            Err(err) => {
+                println!("{}", format!("Error: {}", err).red());
+                return;
+            }
+        }
+    }
+
+    if missing_epochs.is_empty() {
+        println!(
+            "{}",
+            "✓ Every epoch already has a rewards file".green().bold()
+        );
+        println!("{}", "=".repeat(80));
+        return;
+    }
+
+    println!(
+        "{}",
+        "⚠️ Please note that the data on Dune is usually lagging by 2-3 hours"
+            .bold()
+            .yellow()
+    );
+    println!("{}", "=".repeat(80));
+
+    if !yes {
+        let ans = Confirm::new(
+            &format!(
+                "Do you wish to fetch block rewards for epochs {} with a single Dune query?",
+                missing_epochs
+                    .iter()
+                    .map(|(epoch, _)| epoch.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+            .blue()
+            .bold(),
+        )
+        .with_default(true)
+        .prompt();
+
+        match ans {
+            Ok(false) => {
+                return;
+            }
+            Err(_) => {
+                println!("Error: Something went wrong.");
+                return;
+            }
+            _ => (),
+        }
+    }
+
+    println!("{}", "=".repeat(80));
+
+    let epochs: Vec<u64> = missing_epochs.iter().map(|(epoch, _)| *epoch).collect();
+    let epochs_rewards = match source.fetch_epochs_rewards(identity_pubkey, &epochs).await {
+        Ok(rewards) => rewards,
+        Err(err) => {
+            println!("{}", err.red());
+            return;
+        }
+    };
+
+    for (epoch, rewards_file_path) in missing_epochs {
+        let Some(rewards) = epochs_rewards.get(&epoch) else {
+            println!(
+                "{}",
+                format!("⚠️ No rewards data found for epoch {}", epoch)
+                    .yellow()
+                    .bold()
+            );
+            continue;
+        };
+
+        if let Err(err) = write_json_file(&rewards_file_path, rewards) {
+            println!("{}", format!("Error: {}", err).red());
+            return;
+        }
+        record_in_ledger(|ledger| ledger.record_calculation(identity_pubkey, epoch, rewards));
+
+        println!(
+            "{}",
+            format!(
+                "✓ Total block rewards for {} in epoch {} are {} SOL",
+                &identity_pubkey.to_string()[..6],
+                epoch,
+                TokenAmt {
+                    amt: rewards.total_block_rewards,
+                    decimals: 9
+                }
+            )
+            .green()
+            .bold()
+        );
+        println!(
+            "{}",
+            format!("Saved rewards to {}", rewards_file_path).blue()
+        );
+    }
+
+    println!("{}", "=".repeat(80));
+}
+
+fn print_mev_rewards(identity_pubkey: &Pubkey, epoch: u64, mev_rewards: u64) {
+    println!(
+        "{}",
+        format!(
+            "✓ MEV rewards for {}... in epoch {} are {} SOL",
+            &identity_pubkey.to_string()[..6],
+            epoch,
+            TokenAmt {
+                amt: mev_rewards,
+                decimals: 9
+            }
+        )
+        .green()
+        .bold()
+    );
 }
 
 impl CalculateArgs {
@@ -29,10 +325,57 @@ impl CalculateArgs {
         let Self {
             identity_pubkey,
             epoch,
+            source,
+            dune,
+            ledger_dir,
+            detailed,
+            mev,
+            mev_file,
+            vote_pubkey,
+            yes,
         } = match args.subcmd {
             Subcmd::Calculate(args) => args,
             _ => unreachable!(),
         };
+        let DuneOpts {
+            end_epoch,
+            dune_api_key,
+            dune_api_url,
+            dune_query_id,
+            dune_param,
+            dune_epoch_column,
+            dune_rewards_column,
+            dune_last_slot_column,
+            dune_last_block_time_column,
+            allow_stale,
+            allow_partial,
+            timeout,
+            dune_max_age,
+            execution_id,
+        } = dune;
+
+        if end_epoch.is_some() && source != RewardSourceKind::Dune {
+            println!(
+                "{}",
+                "Error: --end-epoch is only supported with --source dune".red()
+            );
+            return;
+        }
+        if end_epoch.is_some() && (mev || mev_file.is_some()) {
+            println!(
+                "{}",
+                "Error: --mev and --mev-file are not supported with --end-epoch".red()
+            );
+            return;
+        }
+
+        if detailed && source == RewardSourceKind::Dune {
+            println!(
+                "{}",
+                "Error: --detailed is not supported with --source dune".red()
+            );
+            return;
+        }
 
         let identity_pubkey = match input_string(
             "Enter your validator's identity key:",
@@ -55,18 +398,37 @@ impl CalculateArgs {
             }
         };
 
-        let rpc_url = match input_with_validation(
-            "Enter the RPC URL:",
-            "RPC URL",
-            Some(SOLANA_PUBLIC_RPC.to_string()),
-            args.rpc_url,
-            validate_rpc_url,
-        ) {
-            Ok(url) => url,
-            Err(_) => {
-                println!("{}", "Error: Invalid RPC URL".red());
-                return;
-            }
+        // Only the RPC source fetches every block, other sources only need the RPC for
+        // epoch info and leader schedules (if at all) so the public RPC is good enough
+        let rpc_url = match source {
+            RewardSourceKind::Rpc => match input_with_validation(
+                "Enter the RPC URL:",
+                "RPC URL",
+                Some(SOLANA_PUBLIC_RPC.to_string()),
+                args.rpc_url,
+                validate_rpc_url,
+            ) {
+                Ok(url) => url,
+                Err(_) => {
+                    println!("{}", "Error: Invalid RPC URL".red());
+                    return;
+                }
+            },
+            _ => args
+                .rpc_url
+                .unwrap_or_else(|| SOLANA_PUBLIC_RPC.to_string()),
+        };
+
+        let mev_source = match (mev_file, mev) {
+            (Some(mev_file), _) => Some(MevSource::File(mev_file)),
+            (None, true) => match vote_pubkey.as_deref().map(Pubkey::from_str).transpose() {
+                Ok(vote_pubkey) => Some(MevSource::TipDistributionAccount { vote_pubkey }),
+                Err(_) => {
+                    println!("{}", "Error: Invalid vote pubkey".red());
+                    return;
+                }
+            },
+            (None, false) => None,
         };
 
         let rpc = RpcClient::new_with_commitment(
@@ -74,28 +436,141 @@ impl CalculateArgs {
             args.commitment.unwrap_or(CommitmentConfig::confirmed()),
         );
 
-        let (current_epoch_info, epoch_schedule) =
-            match tokio::try_join!(rpc.get_epoch_info(), rpc.get_epoch_schedule()) {
-                Ok(result) => result,
+        // The ledger source works fully offline, so it can't know the current epoch
+        // and assumes the mainnet epoch schedule
+        let epoch = match source {
+            RewardSourceKind::Ledger => input_with_validation(
+                "Enter the epoch to calculate rewards for:",
+                "Epoch",
+                None,
+                epoch.map(|e| e.to_string()),
+                |input| {
+                    input
+                        .parse::<u64>()
+                        .map_err(|_| "Error: Please enter a valid number".to_string())
+                },
+            )
+            .map(|e| (e, EpochSchedule::without_warmup(), None)),
+            _ => match tokio::try_join!(rpc.get_epoch_info(), rpc.get_epoch_schedule()) {
+                Ok((current_epoch_info, epoch_schedule)) => input_with_validation(
+                    "Enter the epoch to calculate rewards for:",
+                    &(current_epoch_info.epoch - 1).to_string(),
+                    Some((current_epoch_info.epoch - 1).to_string()),
+                    epoch.map(|e| e.to_string()),
+                    |input| validate_epoch(input, current_epoch_info.epoch),
+                )
+                .map(|e| (e, epoch_schedule, Some(current_epoch_info.epoch))),
                 Err(_) => {
                     println!("{}", "Error: Failed to fetch data from RPC".red());
                     return;
                 }
-            };
-
-        let epoch = match input_with_validation(
-            "Enter the epoch to calculate rewards for:",
-            &(current_epoch_info.epoch - 1).to_string(),
-            Some((current_epoch_info.epoch - 1).to_string()),
-            epoch.map(|e| e.to_string()),
-            |input| validate_epoch(input, current_epoch_info.epoch),
-        ) {
-            Ok(e) => e,
+            },
+        };
+
+        let (epoch, epoch_schedule, current_epoch) = match epoch {
+            Ok(result) => result,
             Err(_) => {
                 println!("{}", "Error: Invalid epoch".red());
                 return;
             }
         };
+
+        let source_opts = match source {
+            RewardSourceKind::Rpc => RewardSourceOpts::Rpc { detailed },
+            RewardSourceKind::Dune => {
+                let dune_api_key =
+                    match input_string("Enter your Dune API key:", "API key", None, dune_api_key) {
+                        Ok(key) => key,
+                        Err(_) => {
+                            println!("{}", "Error: Invalid Dune API key".red());
+                            return;
+                        }
+                    };
+
+                RewardSourceOpts::Dune {
+                    client: DuneClient::new(&dune_api_key).with_base_url(&dune_api_url),
+                    query: DuneQuery {
+                        query_id: dune_query_id,
+                        params: dune_param.into_iter().collect(),
+                        epoch_column: dune_epoch_column,
+                        rewards_column: dune_rewards_column,
+                        last_slot_column: dune_last_slot_column,
+                        last_block_time_column: dune_last_block_time_column,
+                    },
+                    timeout_secs: timeout,
+                    max_age_secs: dune_max_age,
+                    execution_id,
+                    allow_stale,
+                    allow_partial,
+                }
+            }
+            RewardSourceKind::Ledger => {
+                let ledger_dir = match input_string(
+                    "Enter the directory of exported blocks:",
+                    "Ledger directory",
+                    None,
+                    ledger_dir,
+                ) {
+                    Ok(dir) => dir,
+                    Err(_) => {
+                        println!("{}", "Error: Invalid ledger directory".red());
+                        return;
+                    }
+                };
+
+                RewardSourceOpts::Ledger {
+                    blocks_dir: PathBuf::from(ledger_dir),
+                    detailed,
+                }
+            }
+        };
+
+        let source_opts = match (end_epoch, source_opts) {
+            (
+                Some(end_epoch),
+                RewardSourceOpts::Dune {
+                    client,
+                    query,
+                    timeout_secs,
+                    max_age_secs,
+                    execution_id,
+                    allow_stale,
+                    allow_partial,
+                },
+            ) => {
+                let end_epoch = match current_epoch
+                    .ok_or_else(|| "Error: Unknown current epoch".to_string())
+                    .and_then(|current_epoch| validate_epoch(&end_epoch.to_string(), current_epoch))
+                {
+                    Ok(end_epoch) if end_epoch >= epoch => end_epoch,
+                    Ok(_) => {
+                        println!("{}", "Error: --end-epoch must not be before --epoch".red());
+                        return;
+                    }
+                    Err(err) => {
+                        println!("{}", err.red());
+                        return;
+                    }
+                };
+
+                let dune_source = DuneRewardSource {
+                    rpc: &rpc,
+                    epoch_schedule,
+                    client,
+                    query,
+                    timeout_secs,
+                    max_age_secs,
+                    execution_id,
+                    allow_stale,
+                    allow_partial,
+                    quiet: false,
+                };
+                calculate_epoch_range(&dune_source, &identity_pubkey, epoch..=end_epoch, yes).await;
+                return;
+            }
+            (_, source_opts) => source_opts,
+        };
+
         println!("{}", "=".repeat(80));
 
         // Check if rewards file exists
@@ -108,160 +583,179 @@ impl CalculateArgs {
         };
 
         // if path exists, read the file and display the total block rewards
-        if Path::new(&rewards_file_path).exists() {
-            let rewards: Value = match File::open(rewards_file_path.clone())
-                .map_err(|_| "Failed to open rewards file")
-                .and_then(|file| {
-                    serde_json::from_reader(file).map_err(|_| "Failed to parse rewards file")
-                }) {
-                Ok(value) => value,
-                Err(err) => {
-                    println!("{}", format!("Error: {}", err).red());
-                    return;
-                }
-            };
+        match read_rewards_file(&rewards_file_path) {
+            Ok(Some(mut rewards)) => {
+                println!(
+                    "{}",
+                    format!("Rewards file found at {}", rewards_file_path).blue()
+                );
 
-            let total_block_rewards = match rewards["total_block_rewards"].as_u64() {
-                Some(rewards) => rewards,
-                None => {
-                    println!("{}", "Error: Invalid rewards file format".red());
-                    return;
-                }
-            };
-
-            println!(
-                "{}",
-                format!("Rewards file found at {}", rewards_file_path).blue()
-            );
-            println!(
-                "{}",
-                format!(
-                    "✓ Total block rewards for {}... in epoch {} are {} SOL",
-                    &identity_pubkey.to_string()[..6],
-                    epoch,
-                    TokenAmt {
-                        amt: total_block_rewards,
-                        decimals: 9
+                // Add MEV rewards to files calculated without them
+                if let (Some(mev_source), None) = (&mev_source, rewards.mev_rewards) {
+                    match fetch_mev_rewards(&rpc, &identity_pubkey, mev_source, epoch).await {
+                        Ok(mev_rewards) => rewards.mev_rewards = Some(mev_rewards),
+                        Err(err) => {
+                            println!("{}", err.red());
+                            return;
+                        }
                     }
-                )
-                .green()
-                .bold()
-            );
 
-            println!("{}", "=".repeat(80));
-            return;
-        }
-
-        let mut sp = Spinner::new(
-            Spinners::Dots,
-            format!(
-                "Fetching leader slots for {}...",
-                &identity_pubkey.to_string()[..6]
-            ),
-        );
+                    if let Err(err) = write_json_file(&rewards_file_path, &rewards) {
+                        println!("{}", format!("Error: {}", err).red());
+                        return;
+                    }
+                    record_in_ledger(|ledger| {
+                        ledger.record_calculation(&identity_pubkey, epoch, &rewards)
+                    });
+                    println!(
+                        "{}",
+                        format!("Saved MEV rewards to {}", rewards_file_path).blue()
+                    );
+                }
 
-        let leader_slots =
-            match get_leader_slots_for_identity(&rpc, epoch, &epoch_schedule, &identity_pubkey)
-                .await
-            {
-                Ok(slots) => slots,
-                Err(err) => {
-                    println!("{}", format!("Error: {}", err).red());
-                    return;
+                println!(
+                    "{}",
+                    format!(
+                        "✓ Total block rewards for {}... in epoch {} are {} SOL",
+                        &identity_pubkey.to_string()[..6],
+                        epoch,
+                        TokenAmt {
+                            amt: rewards.total_block_rewards,
+                            decimals: 9
+                        }
+                    )
+                    .green()
+                    .bold()
+                );
+                if let Some(mev_rewards) = rewards.mev_rewards {
+                    print_mev_rewards(&identity_pubkey, epoch, mev_rewards);
+                }
+                if let Some(fee_breakdown) = &rewards.fee_breakdown {
+                    print_fee_breakdown(fee_breakdown);
+                } else if detailed {
+                    println!(
+                        "{}",
+                        "⚠️ The rewards file has no fee breakdown. Delete it and run again with --detailed to fetch it."
+                            .yellow()
+                    );
                 }
-            };
 
-        let num_leader_slots = leader_slots.len();
-        sp.stop_with_message(
-            format!(
-                "✓ Found {} leader slots for {}... in epoch {}",
-                num_leader_slots,
-                &identity_pubkey.to_string()[..6],
-                epoch
-            )
-            .green()
-            .bold()
-            .to_string(),
-        );
+                println!("{}", "=".repeat(80));
+                return;
+            }
+            Ok(None) => (),
+            Err(err) => {
+                println!("{}", format!("Error: {}", err).red());
+                return;
+            }
+        }
 
-        if leader_slots.len() > 200 && rpc.url() == SOLANA_PUBLIC_RPC {
-            println!(
-                "{}",
-                "⚠️ We recommend using a custom RPC URL to avoid longer wait time and rate limits."
-                    .yellow()
-                    .bold()
-            );
-            println!(
-                "{}",
-                "⚠️ We also have a `calculate-with-dune` command that also calulcates block rewards but using Dune Analytics."
-                    .yellow()
-                    .bold()
-            );
+        match source {
+            RewardSourceKind::Rpc => {
+                if detailed {
+                    println!(
+                        "{}",
+                        "⚠️ --detailed fetches every transaction of each block, which is much slower and heavier on the RPC."
+                            .yellow()
+                            .bold()
+                    );
+                }
+                if rpc.url() == SOLANA_PUBLIC_RPC {
+                    println!(
+                        "{}",
+                        "⚠️ We recommend using a custom RPC URL to avoid longer wait time and rate limits."
+                            .yellow()
+                            .bold()
+                    );
+                    println!(
+                        "{}",
+                        "⚠️ You can also calculate block rewards using Dune Analytics with `--source dune`."
+                            .yellow()
+                            .bold()
+                    );
+                }
+            }
+            RewardSourceKind::Dune => {
+                println!(
+                    "{}",
+                    "⚠️ Please note that the data on Dune is usually lagging by 2-3 hours"
+                        .bold()
+                        .yellow()
+                );
+            }
+            RewardSourceKind::Ledger => {
+                println!(
+                    "{}",
+                    "⚠️ Skipped leader slots are not in the ledger and are not counted"
+                        .bold()
+                        .yellow()
+                );
+            }
         }
 
         println!("{}", "=".repeat(80));
 
-        let ans = Confirm::new(
-            &"Do you wish to continue with fetching block rewards?"
-                .blue()
-                .bold(),
-        )
-        .with_default(true)
-        .prompt();
+        if !yes {
+            let ans = Confirm::new(
+                &"Do you wish to continue with fetching block rewards?"
+                    .blue()
+                    .bold(),
+            )
+            .with_default(true)
+            .prompt();
 
-        match ans {
-            Ok(false) => {
-                return;
-            }
-            Err(_) => {
-                println!("Error: Something went wrong.");
-                return;
+            match ans {
+                Ok(false) => {
+                    return;
+                }
+                Err(_) => {
+                    println!("Error: Something went wrong.");
+                    return;
+                }
+                _ => (),
             }
-            _ => (),
         }
 
         println!("{}", "=".repeat(80));
 
-        let total_block_rewards = match get_total_block_rewards_for_slots(&rpc, &leader_slots).await
+        let rewards = match calculate_epoch_rewards(
+            &rpc,
+            &identity_pubkey,
+            epoch,
+            CalculateOpts {
+                source: source_opts,
+                mev: mev_source,
+                quiet: false,
+            },
+        )
+        .await
         {
             Ok(rewards) => rewards,
             Err(err) => {
-                println!("{}", err);
+                println!("{}", err.red());
                 return;
             }
         };
 
-        // Create all parent directories if they don't exist
-        if let Some(parent) = Path::new(&rewards_file_path).parent() {
-            match std::fs::create_dir_all(parent) {
-                Ok(_) => (),
-                Err(err) => {
-                    println!(
-                        "{}",
-                        format!("Error: Failed to create directory - {}", err).red()
-                    );
-                    return;
-                }
-            };
+        if let Some(num_leader_slots) = rewards.num_leader_slots {
+            println!(
+                "{}",
+                format!(
+                    "✓ Found {} leader slots for {}... in epoch {}",
+                    num_leader_slots,
+                    &identity_pubkey.to_string()[..6],
+                    epoch
+                )
+                .green()
+                .bold()
+            );
         }
 
-        match File::create(&rewards_file_path)
-            .map_err(|e| e.to_string())
-            .and_then(|file| {
-                serde_json::to_writer_pretty(
-                    file,
-                    &json!({
-                        "total_block_rewards": total_block_rewards,
-                    }),
-                )
-                .map_err(|e| e.to_string())
-            }) {
-            Ok(_) => (),
-            Err(err) => {
-                println!("{}", format!("Error: {}", err).red());
-                return;
-            }
-        };
+        if let Err(err) = write_json_file(&rewards_file_path, &rewards) {
+            println!("{}", format!("Error: {}", err).red());
+            return;
+        }
+        record_in_ledger(|ledger| ledger.record_calculation(&identity_pubkey, epoch, &rewards));
 
         println!(
             "{}",
@@ -270,13 +764,19 @@ impl CalculateArgs {
                 &identity_pubkey.to_string()[..6],
                 epoch,
                 TokenAmt {
-                    amt: total_block_rewards,
+                    amt: rewards.total_block_rewards,
                     decimals: 9
                 }
             )
             .green()
             .bold()
         );
+        if let Some(mev_rewards) = rewards.mev_rewards {
+            print_mev_rewards(&identity_pubkey, epoch, mev_rewards);
+        }
+        if let Some(fee_breakdown) = &rewards.fee_breakdown {
+            print_fee_breakdown(fee_breakdown);
+        }
 
         println!(
             "{}",
@@ -0,0 +1,199 @@
+use crate::{
+    check_endorsement_signature, endorsement_memo, find_endorsement, get_vote_account_for_identity,
+    input_with_validation, subcmd::Subcmd, validate_pubkey, validate_rpc_url,
+    validate_vote_account, SOLANA_PUBLIC_RPC,
+};
+use clap::{command, Args};
+use colored::Colorize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, signature::Signature};
+use spinners::{Spinner, Spinners};
+use std::str::FromStr;
+
+const DEFAULT_MAX_SIGNATURES: usize = 10_000;
+
+#[derive(Args, Debug)]
+#[command(
+    long_about = "Find and check the endorsement of a stake pool by a validator identity made with the `endorse` command"
+)]
+pub struct CheckEndorsementArgs {
+    #[arg(long, help = "The identity pubkey of the validator")]
+    pub identity_pubkey: Option<String>,
+
+    #[arg(long, help = "The stake pool account linked to the LST")]
+    pub stake_pool_pubkey: Option<String>,
+
+    #[arg(
+        long,
+        help = "Vote account of the validator, which must have the identity as its node. Looked up from the identity if not provided"
+    )]
+    pub vote_pubkey: Option<String>,
+
+    #[arg(
+        long,
+        help = "Signature of the endorsement transaction. Searched in the stake pool's transactions if not provided"
+    )]
+    pub signature: Option<String>,
+
+    #[arg(
+        long,
+        help = "Number of the stake pool's most recent transactions to search",
+        default_value_t = DEFAULT_MAX_SIGNATURES
+    )]
+    pub max_signatures: usize,
+}
+
+impl CheckEndorsementArgs {
+    pub async fn run(args: crate::Args) {
+        let Self {
+            identity_pubkey,
+            stake_pool_pubkey,
+            vote_pubkey,
+            signature,
+            max_signatures,
+        } = match args.subcmd {
+            Subcmd::CheckEndorsement(a) => a,
+            _ => unreachable!(),
+        };
+
+        let identity_pubkey = match input_with_validation(
+            "Enter the validator's identity key:",
+            "Identity key",
+            None,
+            identity_pubkey,
+            validate_pubkey,
+        ) {
+            Ok(pubkey) => pubkey,
+            Err(_) => {
+                println!("{}", "Error: Invalid identity pubkey".red());
+                std::process::exit(1);
+            }
+        };
+
+        let stake_pool_pubkey = match input_with_validation(
+            "Enter the stake pool pubkey:",
+            "Stake pool pubkey",
+            None,
+            stake_pool_pubkey,
+            validate_pubkey,
+        ) {
+            Ok(pubkey) => pubkey,
+            Err(_) => {
+                println!("{}", "Error: Invalid pubkey".red());
+                std::process::exit(1);
+            }
+        };
+
+        let signature = match signature.as_deref().map(Signature::from_str).transpose() {
+            Ok(signature) => signature,
+            Err(_) => {
+                println!("{}", "Error: Invalid signature".red());
+                std::process::exit(1);
+            }
+        };
+
+        let rpc_url = match input_with_validation(
+            "Enter the RPC URL:",
+            "RPC URL",
+            Some(SOLANA_PUBLIC_RPC.to_string()),
+            args.rpc_url,
+            validate_rpc_url,
+        ) {
+            Ok(url) => url,
+            Err(_) => {
+                println!("{}", "Error: Invalid RPC URL".red());
+                std::process::exit(1);
+            }
+        };
+
+        let rpc = RpcClient::new_with_commitment(
+            rpc_url,
+            args.commitment.unwrap_or(CommitmentConfig::confirmed()),
+        );
+
+        let vote_pubkey = match vote_pubkey {
+            Some(vote_pubkey) => match validate_pubkey(&vote_pubkey) {
+                Ok(vote_pubkey) => validate_vote_account(&rpc, &vote_pubkey, &identity_pubkey)
+                    .await
+                    .map(|_| vote_pubkey),
+                Err(err) => Err(err),
+            },
+            None => get_vote_account_for_identity(&rpc, &identity_pubkey).await,
+        };
+        let vote_pubkey = match vote_pubkey {
+            Ok(pubkey) => pubkey,
+            Err(err) => {
+                println!("{}", err.red());
+                std::process::exit(1);
+            }
+        };
+
+        println!("{}", "=".repeat(80));
+
+        println!(
+            "{}{}",
+            "Memo: ".blue().bold(),
+            endorsement_memo(&stake_pool_pubkey, &vote_pubkey).bold()
+        );
+
+        let result = match signature {
+            Some(signature) => check_endorsement_signature(
+                &rpc,
+                &signature,
+                &identity_pubkey,
+                &stake_pool_pubkey,
+                &vote_pubkey,
+            )
+            .await
+            .map(|_| Some(signature)),
+            None => {
+                let mut sp = Spinner::new(
+                    Spinners::Dots,
+                    "Searching the stake pool's transactions...".to_string(),
+                );
+                let result = find_endorsement(
+                    &rpc,
+                    &identity_pubkey,
+                    &stake_pool_pubkey,
+                    &vote_pubkey,
+                    max_signatures,
+                )
+                .await;
+                sp.stop_with_newline();
+                result
+            }
+        };
+
+        match result {
+            Ok(Some(signature)) => {
+                println!(
+                    "{}{}",
+                    "Signature: ".blue().bold(),
+                    signature.to_string().bold()
+                );
+                println!(
+                    "{}",
+                    format!("✓ {} endorses {}", identity_pubkey, stake_pool_pubkey)
+                        .green()
+                        .bold()
+                );
+                println!("{}", "=".repeat(80));
+            }
+            Ok(None) => {
+                println!(
+                    "{}",
+                    format!(
+                        "Error: No endorsement found in the stake pool's last {} transactions",
+                        max_signatures
+                    )
+                    .red()
+                );
+                std::process::exit(1);
+            }
+            Err(err) => {
+                println!("{}", err.red());
+                std::process::exit(1);
+            }
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use crate::{
+    endorse_ix, endorsement_memo, get_vote_account_for_identity, handle_tx_full,
+    input_with_validation, subcmd::Subcmd, validate_pubkey, validate_rpc_url,
+    validate_vote_account, with_auto_cb_ixs, KNOWN_STAKE_POOL_PROGRAM_IDS, SOLANA_PUBLIC_RPC,
+};
+use clap::{command, Args};
+use colored::Colorize;
+use inquire::Confirm;
+use sanctum_solana_cli_utils::{parse_named_signer, ParseNamedSigner, TxSendMode};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, signer::Signer};
+
+#[derive(Args, Debug)]
+#[command(
+    long_about = "Endorse distributing your block rewards to a stake pool with a memo transaction signed by your validator identity"
+)]
+pub struct EndorseArgs {
+    #[arg(long, help = "Path to the validator identity keypair")]
+    pub identity_keypair: String,
+
+    #[arg(long, help = "The stake pool account linked to your LST")]
+    pub stake_pool_pubkey: Option<String>,
+
+    #[arg(
+        long,
+        help = "Vote account of your validator, which must have the identity as its node. Looked up from the identity if not provided"
+    )]
+    pub vote_pubkey: Option<String>,
+
+    #[arg(long, help = "Path to the fee payer keypair. Defaults to the identity")]
+    pub payer: Option<String>,
+
+    #[arg(long, help = "Skip the confirmation prompt before sending")]
+    pub yes: bool,
+}
+
+impl EndorseArgs {
+    pub async fn run(args: crate::Args) {
+        let Self {
+            identity_keypair,
+            stake_pool_pubkey,
+            vote_pubkey,
+            payer,
+            yes,
+        } = match args.subcmd {
+            Subcmd::Endorse(a) => a,
+            _ => unreachable!(),
+        };
+
+        let identity_keypair = match parse_named_signer(ParseNamedSigner {
+            name: "identity",
+            arg: &identity_keypair,
+        }) {
+            Ok(keypair) => keypair,
+            Err(_) => {
+                println!("{}", "Error: Invalid identity keypair".red());
+                return;
+            }
+        };
+
+        let payer_keypair = match payer
+            .as_ref()
+            .map(|arg| parse_named_signer(ParseNamedSigner { name: "payer", arg }))
+            .transpose()
+        {
+            Ok(keypair) => keypair,
+            Err(_) => {
+                println!("{}", "Error: Invalid payer keypair".red());
+                return;
+            }
+        };
+
+        let identity_pubkey = identity_keypair.pubkey();
+        let payer_pubkey = payer_keypair
+            .as_ref()
+            .map_or(identity_pubkey, |keypair| keypair.pubkey());
+
+        let rpc_url = match input_with_validation(
+            "Enter the RPC URL:",
+            "RPC URL",
+            Some(SOLANA_PUBLIC_RPC.to_string()),
+            args.rpc_url,
+            validate_rpc_url,
+        ) {
+            Ok(url) => url,
+            Err(_) => {
+                println!("{}", "Error: Invalid RPC URL".red());
+                return;
+            }
+        };
+
+        let rpc = RpcClient::new_with_commitment(
+            rpc_url,
+            args.commitment.unwrap_or(CommitmentConfig::confirmed()),
+        );
+
+        let stake_pool_pubkey = match input_with_validation(
+            "Enter the stake pool pubkey:",
+            "Stake pool pubkey",
+            None,
+            stake_pool_pubkey,
+            validate_pubkey,
+        ) {
+            Ok(pubkey) => pubkey,
+            Err(_) => {
+                println!("{}", "Error: Invalid pubkey".red());
+                return;
+            }
+        };
+
+        match rpc.get_account(&stake_pool_pubkey).await {
+            Ok(account) if KNOWN_STAKE_POOL_PROGRAM_IDS.contains(&account.owner) => (),
+            Ok(account) => {
+                println!(
+                    "{}",
+                    format!(
+                        "Error: Stake pool account is owned by {}, which is not a known stake pool program",
+                        account.owner
+                    )
+                    .red()
+                );
+                return;
+            }
+            Err(e) => {
+                println!(
+                    "{}",
+                    format!("Error: Failed to fetch stake pool account: {}", e).red()
+                );
+                return;
+            }
+        }
+
+        let vote_pubkey = match vote_pubkey {
+            Some(vote_pubkey) => match validate_pubkey(&vote_pubkey) {
+                Ok(vote_pubkey) => validate_vote_account(&rpc, &vote_pubkey, &identity_pubkey)
+                    .await
+                    .map(|_| vote_pubkey),
+                Err(err) => Err(err),
+            },
+            None => get_vote_account_for_identity(&rpc, &identity_pubkey).await,
+        };
+        let vote_pubkey = match vote_pubkey {
+            Ok(pubkey) => pubkey,
+            Err(err) => {
+                println!("{}", err.red());
+                return;
+            }
+        };
+
+        println!("{}", "=".repeat(80));
+
+        println!(
+            "{}{}",
+            "Identity: ".blue().bold(),
+            identity_pubkey.to_string().bold()
+        );
+        println!(
+            "{}{}",
+            "Vote account: ".blue().bold(),
+            vote_pubkey.to_string().bold()
+        );
+        println!(
+            "{}{}",
+            "Stake pool: ".blue().bold(),
+            stake_pool_pubkey.to_string().bold()
+        );
+        println!(
+            "{}{}",
+            "Memo: ".blue().bold(),
+            endorsement_memo(&stake_pool_pubkey, &vote_pubkey).bold()
+        );
+
+        println!("{}", "=".repeat(80));
+
+        if !yes {
+            let ans = Confirm::new(
+                &"Do you wish to endorse this stake pool with your validator identity?"
+                    .blue()
+                    .bold(),
+            )
+            .with_default(true)
+            .prompt();
+
+            match ans {
+                Ok(false) => {
+                    return;
+                }
+                Err(_) => {
+                    println!("Error: Something went wrong.");
+                    return;
+                }
+                _ => (),
+            }
+        }
+
+        let send_mode = args.send_mode;
+
+        let ixs = vec![endorse_ix(
+            &identity_pubkey,
+            &stake_pool_pubkey,
+            &vote_pubkey,
+        )];
+        let fee_limit_cb = match send_mode {
+            TxSendMode::DumpMsg => 0,
+            _ => args.fee_limit_cb,
+        };
+        let final_ixs = with_auto_cb_ixs(&rpc, &payer_pubkey, ixs, &[], fee_limit_cb).await;
+
+        let mut signers: Vec<&dyn Signer> = Vec::new();
+        // The first signer pays for the tx
+        if let Some(keypair) = payer_keypair.as_ref() {
+            if keypair.pubkey() != identity_pubkey {
+                signers.push(keypair.as_ref());
+            }
+        }
+        signers.push(identity_keypair.as_ref());
+
+        if send_mode == TxSendMode::DumpMsg {
+            println!("{}", "Transaction Message:".blue().bold());
+        }
+
+        handle_tx_full(&rpc, send_mode, &final_ixs, &[], &mut signers).await;
+
+        if send_mode == TxSendMode::SendActual {
+            println!(
+                "{}",
+                "Run the `check-endorsement` command to find and check the endorsement.".blue()
+            );
+        }
+    }
+}
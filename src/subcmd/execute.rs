@@ -0,0 +1,491 @@
+use crate::{
+    check_payer_balance, check_plan_rewards, estimate_max_tx_fee, find_distribution,
+    get_ledger_file_path, get_rewards_file_path, handle_tx_full, input_with_validation,
+    print_transfer_summary, read_json_file, read_rewards_file, record_in_ledger, subcmd::Subcmd,
+    validate_distribution_plan, validate_pubkey, validate_rpc_url, validate_sol_amount,
+    with_auto_cb_ixs, CheckPayerBalanceArgs, DistributionPlan, Ledger, LedgerQuery, PoolBalance,
+    PrintTransferSummaryArgs, TransferRecord, TransferStatus, DEFAULT_MAX_DISTRIBUTION_SIGNATURES,
+    DEFAULT_RESERVE_FLOOR_SOL, SOLANA_PUBLIC_RPC,
+};
+use clap::{command, Args};
+use colored::Colorize;
+use inquire::Confirm;
+use sanctum_solana_cli_utils::{parse_named_signer, ParseNamedSigner, TxSendMode};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig, instruction::Instruction, pubkey::Pubkey, signer::Signer,
+};
+use spinners::{Spinner, Spinners};
+
+#[derive(Args, Debug)]
+#[command(
+    long_about = "Re-validate a plan written by the `plan` command against the current chain state and send it"
+)]
+pub struct ExecuteArgs {
+    #[arg(help = "Path to the plan file written by `plan`")]
+    pub file: String,
+
+    #[arg(long, help = "Path to the keypair of the plan's payer")]
+    pub payer: String,
+
+    #[arg(
+        long,
+        help = "Minimum SOL balance the payer must keep after the transfer, on top of the rent exempt minimum and tx fees",
+        default_value = DEFAULT_RESERVE_FLOOR_SOL
+    )]
+    pub reserve_floor: String,
+
+    #[arg(
+        long,
+        help = "Skip the pre-flight check that the payer can afford the transfer while staying above the reserve floor"
+    )]
+    pub skip_balance_check: bool,
+
+    #[arg(long, help = "Skip the confirmation prompt before transferring")]
+    pub yes: bool,
+
+    #[arg(
+        long,
+        help = "Number of the stake pool's most recent transactions to search for an earlier distribution",
+        default_value_t = DEFAULT_MAX_DISTRIBUTION_SIGNATURES
+    )]
+    pub max_signatures: usize,
+}
+
+/// How the payer may execute a plan
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrepareExecutionArgs {
+    pub payer_pubkey: Pubkey,
+    /// Minimum balance the payer must keep in lamports
+    pub reserve_floor: u64,
+    pub skip_balance_check: bool,
+    /// Signatures the distribution tx is paid for, e.g. 2 with a separate nonce authority
+    pub num_signatures: u64,
+    pub fee_limit_cb: u64,
+    pub yes: bool,
+    /// Stake pool transactions searched for an earlier distribution
+    pub max_signatures: usize,
+}
+
+/// Checks that `plan` can still be sent: its rewards, epoch and instructions must
+/// match, it must not have been distributed yet and the payer must be able to
+/// afford it. Prints its summary and asks for confirmation.
+/// Returns the instructions to send and the summary shown, or `None` after printing why not
+pub async fn prepare_plan_execution(
+    rpc: &RpcClient,
+    plan: &DistributionPlan,
+    args: PrepareExecutionArgs,
+) -> Option<(Vec<Instruction>, PrintTransferSummaryArgs)> {
+    let PrepareExecutionArgs {
+        payer_pubkey,
+        reserve_floor,
+        skip_balance_check,
+        num_signatures,
+        fee_limit_cb,
+        yes,
+        max_signatures,
+    } = args;
+
+    let (identity_pubkey, stake_pool_pubkey) = match (
+        validate_pubkey(&plan.identity_pubkey),
+        validate_pubkey(&plan.stake_pool_pubkey),
+    ) {
+        (Ok(identity_pubkey), Ok(stake_pool_pubkey)) => (identity_pubkey, stake_pool_pubkey),
+        _ => {
+            println!("{}", "Error: Invalid pubkey in plan".red());
+            return None;
+        }
+    };
+
+    // The plan may have been edited, or the rewards recalculated since
+    let rewards_file_path = match get_rewards_file_path(&identity_pubkey, plan.epoch) {
+        Ok(path) => path,
+        Err(err) => {
+            println!("{}", format!("Error: {}", err).red());
+            return None;
+        }
+    };
+
+    let rewards = match read_rewards_file(&rewards_file_path) {
+        Ok(Some(rewards)) => rewards,
+        Ok(None) => {
+            println!(
+                "{}",
+                format!("Error: Failed to find rewards at {}", rewards_file_path).red()
+            );
+            return None;
+        }
+        Err(err) => {
+            println!("{}", format!("Error: {}", err).red());
+            return None;
+        }
+    };
+
+    if let Err(err) = check_plan_rewards(plan, &rewards) {
+        println!("{}", err.red());
+        return None;
+    }
+
+    let sent = get_ledger_file_path()
+        .and_then(|path| Ledger::open(&path))
+        .and_then(|ledger| {
+            ledger.transfers(&LedgerQuery {
+                identity_pubkey: Some(identity_pubkey),
+                epoch: Some(plan.epoch),
+                stake_pool_pubkey: Some(stake_pool_pubkey),
+            })
+        });
+    match sent {
+        Ok(transfers) => {
+            if let Some(entry) = transfers
+                .iter()
+                .find(|entry| entry.transfer.status == TransferStatus::Sent)
+            {
+                println!(
+                    "{}",
+                    format!(
+                        "Error: The rewards of epoch {} were already distributed to this stake pool in transaction {}",
+                        plan.epoch,
+                        entry.transfer.signature.as_deref().unwrap_or("-")
+                    )
+                    .red()
+                );
+                return None;
+            }
+        }
+        Err(err) => println!(
+            "{}",
+            format!("⚠ Failed to read the ledger ({})", err).yellow()
+        ),
+    }
+
+    let (current_epoch_info, epoch_schedule, payer_balance, rent_exempt_minimum) = match tokio::try_join!(
+        rpc.get_epoch_info(),
+        rpc.get_epoch_schedule(),
+        rpc.get_balance(&payer_pubkey),
+        rpc.get_minimum_balance_for_rent_exemption(0)
+    ) {
+        Ok(result) => result,
+        Err(_) => {
+            println!("{}", "Error: Failed to fetch data from RPC".red());
+            return None;
+        }
+    };
+
+    if plan.epoch >= current_epoch_info.epoch {
+        println!(
+            "{}",
+            format!(
+                "Error: Epoch {} hasn't ended yet, its rewards can't be distributed before epoch {}",
+                plan.epoch,
+                plan.epoch + 1
+            )
+            .red()
+        );
+        return None;
+    }
+
+    println!("{}", "=".repeat(80));
+
+    let mut sp = Spinner::new(Spinners::Dots, "Validating plan...".to_string());
+
+    let (ixs, stake_pool) =
+        match validate_distribution_plan(rpc, plan, current_epoch_info.epoch).await {
+            Ok(result) => {
+                sp.stop_with_message("✓ Plan validated".green().bold().to_string());
+                result
+            }
+            Err(err) => {
+                sp.stop_with_message(err.red().to_string());
+                return None;
+            }
+        };
+
+    let mut sp = Spinner::new(
+        Spinners::Dots,
+        "Checking for an earlier distribution...".to_string(),
+    );
+
+    match find_distribution(
+        rpc,
+        plan.epoch,
+        &identity_pubkey,
+        &stake_pool_pubkey,
+        epoch_schedule.get_first_slot_in_epoch(plan.epoch + 1),
+        max_signatures,
+    )
+    .await
+    {
+        Ok(None) => {
+            sp.stop_with_message("✓ Not distributed yet".green().bold().to_string());
+        }
+        Ok(Some(signature)) => {
+            sp.stop_with_message(
+                format!(
+                    "Error: The rewards of epoch {} were already distributed to this stake pool in transaction {}",
+                    plan.epoch, signature
+                )
+                .red()
+                .to_string(),
+            );
+            return None;
+        }
+        Err(err) => {
+            sp.stop_with_message(err.red().to_string());
+            return None;
+        }
+    }
+
+    let transfer_lamports = plan.split.transfer_lamports;
+    let mut summary = plan.summary(payer_balance);
+
+    // Deposits and withdrawals since planning change the exchange rate impact,
+    // but not the amount transferred
+    let pool_balance_pre = PoolBalance::from_stake_pool(&stake_pool);
+    if pool_balance_pre != summary.pool_balance_pre {
+        match pool_balance_pre.after_reward(transfer_lamports, &stake_pool.epoch_fee) {
+            Some(pool_balance_post) => {
+                println!(
+                    "{}",
+                    "⚠ The stake pool's balance changed since the plan was made, showing its current balance"
+                        .yellow()
+                );
+                summary.pool_balance_pre = pool_balance_pre;
+                summary.pool_balance_post = pool_balance_post;
+            }
+            None => {
+                println!(
+                    "{}",
+                    "Error: Overflow in calculating the post transfer stake pool balance".red()
+                );
+                return None;
+            }
+        }
+    }
+
+    println!("{}", "=".repeat(80));
+
+    println!(
+        "{}{}",
+        "Identity: ".blue().bold(),
+        plan.identity_pubkey.bold()
+    );
+    println!(
+        "{}{}",
+        "Stake pool: ".blue().bold(),
+        format!(
+            "{} - {} ({})",
+            plan.stake_pool_pubkey, plan.lst_info.name, plan.lst_info.symbol
+        )
+        .bold()
+    );
+    println!(
+        "{}{}",
+        "Payer: ".blue().bold(),
+        payer_pubkey.to_string().bold()
+    );
+    println!("{}{}", "Planned at: ".blue().bold(), plan.created_at.bold());
+
+    print_transfer_summary(summary.clone());
+
+    println!("{}", "=".repeat(80));
+
+    if let Err(err) = check_payer_balance(CheckPayerBalanceArgs {
+        payer_balance,
+        transfer_lamports,
+        rent_exempt_minimum,
+        estimated_fee: estimate_max_tx_fee(num_signatures, fee_limit_cb),
+        reserve_floor,
+    }) {
+        println!("{}", err.red().bold());
+        if plan.payer_pubkey == plan.identity_pubkey {
+            println!(
+                "{}",
+                "⚠️ The payer is your validator identity. Draining it can leave your validator unable to pay vote fees."
+                    .yellow()
+                    .bold()
+            );
+        }
+        if !skip_balance_check {
+            println!(
+                "{}",
+                "Refusing to transfer. Top up the payer, lower --reserve-floor, or pass --skip-balance-check to override."
+                    .red()
+            );
+            return None;
+        }
+        println!(
+            "{}",
+            "⚠️ Continuing because --skip-balance-check was provided"
+                .yellow()
+                .bold()
+        );
+        println!("{}", "=".repeat(80));
+    }
+
+    if !yes {
+        let confirm_message = format!(
+            "Do you wish to continue to transfer your block rewards to {} ({})?",
+            plan.lst_info.name.magenta(),
+            plan.lst_info.symbol.magenta()
+        );
+
+        let ans = Confirm::new(&confirm_message.blue().bold())
+            .with_default(true)
+            .prompt();
+
+        match ans {
+            Ok(false) => {
+                return None;
+            }
+            Err(_) => {
+                println!("Error: Something went wrong.");
+                return None;
+            }
+            _ => (),
+        }
+    }
+
+    println!("{}", "=".repeat(80));
+
+    Some((ixs, summary))
+}
+
+/// Sends the instructions returned by [`prepare_plan_execution`] and records the
+/// transfer in the ledger once it was actually sent
+pub async fn send_plan(
+    rpc: &RpcClient,
+    send_mode: TxSendMode,
+    fee_limit_cb: u64,
+    plan: &DistributionPlan,
+    ixs: Vec<Instruction>,
+    payer_keypair: &dyn Signer,
+) {
+    let payer_pubkey = payer_keypair.pubkey();
+
+    let fee_limit_cb = match send_mode {
+        TxSendMode::DumpMsg => 0,
+        _ => fee_limit_cb,
+    };
+    let final_ixs = with_auto_cb_ixs(rpc, &payer_pubkey, ixs, &[], fee_limit_cb).await;
+
+    if send_mode == TxSendMode::DumpMsg {
+        println!("{}", "Transaction Message:".blue().bold());
+    }
+
+    let signature = handle_tx_full(rpc, send_mode, &final_ixs, &[], &mut [payer_keypair]).await;
+
+    if send_mode == TxSendMode::SendActual {
+        record_in_ledger(|ledger| {
+            ledger.record_transfer(&TransferRecord::from_plan(
+                plan,
+                Some(signature.to_string()),
+            ))
+        });
+    }
+}
+
+impl ExecuteArgs {
+    pub async fn run(args: crate::Args) {
+        let Self {
+            file,
+            payer,
+            reserve_floor,
+            skip_balance_check,
+            yes,
+            max_signatures,
+        } = match args.subcmd {
+            Subcmd::Execute(a) => a,
+            _ => unreachable!(),
+        };
+
+        let plan: DistributionPlan = match read_json_file(&file) {
+            Ok(plan) => plan,
+            Err(err) => {
+                println!("{}", format!("Error: {}", err).red());
+                return;
+            }
+        };
+
+        let payer_keypair = match parse_named_signer(ParseNamedSigner {
+            name: "payer",
+            arg: &payer,
+        }) {
+            Ok(keypair) => keypair,
+            Err(_) => {
+                println!("{}", "Error: Invalid payer keypair".red());
+                return;
+            }
+        };
+
+        let payer_pubkey = payer_keypair.pubkey();
+
+        if payer_pubkey.to_string() != plan.payer_pubkey {
+            println!(
+                "{}",
+                format!(
+                    "Error: The plan must be executed by its payer {}, not {}",
+                    plan.payer_pubkey, payer_pubkey
+                )
+                .red()
+            );
+            return;
+        }
+
+        let reserve_floor = match validate_sol_amount(&reserve_floor) {
+            Ok(lamports) => lamports,
+            Err(err) => {
+                println!("{}", err.red());
+                return;
+            }
+        };
+
+        let rpc_url = match input_with_validation(
+            "Enter the RPC URL:",
+            "RPC URL",
+            Some(SOLANA_PUBLIC_RPC.to_string()),
+            args.rpc_url,
+            validate_rpc_url,
+        ) {
+            Ok(url) => url,
+            Err(_) => {
+                println!("{}", "Error: Invalid RPC URL".red());
+                return;
+            }
+        };
+
+        let rpc = RpcClient::new_with_commitment(
+            rpc_url,
+            args.commitment.unwrap_or(CommitmentConfig::confirmed()),
+        );
+
+        let ixs = match prepare_plan_execution(
+            &rpc,
+            &plan,
+            PrepareExecutionArgs {
+                payer_pubkey,
+                reserve_floor,
+                skip_balance_check,
+                num_signatures: 1,
+                fee_limit_cb: args.fee_limit_cb,
+                yes,
+                max_signatures,
+            },
+        )
+        .await
+        {
+            Some((ixs, _)) => ixs,
+            None => return,
+        };
+
+        send_plan(
+            &rpc,
+            args.send_mode,
+            args.fee_limit_cb,
+            &plan,
+            ixs,
+            payer_keypair.as_ref(),
+        )
+        .await;
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{
+    get_ledger_file_path, get_sanctum_dir_path, subcmd::Subcmd, validate_pubkey, Ledger,
+    LedgerQuery,
+};
+use clap::{command, Args};
+use colored::Colorize;
+use comfy_table::{Attribute, Cell, Color, Table};
+use sanctum_solana_cli_utils::TokenAmt;
+
+#[derive(Args, Debug)]
+#[command(
+    long_about = "Show the calculations and transfers recorded in the local ledger at ~/.local/sanctum/ledger.sqlite"
+)]
+pub struct LedgerArgs {
+    #[arg(long, help = "Only show records of this validator identity")]
+    pub identity_pubkey: Option<String>,
+
+    #[arg(long, help = "Only show records of this epoch")]
+    pub epoch: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Only show transfers to this stake pool, and the calculations they were made from"
+    )]
+    pub stake_pool_pubkey: Option<String>,
+
+    #[arg(
+        long,
+        help = "First import the rewards, plan and signed transfer files in ~/.local/sanctum that aren't recorded yet"
+    )]
+    pub import: bool,
+}
+
+fn header(headers: &[&str]) -> Vec<Cell> {
+    headers
+        .iter()
+        .map(|header| {
+            Cell::new(header)
+                .add_attribute(Attribute::Bold)
+                .fg(Color::Blue)
+        })
+        .collect()
+}
+
+fn sol(amt: u64) -> String {
+    format!("{} SOL", TokenAmt { amt, decimals: 9 })
+}
+
+impl LedgerArgs {
+    pub async fn run(args: crate::Args) {
+        let Self {
+            identity_pubkey,
+            epoch,
+            stake_pool_pubkey,
+            import,
+        } = match args.subcmd {
+            Subcmd::Ledger(a) => a,
+            _ => unreachable!(),
+        };
+
+        let query = match (
+            identity_pubkey.as_deref().map(validate_pubkey).transpose(),
+            stake_pool_pubkey
+                .as_deref()
+                .map(validate_pubkey)
+                .transpose(),
+        ) {
+            (Ok(identity_pubkey), Ok(stake_pool_pubkey)) => LedgerQuery {
+                identity_pubkey,
+                epoch,
+                stake_pool_pubkey,
+            },
+            (Err(err), _) | (_, Err(err)) => {
+                println!("{}", err.red());
+                return;
+            }
+        };
+
+        let mut ledger = match get_ledger_file_path().and_then(|path| Ledger::open(&path)) {
+            Ok(ledger) => ledger,
+            Err(err) => {
+                println!("{}", err.red());
+                return;
+            }
+        };
+
+        println!("{}", "=".repeat(80));
+
+        if import {
+            let summary = match get_sanctum_dir_path().and_then(|dir| ledger.import_dir(&dir)) {
+                Ok(summary) => summary,
+                Err(err) => {
+                    println!("{}", err.red());
+                    return;
+                }
+            };
+
+            for failure in summary.failed.iter() {
+                println!("{}", format!("⚠ Failed to import {}", failure).yellow());
+            }
+            println!(
+                "{}",
+                format!(
+                    "✓ Imported {} calculations and {} transfers, {} files were already recorded",
+                    summary.calculations, summary.transfers, summary.skipped
+                )
+                .green()
+                .bold()
+            );
+        }
+
+        let calculations = match ledger.calculations(&query) {
+            Ok(calculations) => calculations,
+            Err(err) => {
+                println!("{}", err.red());
+                return;
+            }
+        };
+
+        let transfers = match ledger.transfers(&query) {
+            Ok(transfers) => transfers,
+            Err(err) => {
+                println!("{}", err.red());
+                return;
+            }
+        };
+
+        println!("{}", "Calculations".blue().bold());
+        let mut table = Table::new();
+        table.set_header(header(&[
+            "Identity",
+            "Epoch",
+            "Source",
+            "Leader Slots",
+            "Block Rewards",
+            "MEV Rewards",
+            "Recorded At",
+        ]));
+        for calculation in calculations.iter() {
+            let rewards = &calculation.rewards;
+            table.add_row(vec![
+                Cell::new(&calculation.identity_pubkey),
+                Cell::new(calculation.epoch),
+                Cell::new(rewards.source.as_deref().unwrap_or("-")),
+                Cell::new(
+                    rewards
+                        .num_leader_slots
+                        .map_or("-".to_string(), |slots| slots.to_string()),
+                ),
+                Cell::new(sol(rewards.total_block_rewards)),
+                Cell::new(rewards.mev_rewards.map_or("-".to_string(), sol)),
+                Cell::new(&calculation.recorded_at),
+            ]);
+        }
+        println!("{table}");
+
+        println!("{}", "Transfers".blue().bold());
+        let mut table = Table::new();
+        table.set_header(header(&[
+            "Identity",
+            "Epoch",
+            "Stake Pool",
+            "Amount",
+            "Status",
+            "Signature",
+            "Recorded At",
+        ]));
+        for entry in transfers.iter() {
+            let transfer = &entry.transfer;
+            table.add_row(vec![
+                Cell::new(&transfer.identity_pubkey),
+                Cell::new(transfer.epoch),
+                Cell::new(&transfer.stake_pool_pubkey),
+                Cell::new(sol(transfer.transfer_lamports)),
+                Cell::new(transfer.status),
+                Cell::new(transfer.signature.as_deref().unwrap_or("-")),
+                Cell::new(&entry.recorded_at),
+            ]);
+        }
+        println!("{table}");
+
+        println!("{}", "=".repeat(80));
+    }
+}
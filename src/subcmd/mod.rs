@@ -1,18 +1,45 @@
 use clap::Subcommand;
 
+mod attest;
+mod broadcast;
 mod calculate;
 mod calculate_with_dune;
+mod check_endorsement;
+mod endorse;
+mod execute;
+mod ledger;
+mod plan;
 mod transfer;
+mod verify;
+mod verify_transfer;
 
+pub use attest::*;
+pub use broadcast::*;
 pub use calculate::*;
 pub use calculate_with_dune::*;
+pub use check_endorsement::*;
+pub use endorse::*;
+pub use execute::*;
+pub use ledger::*;
+pub use plan::*;
 pub use transfer::*;
+pub use verify::*;
+pub use verify_transfer::*;
 
 #[derive(Debug, Subcommand)]
 pub enum Subcmd {
     Transfer(TransferArgs),
     Calculate(CalculateArgs),
     CalculateWithDune(CalculateWithDuneArgs),
+    Broadcast(BroadcastArgs),
+    Plan(PlanArgs),
+    Execute(ExecuteArgs),
+    Attest(AttestArgs),
+    Verify(VerifyArgs),
+    Endorse(EndorseArgs),
+    CheckEndorsement(CheckEndorsementArgs),
+    VerifyTransfer(VerifyTransferArgs),
+    Ledger(LedgerArgs),
 }
 
 impl Subcmd {
@@ -21,6 +48,15 @@ impl Subcmd {
             Self::Transfer(_) => TransferArgs::run(args).await,
             Self::Calculate(_) => CalculateArgs::run(args).await,
             Self::CalculateWithDune(_) => CalculateWithDuneArgs::run(args).await,
+            Self::Broadcast(_) => BroadcastArgs::run(args).await,
+            Self::Plan(_) => PlanArgs::run(args).await,
+            Self::Execute(_) => ExecuteArgs::run(args).await,
+            Self::Attest(_) => AttestArgs::run(args).await,
+            Self::Verify(_) => VerifyArgs::run(args).await,
+            Self::Endorse(_) => EndorseArgs::run(args).await,
+            Self::CheckEndorsement(_) => CheckEndorsementArgs::run(args).await,
+            Self::VerifyTransfer(_) => VerifyTransferArgs::run(args).await,
+            Self::Ledger(_) => LedgerArgs::run(args).await,
         }
     }
 }
@@ -0,0 +1,430 @@
+use crate::{
+    build_distribution_plan, check_payer_balance, estimate_max_tx_fee, fallback_lst_info,
+    get_distribution_plan_file_path, get_lst_info, get_recent_slot_duration_secs,
+    get_rewards_file_path, input_with_validation, print_transfer_summary, read_rewards_file,
+    record_in_ledger, subcmd::Subcmd, validate_bps, validate_epoch, validate_pubkey,
+    validate_rpc_url, validate_sol_amount, validate_stake_pool, write_json_file,
+    CheckPayerBalanceArgs, DistributionPlan, DistributionPlanArgs, RewardsSplitBps, TransferRecord,
+    DEFAULT_RESERVE_FLOOR_SOL, SOLANA_PUBLIC_RPC,
+};
+use clap::{command, Args};
+use colored::Colorize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    clock::DEFAULT_MS_PER_SLOT, commitment_config::CommitmentConfig, epoch_info::EpochInfo,
+    pubkey::Pubkey,
+};
+use spinners::{Spinner, Spinners};
+
+/// What a distribution is computed from, shared by `plan` and `transfer`
+#[derive(Args, Debug)]
+pub struct PlanOpts {
+    #[arg(long, help = "The identity pubkey of your validator")]
+    pub identity_pubkey: Option<String>,
+
+    #[arg(long, help = "The epoch to calculate rewards for")]
+    pub epoch: Option<u64>,
+
+    #[arg(long, help = "The stake pool account linked to your LST")]
+    pub stake_pool_pubkey: Option<String>,
+
+    #[arg(
+        long,
+        help = "Percentage of stake you want to consider for calculating the block rewards"
+    )]
+    pub total_rewards_pct: Option<String>,
+
+    #[arg(long, help = "Percentage of block rewards to share to LST holders")]
+    pub lst_rewards_pct: Option<String>,
+
+    #[arg(
+        long,
+        help = "Percentage of MEV rewards to share to LST holders. Only used if the rewards file has MEV rewards"
+    )]
+    pub mev_rewards_pct: Option<String>,
+
+    #[arg(
+        long,
+        help = "Path to a local sanctum-lst-list.toml to use instead of the cached list from GitHub"
+    )]
+    pub lst_list: Option<String>,
+}
+
+impl PlanOpts {
+    /// Prompts for the options that weren't provided and computes the distribution
+    /// paid by `payer_pubkey`. Returns `None` after printing why if it can't be made
+    pub async fn prompt_plan(
+        self,
+        rpc: &RpcClient,
+        payer_pubkey: Pubkey,
+        current_epoch_info: &EpochInfo,
+    ) -> Option<DistributionPlan> {
+        let Self {
+            identity_pubkey,
+            epoch,
+            stake_pool_pubkey,
+            total_rewards_pct,
+            lst_rewards_pct,
+            mev_rewards_pct,
+            lst_list,
+        } = self;
+
+        let identity_pubkey = match input_with_validation(
+            "Enter your validator's identity key:",
+            "Identity key",
+            None,
+            identity_pubkey,
+            validate_pubkey,
+        ) {
+            Ok(pubkey) => pubkey,
+            Err(_) => {
+                println!("{}", "Error: Invalid identity pubkey".red());
+                return None;
+            }
+        };
+
+        let epoch = match input_with_validation(
+            "Enter the epoch to calculate rewards for:",
+            &(current_epoch_info.epoch - 1).to_string(),
+            Some((current_epoch_info.epoch - 1).to_string()),
+            epoch.map(|e| e.to_string()),
+            |input| validate_epoch(input, current_epoch_info.epoch),
+        ) {
+            Ok(e) => e,
+            Err(_) => {
+                println!("{}", "Error: Invalid epoch".red());
+                return None;
+            }
+        };
+
+        println!("{}", "=".repeat(80));
+
+        let rewards_file_path = match get_rewards_file_path(&identity_pubkey, epoch) {
+            Ok(path) => path,
+            Err(err) => {
+                println!("{}", format!("Error: {}", err).red());
+                return None;
+            }
+        };
+
+        let rewards = match read_rewards_file(&rewards_file_path) {
+            Ok(Some(rewards)) => rewards,
+            Ok(None) => {
+                println!(
+                    "{}",
+                    format!("Failed to find rewards at {}", rewards_file_path).blue()
+                );
+                println!(
+                    "{}",
+                    "Please run the calculate command first to generate the rewards file."
+                        .blue()
+                        .bold()
+                );
+
+                println!("{}", "=".repeat(80));
+                return None;
+            }
+            Err(err) => {
+                println!("{}", format!("Error: {}", err).red());
+                return None;
+            }
+        };
+
+        let stake_pool_pubkey = match input_with_validation(
+            "Enter the stake pool pubkey:",
+            "Stake pool pubkey",
+            None,
+            stake_pool_pubkey,
+            validate_pubkey,
+        ) {
+            Ok(pubkey) => pubkey,
+            Err(_) => {
+                println!("{}", "Error: Invalid pubkey".red());
+                return None;
+            }
+        };
+
+        let mut sp = Spinner::new(Spinners::Dots, "Validating stake pool...".to_string());
+
+        let (stake_pool, stake_pool_account) =
+            match validate_stake_pool(rpc, &stake_pool_pubkey, current_epoch_info.epoch).await {
+                Ok(validated) => {
+                    sp.stop_with_message("✓ Stake pool validated".green().bold().to_string());
+                    validated
+                }
+                Err(err) => {
+                    sp.stop_with_message(err.red().to_string());
+                    return None;
+                }
+            };
+
+        let lst_info = match get_lst_info(&stake_pool_pubkey.to_string(), lst_list.as_deref()).await
+        {
+            Ok((lst_info, Some(warning))) => {
+                println!("{}", format!("⚠️ {}", warning).yellow());
+                lst_info
+            }
+            Ok((lst_info, None)) => lst_info,
+            Err(err) => {
+                let message = format!("⚠ Failed to load the LST list ({})", err);
+                println!("{}", message.yellow());
+                println!("{}", "Showing the LST mint address instead".yellow());
+                Some(fallback_lst_info(&stake_pool, &stake_pool_pubkey))
+            }
+        };
+
+        let lst_info = match lst_info {
+            Some(info) => info,
+            None => {
+                println!(
+                    "{}",
+                    "⚠ We could not find a LST for the specified address".yellow()
+                );
+                return None;
+            }
+        };
+
+        let total_rewards_bps = match input_with_validation(
+            "Enter the percentage of stake you want to consider for calculating the block rewards:",
+            "75",
+            None,
+            total_rewards_pct,
+            validate_bps,
+        ) {
+            Ok(bps) => bps,
+            Err(_) => {
+                println!("{}", "Error: Invalid total rewards BPS".red());
+                return None;
+            }
+        };
+
+        let lst_rewards_bps = match input_with_validation(
+            "Enter the percentage of block rewards to share:",
+            "100",
+            None,
+            lst_rewards_pct,
+            validate_bps,
+        ) {
+            Ok(bps) => bps,
+            Err(_) => {
+                println!("{}", "Error: Invalid LST rewards BPS".red());
+                return None;
+            }
+        };
+
+        let mev_rewards_bps = match rewards.mev_rewards {
+            Some(_) => match input_with_validation(
+                "Enter the percentage of MEV rewards to share:",
+                "100",
+                None,
+                mev_rewards_pct,
+                validate_bps,
+            ) {
+                Ok(bps) => bps,
+                Err(_) => {
+                    println!("{}", "Error: Invalid MEV rewards BPS".red());
+                    return None;
+                }
+            },
+            None => 0,
+        };
+
+        let reserve_balance = match rpc.get_balance(&stake_pool.reserve_stake).await {
+            Ok(balance) => balance,
+            Err(_) => {
+                println!("{}", "Error: Failed to fetch data from RPC".red());
+                return None;
+            }
+        };
+
+        let slot_duration_secs = match get_recent_slot_duration_secs(rpc).await {
+            Ok(secs) => secs,
+            Err(err) => {
+                println!(
+                    "{}",
+                    format!("⚠ {}, assuming the default slot duration", err).yellow()
+                );
+                DEFAULT_MS_PER_SLOT as f64 / 1_000.0
+            }
+        };
+
+        match build_distribution_plan(
+            DistributionPlanArgs {
+                identity_pubkey,
+                stake_pool_pubkey,
+                payer_pubkey,
+                epoch,
+                bps: RewardsSplitBps {
+                    total_rewards_bps,
+                    lst_rewards_bps,
+                    mev_rewards_bps,
+                },
+                lst_info,
+                reserve_balance,
+                epoch_duration_secs: slot_duration_secs * current_epoch_info.slots_in_epoch as f64,
+            },
+            &rewards,
+            &stake_pool,
+            &stake_pool_account,
+        ) {
+            Ok(plan) => Some(plan),
+            Err(err) => {
+                println!("{}", err.red());
+                None
+            }
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+#[command(
+    long_about = "Compute a distribution of block rewards and write it to a plan file for review. Use the `execute` command to send it"
+)]
+pub struct PlanArgs {
+    #[arg(
+        long,
+        help = "Pubkey of the account rewards will be transferred from. The plan must be executed with its keypair"
+    )]
+    pub payer_pubkey: Option<String>,
+
+    #[command(flatten)]
+    pub plan: PlanOpts,
+
+    #[arg(
+        long,
+        help = "Minimum SOL balance the payer must keep after the transfer, on top of the rent exempt minimum and tx fees",
+        default_value = DEFAULT_RESERVE_FLOOR_SOL
+    )]
+    pub reserve_floor: String,
+
+    #[arg(long, help = "Path to write the plan to")]
+    pub out_file: Option<String>,
+}
+
+impl PlanArgs {
+    pub async fn run(args: crate::Args) {
+        let Self {
+            payer_pubkey,
+            plan,
+            reserve_floor,
+            out_file,
+        } = match args.subcmd {
+            Subcmd::Plan(a) => a,
+            _ => unreachable!(),
+        };
+
+        let payer_pubkey = match input_with_validation(
+            "Enter the pubkey rewards will be transferred from:",
+            "Payer pubkey",
+            None,
+            payer_pubkey,
+            validate_pubkey,
+        ) {
+            Ok(pubkey) => pubkey,
+            Err(_) => {
+                println!("{}", "Error: Invalid payer pubkey".red());
+                return;
+            }
+        };
+
+        let rpc_url = match input_with_validation(
+            "Enter the RPC URL:",
+            "RPC URL",
+            Some(SOLANA_PUBLIC_RPC.to_string()),
+            args.rpc_url,
+            validate_rpc_url,
+        ) {
+            Ok(url) => url,
+            Err(_) => {
+                println!("{}", "Error: Invalid RPC URL".red());
+                return;
+            }
+        };
+
+        let rpc = RpcClient::new_with_commitment(
+            rpc_url,
+            args.commitment.unwrap_or(CommitmentConfig::confirmed()),
+        );
+
+        let reserve_floor = match validate_sol_amount(&reserve_floor) {
+            Ok(lamports) => lamports,
+            Err(err) => {
+                println!("{}", err.red());
+                return;
+            }
+        };
+
+        let (current_epoch_info, payer_balance, rent_exempt_minimum) = match tokio::try_join!(
+            rpc.get_epoch_info(),
+            rpc.get_balance(&payer_pubkey),
+            rpc.get_minimum_balance_for_rent_exemption(0)
+        ) {
+            Ok(result) => result,
+            Err(_) => {
+                println!("{}", "Error: Failed to fetch data from RPC".red());
+                return;
+            }
+        };
+
+        let plan = match plan
+            .prompt_plan(&rpc, payer_pubkey, &current_epoch_info)
+            .await
+        {
+            Some(plan) => plan,
+            None => return,
+        };
+
+        println!("{}", "=".repeat(80));
+
+        print_transfer_summary(plan.summary(payer_balance));
+
+        println!("{}", "=".repeat(80));
+
+        // The payer can still be topped up before the plan is executed
+        if let Err(err) = check_payer_balance(CheckPayerBalanceArgs {
+            payer_balance,
+            transfer_lamports: plan.split.transfer_lamports,
+            rent_exempt_minimum,
+            estimated_fee: estimate_max_tx_fee(1, args.fee_limit_cb),
+            reserve_floor,
+        }) {
+            println!("{}", format!("⚠️ {}", err).yellow().bold());
+            println!("{}", "Top up the payer before executing the plan.".yellow());
+            println!("{}", "=".repeat(80));
+        }
+
+        let out_file = match out_file.map_or_else(
+            || {
+                validate_pubkey(&plan.identity_pubkey).and_then(|identity_pubkey| {
+                    get_distribution_plan_file_path(&identity_pubkey, plan.epoch)
+                })
+            },
+            Ok,
+        ) {
+            Ok(path) => path,
+            Err(err) => {
+                println!("{}", format!("Error: {}", err).red());
+                return;
+            }
+        };
+
+        if let Err(err) = write_json_file(&out_file, &plan) {
+            println!("{}", format!("Error: {}", err).red());
+            return;
+        }
+        record_in_ledger(|ledger| ledger.record_transfer(&TransferRecord::from_plan(&plan, None)));
+
+        println!(
+            "{}",
+            format!("✓ Saved distribution plan to {}", out_file)
+                .green()
+                .bold()
+        );
+        println!(
+            "{}",
+            "Once it has been reviewed, run the `execute` command with this file to send it."
+                .blue()
+        );
+        println!("{}", "=".repeat(80));
+    }
+}
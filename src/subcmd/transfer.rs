@@ -1,18 +1,18 @@
 use crate::{
-    checked_pct, get_lst_info, get_rewards_file_path, handle_tx_full, input_string,
-    input_with_validation, print_transfer_summary, subcmd::Subcmd,
-    transfer_to_reserve_and_update_stake_pool_balance_ixs, validate_bps, validate_epoch,
-    validate_pubkey, validate_rpc_url, with_auto_cb_ixs, PrintTransferSummaryArgs,
+    encode_tx, get_durable_nonce_blockhash, get_signed_transfer_file_path, input_with_validation,
+    prepare_plan_execution, read_json_file, record_in_ledger, send_plan, sign_tx_full,
+    subcmd::Subcmd, validate_pubkey, validate_rpc_url, validate_sol_amount,
+    with_nonce_and_auto_cb_ixs, with_nonce_and_fixed_cb_ixs, write_json_file, DistributionPlan,
+    PlanInstruction, PlanOpts, PrepareExecutionArgs, SignedTransferFile, TransferRecord,
+    TransferStatus, DEFAULT_MAX_DISTRIBUTION_SIGNATURES, DEFAULT_RESERVE_FLOOR_SOL,
     SOLANA_PUBLIC_RPC,
 };
 use clap::{command, Args};
 use colored::Colorize;
-use inquire::Confirm;
-use sanctum_solana_cli_utils::{parse_named_signer, ParseNamedSigner, TxSendMode};
-use serde_json::Value;
+use sanctum_solana_cli_utils::{parse_named_signer, ParseNamedSigner};
 use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
-use std::{fs::File, path::Path, str::FromStr};
+use solana_sdk::{commitment_config::CommitmentConfig, hash::Hash, signer::Signer};
+use std::str::FromStr;
 
 #[derive(Args, Debug)]
 #[command(long_about = "Transfer block rewards to the stake pool reserve")]
@@ -23,65 +23,201 @@ pub struct TransferArgs {
     )]
     pub payer: String,
 
-    #[arg(long, help = "The identity pubkey of your validator")]
-    pub identity_pubkey: Option<String>,
+    #[command(flatten)]
+    pub plan: PlanOpts,
 
-    #[arg(long, help = "The epoch to calculate rewards for")]
-    pub epoch: Option<u64>,
+    #[arg(
+        long,
+        help = "Minimum SOL balance the payer must keep after the transfer, on top of the rent exempt minimum and tx fees",
+        default_value = DEFAULT_RESERVE_FLOOR_SOL
+    )]
+    pub reserve_floor: String,
+
+    #[arg(
+        long,
+        help = "Skip the pre-flight check that the payer can afford the transfer while staying above the reserve floor"
+    )]
+    pub skip_balance_check: bool,
+
+    #[arg(long, help = "Skip the confirmation prompt before transferring")]
+    pub yes: bool,
+
+    #[arg(
+        long,
+        help = "Number of the stake pool's most recent transactions to search for an earlier distribution",
+        default_value_t = DEFAULT_MAX_DISTRIBUTION_SIGNATURES
+    )]
+    pub max_signatures: usize,
 
-    #[arg(long, help = "The stake pool account linked to your LST")]
-    pub stake_pool_pubkey: Option<String>,
+    #[arg(
+        long,
+        value_name = "PLAN_FILE",
+        help = "Sign the distribution of a plan file written by `plan` and write it to a file instead of sending it, without re-validating the plan. Use the `broadcast` command to send it later",
+        conflicts_with_all = [
+            "identity_pubkey",
+            "epoch",
+            "stake_pool_pubkey",
+            "total_rewards_pct",
+            "lst_rewards_pct",
+            "mev_rewards_pct",
+            "lst_list",
+            "skip_balance_check",
+            "yes",
+            "max_signatures"
+        ]
+    )]
+    pub sign_only: Option<String>,
+
+    #[arg(
+        long,
+        help = "Blockhash to sign against when using --sign-only. If --nonce-account is also provided, this must be the nonce account's stored blockhash. Fetched from the RPC if not provided",
+        requires = "sign_only"
+    )]
+    pub blockhash: Option<String>,
+
+    #[arg(
+        long,
+        help = "Durable nonce account to sign against when using --sign-only",
+        requires = "sign_only"
+    )]
+    pub nonce_account: Option<String>,
 
     #[arg(
         long,
-        help = "Percentage of stake you want to consider for calculating the block rewards"
+        help = "Path to the nonce authority keypair. Defaults to the payer",
+        requires = "nonce_account"
     )]
-    pub total_rewards_pct: Option<u64>,
+    pub nonce_authority: Option<String>,
 
-    #[arg(long, help = "Percentage of block rewards to share to LST holders")]
-    pub lst_rewards_pct: Option<u64>,
+    #[arg(
+        long,
+        help = "Compute unit limit of the transaction when using --sign-only, including the nonce instruction. Simulated over RPC if not provided",
+        requires = "sign_only"
+    )]
+    pub compute_unit_limit: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Path to write the signed transaction to when using --sign-only",
+        requires = "sign_only"
+    )]
+    pub out_file: Option<String>,
 }
 
-impl TransferArgs {
-    pub async fn run(args: crate::Args) {
-        let Self {
-            payer,
-            identity_pubkey,
-            epoch,
-            stake_pool_pubkey,
-            total_rewards_pct,
-            lst_rewards_pct,
-        } = match args.subcmd {
-            Subcmd::Transfer(a) => a,
-            _ => unreachable!(),
-        };
+/// What `transfer --sign-only` signs and how
+pub struct SignPlanArgs {
+    pub plan_file: String,
+    /// Only prompted for if the blockhash or the compute unit limit must be fetched
+    pub rpc_url: Option<String>,
+    pub commitment: Option<CommitmentConfig>,
+    pub fee_limit_cb: u64,
+    pub blockhash: Option<String>,
+    pub nonce_account: Option<String>,
+    pub nonce_authority: Option<String>,
+    pub compute_unit_limit: Option<u32>,
+    pub out_file: Option<String>,
+}
 
-        let identity_pubkey = match input_string(
-            "Enter your validator's identity key:",
-            "Identity key",
-            None,
-            identity_pubkey,
-        ) {
-            Ok(key) => key,
-            Err(_) => {
-                println!("{}", "Error: Invalid identity key".red());
-                return;
-            }
-        };
+/// Signs the distribution of a plan file and writes it to a signed transfer file.
+/// Makes no RPC call if the blockhash and the compute unit limit are given
+pub async fn sign_plan(payer_keypair: &dyn Signer, args: SignPlanArgs) {
+    let SignPlanArgs {
+        plan_file,
+        rpc_url,
+        commitment,
+        fee_limit_cb,
+        blockhash,
+        nonce_account,
+        nonce_authority,
+        compute_unit_limit,
+        out_file,
+    } = args;
+
+    let plan: DistributionPlan = match read_json_file(&plan_file) {
+        Ok(plan) => plan,
+        Err(err) => {
+            println!("{}", format!("Error: {}", err).red());
+            return;
+        }
+    };
+
+    let payer_pubkey = payer_keypair.pubkey();
+
+    if payer_pubkey.to_string() != plan.payer_pubkey {
+        println!(
+            "{}",
+            format!(
+                "Error: The plan must be signed by its payer {}, not {}",
+                plan.payer_pubkey, payer_pubkey
+            )
+            .red()
+        );
+        return;
+    }
 
-        let identity_pubkey = match Pubkey::from_str(&identity_pubkey) {
-            Ok(pubkey) => pubkey,
-            Err(_) => {
-                println!("{}", "Error: Invalid identity pubkey".red());
-                return;
-            }
-        };
+    let ixs = match plan
+        .instructions
+        .iter()
+        .map(PlanInstruction::to_instruction)
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(ixs) => ixs,
+        Err(err) => {
+            println!("{}", err.red());
+            return;
+        }
+    };
 
-        let rpc_url = match input_with_validation(
+    let blockhash = match blockhash.as_deref().map(Hash::from_str).transpose() {
+        Ok(hash) => hash,
+        Err(_) => {
+            println!("{}", "Error: Invalid blockhash".red());
+            return;
+        }
+    };
+
+    let nonce_account = match nonce_account.as_deref().map(validate_pubkey).transpose() {
+        Ok(pubkey) => pubkey,
+        Err(_) => {
+            println!("{}", "Error: Invalid nonce account".red());
+            return;
+        }
+    };
+
+    let nonce_authority_keypair = match nonce_authority
+        .as_ref()
+        .map(|arg| {
+            parse_named_signer(ParseNamedSigner {
+                name: "nonce authority",
+                arg,
+            })
+        })
+        .transpose()
+    {
+        Ok(keypair) => keypair,
+        Err(_) => {
+            println!("{}", "Error: Invalid nonce authority keypair".red());
+            return;
+        }
+    };
+
+    let nonce = nonce_account.map(|nonce_account| {
+        (
+            nonce_account,
+            nonce_authority_keypair
+                .as_ref()
+                .map_or(payer_pubkey, |k| k.pubkey()),
+        )
+    });
+
+    // Creating the client makes no RPC call, so only ask for the URL if it's used
+    let needs_rpc = blockhash.is_none() || (compute_unit_limit.is_none() && fee_limit_cb != 0);
+    let rpc_url = if needs_rpc {
+        match input_with_validation(
             "Enter the RPC URL:",
             "RPC URL",
             Some(SOLANA_PUBLIC_RPC.to_string()),
-            args.rpc_url,
+            rpc_url,
             validate_rpc_url,
         ) {
             Ok(url) => url,
@@ -89,232 +225,239 @@ impl TransferArgs {
                 println!("{}", "Error: Invalid RPC URL".red());
                 return;
             }
-        };
-
-        let rpc = RpcClient::new_with_commitment(
-            rpc_url,
-            args.commitment.unwrap_or(CommitmentConfig::confirmed()),
-        );
-
-        let payer_keypair = match parse_named_signer(ParseNamedSigner {
-            name: "payer",
-            arg: &payer,
-        }) {
-            Ok(keypair) => keypair,
-            Err(_) => {
-                println!("{}", "Error: Invalid identity keypair".red());
-                return;
-            }
-        };
-
-        let payer_pubkey = payer_keypair.pubkey();
-
-        let (current_epoch_info, payer_balance) =
-            match tokio::try_join!(rpc.get_epoch_info(), rpc.get_balance(&payer_pubkey)) {
-                Ok(result) => result,
-                Err(_) => {
-                    println!("{}", "Error: Failed to fetch data from RPC".red());
+        }
+    } else {
+        rpc_url.unwrap_or_else(|| SOLANA_PUBLIC_RPC.to_string())
+    };
+
+    let rpc = RpcClient::new_with_commitment(
+        rpc_url,
+        commitment.unwrap_or(CommitmentConfig::confirmed()),
+    );
+
+    let final_ixs = match compute_unit_limit {
+        Some(compute_unit_limit) => {
+            with_nonce_and_fixed_cb_ixs(ixs, nonce, compute_unit_limit, fee_limit_cb)
+        }
+        None => with_nonce_and_auto_cb_ixs(&rpc, &payer_pubkey, ixs, nonce, fee_limit_cb).await,
+    };
+
+    let hash = match (nonce_account, blockhash) {
+        (_, Some(hash)) => hash,
+        (Some(nonce_account), None) => {
+            match get_durable_nonce_blockhash(&rpc, &nonce_account).await {
+                Ok(hash) => hash,
+                Err(err) => {
+                    println!("{}", err.red());
                     return;
                 }
-            };
-
-        let epoch = match input_with_validation(
-            "Enter the epoch to calculate rewards for:",
-            &(current_epoch_info.epoch - 1).to_string(),
-            Some((current_epoch_info.epoch - 1).to_string()),
-            epoch.map(|e| e.to_string()),
-            |input| validate_epoch(input, current_epoch_info.epoch),
-        ) {
-            Ok(e) => e,
-            Err(_) => {
-                println!("{}", "Error: Invalid epoch".red());
-                return;
-            }
-        };
-
-        println!("{}", "=".repeat(80));
-
-        let rewards_file_path = match get_rewards_file_path(&identity_pubkey, epoch) {
-            Ok(path) => path,
-            Err(err) => {
-                println!("{}", format!("Error: {}", err).red());
-                return;
             }
-        };
-
-        if !Path::new(&rewards_file_path).exists() {
-            println!(
-                "{}",
-                format!("Failed to find rewards at {}", rewards_file_path).blue()
-            );
+        }
+        (None, None) => {
             println!(
                 "{}",
-                "Please run the calculate command first to generate the rewards file."
-                    .blue()
+                "⚠️ No --blockhash or --nonce-account provided, signing against the latest blockhash. The transaction must be broadcast within ~60 seconds."
+                    .yellow()
                     .bold()
             );
+            match rpc.get_latest_blockhash().await {
+                Ok(hash) => hash,
+                Err(_) => {
+                    println!("{}", "Error: Failed to fetch latest blockhash".red());
+                    return;
+                }
+            }
+        }
+    };
 
-            println!("{}", "=".repeat(80));
-            return;
+    let mut signers: Vec<&dyn Signer> = vec![payer_keypair];
+    if let Some(keypair) = nonce_authority_keypair.as_ref() {
+        if keypair.pubkey() != payer_pubkey {
+            signers.push(keypair.as_ref());
         }
+    }
 
-        let rewards: Value = match File::open(rewards_file_path.clone())
-            .map_err(|_| "Failed to open rewards file")
-            .and_then(|file| {
-                serde_json::from_reader(file).map_err(|_| "Failed to parse rewards file")
-            }) {
-            Ok(value) => value,
+    let transaction =
+        match sign_tx_full(&final_ixs, &[], hash, &mut signers).and_then(|tx| encode_tx(&tx)) {
+            Ok(tx) => tx,
             Err(err) => {
-                println!("{}", format!("Error: {}", err).red());
+                println!("{}", err.red());
                 return;
             }
         };
 
-        let total_block_rewards = match rewards["total_block_rewards"].as_u64() {
-            Some(rewards) => rewards,
-            None => {
-                println!("{}", "Error: Invalid rewards file format".red());
-                return;
-            }
-        };
+    let out_file = match out_file.map_or_else(
+        || {
+            validate_pubkey(&plan.identity_pubkey).and_then(|identity_pubkey| {
+                get_signed_transfer_file_path(&identity_pubkey, plan.epoch)
+            })
+        },
+        Ok,
+    ) {
+        Ok(path) => path,
+        Err(err) => {
+            println!("{}", format!("Error: {}", err).red());
+            return;
+        }
+    };
+
+    let signed_transfer = SignedTransferFile {
+        identity_pubkey: plan.identity_pubkey.clone(),
+        stake_pool_pubkey: plan.stake_pool_pubkey.clone(),
+        payer_pubkey: plan.payer_pubkey.clone(),
+        // The payer's balance isn't known offline, `broadcast` shows its current one
+        summary: plan.summary(0),
+        nonce_account: nonce_account.map(|pk| pk.to_string()),
+        blockhash: hash.to_string(),
+        transaction,
+    };
+
+    if let Err(err) = write_json_file(&out_file, &signed_transfer) {
+        println!("{}", format!("Error: {}", err).red());
+        return;
+    }
+    record_in_ledger(|ledger| {
+        ledger.record_transfer(&TransferRecord::from_signed_transfer(
+            &signed_transfer,
+            TransferStatus::Signed,
+        )?)
+    });
+
+    println!(
+        "{}",
+        format!("✓ Saved signed transaction to {}", out_file)
+            .green()
+            .bold()
+    );
+    println!(
+        "{}",
+        "Run the `broadcast` command with this file to send it.".blue()
+    );
+    println!("{}", "=".repeat(80));
+}
 
-        let stake_pool_pubkey = match input_with_validation(
-            "Enter the stake pool pubkey:",
-            "Stake pool pubkey",
-            None,
-            stake_pool_pubkey,
-            validate_pubkey,
-        ) {
-            Ok(pubkey) => pubkey,
-            Err(_) => {
-                println!("{}", "Error: Invalid pubkey".red());
-                return;
-            }
+impl TransferArgs {
+    pub async fn run(args: crate::Args) {
+        let Self {
+            payer,
+            plan,
+            reserve_floor,
+            skip_balance_check,
+            yes,
+            max_signatures,
+            sign_only,
+            blockhash,
+            nonce_account,
+            nonce_authority,
+            compute_unit_limit,
+            out_file,
+        } = match args.subcmd {
+            Subcmd::Transfer(a) => a,
+            _ => unreachable!(),
         };
 
-        let (lst_name, lst_symbol) = match get_lst_info(&stake_pool_pubkey.to_string()).await {
-            Ok(info) => info,
+        let payer_keypair = match parse_named_signer(ParseNamedSigner {
+            name: "payer",
+            arg: &payer,
+        }) {
+            Ok(keypair) => keypair,
             Err(_) => {
-                println!(
-                    "{}",
-                    "⚠ We could not find a LST for the specified address".yellow()
-                );
+                println!("{}", "Error: Invalid payer keypair".red());
                 return;
             }
         };
 
-        let total_rewards_bps = match input_with_validation(
-            "Enter the percentage of stake you want to consider for calculating the block rewards:",
-            "75",
-            None,
-            total_rewards_pct.map(|bps| bps.to_string()),
-            validate_bps,
-        ) {
-            Ok(bps) => bps,
-            Err(_) => {
-                println!("{}", "Error: Invalid total rewards BPS".red());
-                return;
-            }
-        };
+        if let Some(plan_file) = sign_only {
+            sign_plan(
+                payer_keypair.as_ref(),
+                SignPlanArgs {
+                    plan_file,
+                    rpc_url: args.rpc_url,
+                    commitment: args.commitment,
+                    fee_limit_cb: args.fee_limit_cb,
+                    blockhash,
+                    nonce_account,
+                    nonce_authority,
+                    compute_unit_limit,
+                    out_file,
+                },
+            )
+            .await;
+            return;
+        }
 
-        let lst_rewards_bps = match input_with_validation(
-            "Enter the percentage of block rewards to share:",
-            "100",
-            None,
-            lst_rewards_pct.map(|bps| bps.to_string()),
-            validate_bps,
+        let rpc_url = match input_with_validation(
+            "Enter the RPC URL:",
+            "RPC URL",
+            Some(SOLANA_PUBLIC_RPC.to_string()),
+            args.rpc_url,
+            validate_rpc_url,
         ) {
-            Ok(bps) => bps,
+            Ok(url) => url,
             Err(_) => {
-                println!("{}", "Error: Invalid LST rewards BPS".red());
-                return;
-            }
-        };
-
-        // Calculate stake pool's share
-        let stake_pool_rewards = match checked_pct(total_block_rewards, total_rewards_bps) {
-            Some(rewards) => rewards,
-            None => {
-                println!("{}", "Error: Error in calculating stake pool rewards".red());
-                return;
-            }
-        };
-
-        // Calculate LST holders' share
-        let lst_rewards = match checked_pct(stake_pool_rewards, lst_rewards_bps) {
-            Some(rewards) => rewards,
-            None => {
-                println!("{}", "Error: Overflow in calculating LST rewards".red());
+                println!("{}", "Error: Invalid RPC URL".red());
                 return;
             }
         };
 
-        println!("{}", "=".repeat(80));
-
-        print_transfer_summary(PrintTransferSummaryArgs {
-            epoch,
-            payer_balance,
-            total_block_rewards,
-            total_rewards_bps,
-            stake_pool_rewards,
-            lst_rewards_bps,
-            lst_rewards,
-        });
-
-        println!("{}", "=".repeat(80));
-
-        let confirm_message = format!(
-            "Do you wish to continue to transfer your block rewards to {} ({})?",
-            lst_name.magenta(),
-            lst_symbol.magenta()
+        let rpc = RpcClient::new_with_commitment(
+            rpc_url,
+            args.commitment.unwrap_or(CommitmentConfig::confirmed()),
         );
 
-        let ans = Confirm::new(&confirm_message.blue().bold())
-            .with_default(true)
-            .prompt();
+        let payer_pubkey = payer_keypair.pubkey();
 
-        match ans {
-            Ok(false) => {
+        let reserve_floor = match validate_sol_amount(&reserve_floor) {
+            Ok(lamports) => lamports,
+            Err(err) => {
+                println!("{}", err.red());
                 return;
             }
+        };
+
+        let current_epoch_info = match rpc.get_epoch_info().await {
+            Ok(epoch_info) => epoch_info,
             Err(_) => {
-                println!("Error: Something went wrong.");
+                println!("{}", "Error: Failed to fetch data from RPC".red());
                 return;
             }
-            _ => (),
-        }
-
-        println!("{}", "=".repeat(80));
+        };
 
-        let send_mode = args.send_mode;
-        let fee_limit_cb = args.fee_limit_cb;
+        let plan = match plan
+            .prompt_plan(&rpc, payer_pubkey, &current_epoch_info)
+            .await
+        {
+            Some(plan) => plan,
+            None => return,
+        };
 
-        let final_ixs = match transfer_to_reserve_and_update_stake_pool_balance_ixs(
+        let ixs = match prepare_plan_execution(
             &rpc,
-            &payer_pubkey,
-            &identity_pubkey,
-            &stake_pool_pubkey,
-            lst_rewards,
-            epoch,
+            &plan,
+            PrepareExecutionArgs {
+                payer_pubkey,
+                reserve_floor,
+                skip_balance_check,
+                num_signatures: 1,
+                fee_limit_cb: args.fee_limit_cb,
+                yes,
+                max_signatures,
+            },
         )
         .await
         {
-            Ok(ixs) => ixs,
-            Err(err) => {
-                println!("{}", err);
-                return;
-            }
+            Some((ixs, _)) => ixs,
+            None => return,
         };
 
-        let final_ixs = match send_mode {
-            TxSendMode::DumpMsg => final_ixs,
-            _ => with_auto_cb_ixs(&rpc, &identity_pubkey, final_ixs, &[], fee_limit_cb).await,
-        };
-
-        if send_mode == TxSendMode::DumpMsg {
-            println!("{}", "Transaction Message:".blue().bold());
-        }
-
-        handle_tx_full(&rpc, send_mode, &final_ixs, &[], &mut [&payer_keypair]).await;
+        send_plan(
+            &rpc,
+            args.send_mode,
+            args.fee_limit_cb,
+            &plan,
+            ixs,
+            payer_keypair.as_ref(),
+        )
+        .await;
     }
 }
@@ -0,0 +1,100 @@
+use crate::{
+    read_json_file, subcmd::Subcmd, validate_pubkey, verify_attestation, RewardsAttestation,
+};
+use clap::{command, Args};
+use colored::Colorize;
+use sanctum_solana_cli_utils::TokenAmt;
+
+#[derive(Args, Debug)]
+#[command(long_about = "Verify an attestation written by the `attest` command")]
+pub struct VerifyArgs {
+    #[arg(help = "Path to the attestation file")]
+    pub file: String,
+
+    #[arg(long, help = "Identity the attestation must be signed by")]
+    pub identity_pubkey: String,
+}
+
+impl VerifyArgs {
+    pub async fn run(args: crate::Args) {
+        let Self {
+            file,
+            identity_pubkey,
+        } = match args.subcmd {
+            Subcmd::Verify(a) => a,
+            _ => unreachable!(),
+        };
+
+        let attestation: RewardsAttestation = match read_json_file(&file) {
+            Ok(attestation) => attestation,
+            Err(err) => {
+                println!("{}", format!("Error: {}", err).red());
+                std::process::exit(1);
+            }
+        };
+
+        // The file names its own identity, so a valid signature alone proves nothing
+        // about who the rewards belong to
+        let identity_pubkey = match validate_pubkey(&identity_pubkey) {
+            Ok(pubkey) => pubkey,
+            Err(err) => {
+                println!("{}", err.red());
+                std::process::exit(1);
+            }
+        };
+
+        if attestation.identity_pubkey != identity_pubkey.to_string() {
+            println!(
+                "{}",
+                format!(
+                    "Error: The attestation is by {}, not {}",
+                    attestation.identity_pubkey, identity_pubkey
+                )
+                .red()
+            );
+            std::process::exit(1);
+        }
+
+        if let Err(err) = verify_attestation(&attestation) {
+            println!("{}", err.red());
+            std::process::exit(1);
+        }
+
+        println!("{}", "=".repeat(80));
+        println!(
+            "{}{}",
+            "Identity: ".blue().bold(),
+            attestation.identity_pubkey.bold()
+        );
+        println!(
+            "{}{}",
+            "Epoch: ".blue().bold(),
+            attestation.epoch.to_string().bold()
+        );
+        println!(
+            "{}{}",
+            "Total block rewards: ".blue().bold(),
+            format!(
+                "{} SOL",
+                TokenAmt {
+                    amt: attestation.total_block_rewards,
+                    decimals: 9
+                }
+            )
+            .bold()
+        );
+        println!(
+            "{}{}",
+            "Leader slots: ".blue().bold(),
+            attestation.num_leader_slots.to_string().bold()
+        );
+        println!("{}{}", "Source: ".blue().bold(), attestation.source.bold());
+        println!(
+            "{}",
+            "✓ Valid attestation signed by the validator identity"
+                .green()
+                .bold()
+        );
+        println!("{}", "=".repeat(80));
+    }
+}
@@ -0,0 +1,277 @@
+use crate::{
+    calculate_rewards_split, get_rewards_file_path, get_stake_pool, get_successful_transaction,
+    input_with_validation, parse_distribution_tx, read_rewards_file, subcmd::Subcmd, validate_bps,
+    validate_pubkey, validate_rpc_url, verify_distribution_transfer, RewardsSplitBps,
+    SOLANA_PUBLIC_RPC,
+};
+use clap::{command, Args};
+use colored::Colorize;
+use sanctum_solana_cli_utils::TokenAmt;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, signature::Signature};
+use spinners::{Spinner, Spinners};
+use std::str::FromStr;
+
+#[derive(Args, Debug)]
+#[command(
+    long_about = "Verify a sent rewards transfer against the local rewards file and the percentages it was made with"
+)]
+pub struct VerifyTransferArgs {
+    #[arg(long, help = "Signature of the transfer transaction")]
+    pub signature: String,
+
+    #[arg(
+        long,
+        help = "The stake pool the rewards must have been transferred to. Defaults to the one in the transfer's memo"
+    )]
+    pub stake_pool_pubkey: Option<String>,
+
+    #[arg(
+        long,
+        help = "Percentage of stake considered for calculating the block rewards"
+    )]
+    pub total_rewards_pct: Option<String>,
+
+    #[arg(long, help = "Percentage of block rewards shared to LST holders")]
+    pub lst_rewards_pct: Option<String>,
+
+    #[arg(
+        long,
+        help = "Percentage of MEV rewards shared to LST holders. Only used if the rewards file has MEV rewards"
+    )]
+    pub mev_rewards_pct: Option<String>,
+}
+
+impl VerifyTransferArgs {
+    pub async fn run(args: crate::Args) {
+        let Self {
+            signature,
+            stake_pool_pubkey,
+            total_rewards_pct,
+            lst_rewards_pct,
+            mev_rewards_pct,
+        } = match args.subcmd {
+            Subcmd::VerifyTransfer(a) => a,
+            _ => unreachable!(),
+        };
+
+        let signature = match Signature::from_str(&signature) {
+            Ok(signature) => signature,
+            Err(_) => {
+                println!("{}", "Error: Invalid signature".red());
+                std::process::exit(1);
+            }
+        };
+
+        let stake_pool_pubkey = match stake_pool_pubkey
+            .as_deref()
+            .map(validate_pubkey)
+            .transpose()
+        {
+            Ok(pubkey) => pubkey,
+            Err(err) => {
+                println!("{}", err.red());
+                std::process::exit(1);
+            }
+        };
+
+        let rpc_url = match input_with_validation(
+            "Enter the RPC URL:",
+            "RPC URL",
+            Some(SOLANA_PUBLIC_RPC.to_string()),
+            args.rpc_url,
+            validate_rpc_url,
+        ) {
+            Ok(url) => url,
+            Err(_) => {
+                println!("{}", "Error: Invalid RPC URL".red());
+                std::process::exit(1);
+            }
+        };
+
+        let rpc = RpcClient::new_with_commitment(
+            rpc_url,
+            args.commitment.unwrap_or(CommitmentConfig::confirmed()),
+        );
+
+        let mut sp = Spinner::new(Spinners::Dots, "Fetching transaction...".to_string());
+
+        let tx = match get_successful_transaction(&rpc, &signature).await {
+            Ok(Some(tx)) => {
+                sp.stop_with_message("✓ Transaction fetched".green().bold().to_string());
+                tx
+            }
+            Ok(None) => {
+                sp.stop_with_message(
+                    format!("Error: Transaction {} failed", signature)
+                        .red()
+                        .to_string(),
+                );
+                std::process::exit(1);
+            }
+            Err(err) => {
+                sp.stop_with_message(err.red().to_string());
+                std::process::exit(1);
+            }
+        };
+
+        let transfer = match parse_distribution_tx(&tx) {
+            Ok(transfer) => transfer,
+            Err(err) => {
+                println!("{}", err.red());
+                std::process::exit(1);
+            }
+        };
+        let memo = transfer.memo;
+
+        if let Some(stake_pool_pubkey) = stake_pool_pubkey {
+            if stake_pool_pubkey != memo.stake_pool_pubkey {
+                println!(
+                    "{}",
+                    format!(
+                        "Error: The transfer's memo names stake pool {}, not {}",
+                        memo.stake_pool_pubkey, stake_pool_pubkey
+                    )
+                    .red()
+                );
+                std::process::exit(1);
+            }
+        }
+
+        println!("{}", "=".repeat(80));
+        println!(
+            "{}{}",
+            "Identity: ".blue().bold(),
+            memo.identity_pubkey.to_string().bold()
+        );
+        println!(
+            "{}{}",
+            "Epoch: ".blue().bold(),
+            memo.epoch.to_string().bold()
+        );
+        println!(
+            "{}{}",
+            "Stake pool: ".blue().bold(),
+            memo.stake_pool_pubkey.to_string().bold()
+        );
+        println!(
+            "{}{}",
+            "Transferred: ".blue().bold(),
+            format!(
+                "{} SOL from {} to {}",
+                TokenAmt {
+                    amt: transfer.lamports,
+                    decimals: 9
+                },
+                transfer.from,
+                transfer.to
+            )
+            .bold()
+        );
+        println!("{}", "=".repeat(80));
+
+        let rewards_file_path = match get_rewards_file_path(&memo.identity_pubkey, memo.epoch) {
+            Ok(path) => path,
+            Err(err) => {
+                println!("{}", format!("Error: {}", err).red());
+                std::process::exit(1);
+            }
+        };
+
+        let rewards = match read_rewards_file(&rewards_file_path) {
+            Ok(Some(rewards)) => rewards,
+            Ok(None) => {
+                println!(
+                    "{}",
+                    format!("Error: Failed to find rewards at {}", rewards_file_path).red()
+                );
+                std::process::exit(1);
+            }
+            Err(err) => {
+                println!("{}", format!("Error: {}", err).red());
+                std::process::exit(1);
+            }
+        };
+
+        let total_rewards_bps = match input_with_validation(
+            "Enter the percentage of stake considered for calculating the block rewards:",
+            "75",
+            None,
+            total_rewards_pct,
+            validate_bps,
+        ) {
+            Ok(bps) => bps,
+            Err(_) => {
+                println!("{}", "Error: Invalid total rewards BPS".red());
+                std::process::exit(1);
+            }
+        };
+
+        let lst_rewards_bps = match input_with_validation(
+            "Enter the percentage of block rewards shared:",
+            "100",
+            None,
+            lst_rewards_pct,
+            validate_bps,
+        ) {
+            Ok(bps) => bps,
+            Err(_) => {
+                println!("{}", "Error: Invalid LST rewards BPS".red());
+                std::process::exit(1);
+            }
+        };
+
+        let mev_rewards_bps = match rewards.mev_rewards {
+            Some(_) => match input_with_validation(
+                "Enter the percentage of MEV rewards shared:",
+                "100",
+                None,
+                mev_rewards_pct,
+                validate_bps,
+            ) {
+                Ok(bps) => bps,
+                Err(_) => {
+                    println!("{}", "Error: Invalid MEV rewards BPS".red());
+                    std::process::exit(1);
+                }
+            },
+            None => 0,
+        };
+
+        let split = match calculate_rewards_split(
+            &rewards,
+            &RewardsSplitBps {
+                total_rewards_bps,
+                lst_rewards_bps,
+                mev_rewards_bps,
+            },
+        ) {
+            Ok(split) => split,
+            Err(err) => {
+                println!("{}", err.red());
+                std::process::exit(1);
+            }
+        };
+
+        let stake_pool = match get_stake_pool(&rpc, &memo.stake_pool_pubkey).await {
+            Ok(stake_pool) => stake_pool,
+            Err(err) => {
+                println!("{}", err.red());
+                std::process::exit(1);
+            }
+        };
+
+        if let Err(err) = verify_distribution_transfer(&transfer, &stake_pool, &split) {
+            println!("{}", err.red());
+            std::process::exit(1);
+        }
+
+        println!(
+            "{}",
+            "✓ The transfer matches the rewards record and went to the stake pool reserve"
+                .green()
+                .bold()
+        );
+        println!("{}", "=".repeat(80));
+    }
+}
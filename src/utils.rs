@@ -1,14 +1,27 @@
+use crate::{
+    annualized_apy_pct, exchange_rate_increase_bps, EpochRewards, FeeBreakdown, PoolBalance,
+};
 use colored::Colorize;
 use comfy_table::{Attribute, Cell, Color, Table};
 use inquire::Text;
 use sanctum_solana_cli_utils::TokenAmt;
-use serde::Deserialize;
-use solana_sdk::pubkey::Pubkey;
-use std::str::FromStr;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+use std::{
+    fs::File,
+    path::Path,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
 
 const MAX_EPOCH_BACKWARDS_LOOKUP: u64 = 5;
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+/// 100%
+pub const MAX_BPS: u64 = 10_000;
+const LST_LIST_URL: &str = "https://raw.githubusercontent.com/igneous-labs/sanctum-lst-list/refs/heads/master/sanctum-lst-list.toml";
+const LST_LIST_CACHE_TTL_SECS: u64 = 24 * 60 * 60; // 1 day
 
-pub fn get_rewards_file_path(identity_pubkey: &Pubkey, epoch: u64) -> Result<String, String> {
+pub fn get_sanctum_dir_path() -> Result<String, String> {
     let home_dir = dirs_next::home_dir()
         .ok_or_else(|| "Could not find home directory".to_string())
         .and_then(|dir| {
@@ -17,16 +30,67 @@ pub fn get_rewards_file_path(identity_pubkey: &Pubkey, epoch: u64) -> Result<Str
                 .map(String::from)
         })?;
 
+    Ok(format!("{}/.local/sanctum", home_dir))
+}
+
+pub fn get_rewards_file_path(identity_pubkey: &Pubkey, epoch: u64) -> Result<String, String> {
+    Ok(format!(
+        "{}/rewards_{}_{}.json",
+        get_sanctum_dir_path()?,
+        identity_pubkey,
+        epoch
+    ))
+}
+
+pub fn get_lst_list_cache_path() -> Result<String, String> {
+    Ok(format!("{}/sanctum-lst-list.toml", get_sanctum_dir_path()?))
+}
+
+pub fn get_signed_transfer_file_path(
+    identity_pubkey: &Pubkey,
+    epoch: u64,
+) -> Result<String, String> {
+    Ok(format!(
+        "{}/signed_transfer_{}_{}.json",
+        get_sanctum_dir_path()?,
+        identity_pubkey,
+        epoch
+    ))
+}
+
+pub fn get_distribution_plan_file_path(
+    identity_pubkey: &Pubkey,
+    epoch: u64,
+) -> Result<String, String> {
+    Ok(format!(
+        "{}/plan_{}_{}.json",
+        get_sanctum_dir_path()?,
+        identity_pubkey,
+        epoch
+    ))
+}
+
+pub fn get_ledger_file_path() -> Result<String, String> {
+    Ok(format!("{}/ledger.sqlite", get_sanctum_dir_path()?))
+}
+
+pub fn get_attestation_file_path(identity_pubkey: &Pubkey, epoch: u64) -> Result<String, String> {
     Ok(format!(
-        "{}/.local/sanctum/rewards_{}_{}.json",
-        home_dir, identity_pubkey, epoch
+        "{}/attestation_{}_{}.json",
+        get_sanctum_dir_path()?,
+        identity_pubkey,
+        epoch
     ))
 }
 
+/// `bps` of `value`, rounded down to the lamport so a share never exceeds what it
+/// is taken from. Returns `None` if `bps` is over 100%.
 pub fn checked_pct(value: u64, bps: u64) -> Option<u64> {
-    value
-        .checked_mul(bps)
-        .and_then(|result| result.checked_div(10_000))
+    if bps > MAX_BPS {
+        return None;
+    }
+    // Can't overflow or truncate since bps <= MAX_BPS
+    u64::try_from(u128::from(value) * u128::from(bps) / u128::from(MAX_BPS)).ok()
 }
 
 fn get_input(
@@ -121,27 +185,63 @@ pub fn validate_rpc_url(input: &str) -> Result<String, String> {
     }
 }
 
+/// Parses a percentage with at most 2 decimals, e.g. `75` or `12.5`, into bps.
+/// Finer percentages are rejected rather than rounded.
 pub fn validate_bps(input: &str) -> Result<u64, String> {
-    // Parse the input as f64 to handle decimals
-    match input.parse::<f64>() {
-        Ok(percentage) => {
-            // Convert percentage to BPS (multiply by 100 to convert to basis points)
-            let bps = (percentage * 100.0).round() as u64;
-
-            if bps > 10_000 {
-                Err("Error: Percentage cannot exceed 100%".to_string())
-            } else {
-                Ok(bps)
-            }
-        }
-        Err(_) => Err("Error: Please enter a valid number".to_string()),
+    let input = input.trim();
+    let (integer, fraction) = input.split_once('.').unwrap_or((input, ""));
+
+    let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if (integer.is_empty() && fraction.is_empty()) || !is_digits(integer) || !is_digits(fraction) {
+        return Err("Error: Please enter a valid number".to_string());
+    }
+    if fraction.len() > 2 {
+        return Err("Error: Percentage can have at most 2 decimals".to_string());
+    }
+
+    // Leading zeros are fine, but anything longer can't be at most 100%
+    let integer = integer.trim_start_matches('0');
+    if integer.len() > 3 {
+        return Err("Error: Percentage cannot exceed 100%".to_string());
+    }
+
+    let integer: u64 = if integer.is_empty() {
+        0
+    } else {
+        integer
+            .parse()
+            .map_err(|_| "Error: Please enter a valid number".to_string())?
+    };
+    let fraction: u64 = format!("{:0<2}", fraction)
+        .parse()
+        .map_err(|_| "Error: Please enter a valid number".to_string())?;
+
+    let bps = integer * 100 + fraction;
+    if bps > MAX_BPS {
+        return Err("Error: Percentage cannot exceed 100%".to_string());
     }
+
+    Ok(bps)
 }
 
 pub fn validate_pubkey(input: &str) -> Result<Pubkey, String> {
     Pubkey::from_str(input).map_err(|_| "Error: Please enter a valid Solana public key".to_owned())
 }
 
+/// Parses a `key=value` query parameter
+pub fn parse_dune_param(input: &str) -> Result<(String, String), String> {
+    match input.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!(
+            "Error: Invalid Dune parameter `{}`, expected key=value",
+            input
+        )),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PrintTransferSummaryArgs {
     pub epoch: u64,
     pub payer_balance: u64,
@@ -150,6 +250,60 @@ pub struct PrintTransferSummaryArgs {
     pub stake_pool_rewards: u64,
     pub lst_rewards_bps: u64,
     pub lst_rewards: u64,
+    /// MEV fields are missing from signed transfer files written by older versions
+    #[serde(default)]
+    pub total_mev_rewards: u64,
+    #[serde(default)]
+    pub mev_rewards_bps: u64,
+    #[serde(default)]
+    pub stake_pool_mev_rewards: u64,
+    #[serde(default)]
+    pub lst_mev_rewards: u64,
+    pub lst_info: LstInfo,
+    pub pool_balance_pre: PoolBalance,
+    pub pool_balance_post: PoolBalance,
+    /// Projected epoch duration used to annualize the yield boost
+    pub epoch_duration_secs: f64,
+    /// Only present if the rewards were calculated with `--detailed`
+    #[serde(default)]
+    pub fee_breakdown: Option<FeeBreakdown>,
+}
+
+pub fn print_fee_breakdown(fee_breakdown: &FeeBreakdown) {
+    let FeeBreakdown {
+        num_transactions,
+        base_fees,
+        priority_fees,
+        burned_fees,
+    } = *fee_breakdown;
+    let leader_fees = base_fees
+        .saturating_add(priority_fees)
+        .saturating_sub(burned_fees);
+
+    let mut table = Table::new();
+    table
+        .set_header(
+            [
+                "Transactions",
+                "Base Fees",
+                "Priority Fees",
+                "Burned Fees",
+                "Leader Fee Rewards",
+            ]
+            .map(|header| {
+                Cell::new(header)
+                    .add_attribute(Attribute::Bold)
+                    .fg(Color::Blue)
+            }),
+        )
+        .add_row(
+            std::iter::once(Cell::new(num_transactions)).chain(
+                [base_fees, priority_fees, burned_fees, leader_fees]
+                    .map(|amt| Cell::new(format!("{} SOL", TokenAmt { amt, decimals: 9 }))),
+            ),
+        );
+
+    println!("{table}");
 }
 
 pub fn print_transfer_summary(args: PrintTransferSummaryArgs) {
@@ -161,6 +315,15 @@ pub fn print_transfer_summary(args: PrintTransferSummaryArgs) {
         stake_pool_rewards,
         lst_rewards_bps,
         lst_rewards,
+        total_mev_rewards,
+        mev_rewards_bps,
+        stake_pool_mev_rewards,
+        lst_mev_rewards,
+        lst_info,
+        pool_balance_pre,
+        pool_balance_post,
+        epoch_duration_secs,
+        fee_breakdown,
     } = args;
 
     let mut table = Table::new();
@@ -209,6 +372,97 @@ pub fn print_transfer_summary(args: PrintTransferSummaryArgs) {
 
     println!("{table}");
 
+    if total_mev_rewards > 0 {
+        let mut mev_table = Table::new();
+        mev_table
+            .set_header(
+                [
+                    "Total MEV Rewards".to_string(),
+                    format!(
+                        "Stake Pool MEV Rewards ({}%)",
+                        total_rewards_bps as f64 / 100.0
+                    ),
+                    format!("LST MEV Rewards ({}%)", mev_rewards_bps as f64 / 100.0),
+                    "Total LST Rewards".to_string(),
+                ]
+                .map(|header| {
+                    Cell::new(header)
+                        .add_attribute(Attribute::Bold)
+                        .fg(Color::Blue)
+                }),
+            )
+            .add_row(
+                [
+                    total_mev_rewards,
+                    stake_pool_mev_rewards,
+                    lst_mev_rewards,
+                    lst_rewards.saturating_add(lst_mev_rewards),
+                ]
+                .map(|amt| Cell::new(format!("{} SOL", TokenAmt { amt, decimals: 9 }))),
+            );
+
+        println!("{mev_table}");
+    }
+
+    let mut lst_table = Table::new();
+    lst_table
+        .set_header(
+            ["LST", "Mint", "Pool Program", "Decimals", "Logo"].map(|header| {
+                Cell::new(header)
+                    .add_attribute(Attribute::Bold)
+                    .fg(Color::Blue)
+            }),
+        )
+        .add_row(vec![
+            Cell::new(format!("{} ({})", lst_info.name, lst_info.symbol)),
+            Cell::new(&lst_info.mint),
+            Cell::new(lst_info.pool.program.as_deref().unwrap_or("Unknown")),
+            Cell::new(lst_info.decimals),
+            Cell::new(lst_info.logo_uri.as_deref().unwrap_or("-")),
+        ]);
+
+    println!("{lst_table}");
+
+    let yield_boost_bps = exchange_rate_increase_bps(&pool_balance_pre, &pool_balance_post);
+
+    let mut rate_table = Table::new();
+    rate_table
+        .set_header(
+            [
+                "Pre Transfer Rate".to_string(),
+                "Post Transfer Rate".to_string(),
+                "Yield Boost (per epoch)".to_string(),
+                format!(
+                    "APY Contribution (~{:.1}d epochs)",
+                    epoch_duration_secs / 86_400.0
+                ),
+            ]
+            .map(|header| {
+                Cell::new(header)
+                    .add_attribute(Attribute::Bold)
+                    .fg(Color::Blue)
+            }),
+        )
+        .add_row(vec![
+            Cell::new(format!(
+                "{:.9} SOL/{}",
+                pool_balance_pre.sol_value(lst_info.decimals),
+                lst_info.symbol
+            )),
+            Cell::new(format!(
+                "{:.9} SOL/{}",
+                pool_balance_post.sol_value(lst_info.decimals),
+                lst_info.symbol
+            )),
+            Cell::new(format!("{:.4} bps", yield_boost_bps)),
+            Cell::new(format!(
+                "{:.4}%",
+                annualized_apy_pct(yield_boost_bps, epoch_duration_secs)
+            )),
+        ]);
+
+    println!("{rate_table}");
+
     println!("{}", "=".repeat(80));
 
     println!(
@@ -230,7 +484,7 @@ pub fn print_transfer_summary(args: PrintTransferSummaryArgs) {
         "Post Transfer balance: ".blue().bold(),
         {
             let post_balance = TokenAmt {
-                amt: payer_balance.saturating_sub(lst_rewards),
+                amt: payer_balance.saturating_sub(lst_rewards.saturating_add(lst_mev_rewards)),
                 decimals: 9,
             };
             let formatted = format!("{} SOL", post_balance);
@@ -244,20 +498,152 @@ pub fn print_transfer_summary(args: PrintTransferSummaryArgs) {
         }
         .bold()
     );
+
+    if let Some(fee_breakdown) = &fee_breakdown {
+        print_fee_breakdown(fee_breakdown);
+    }
+}
+
+pub struct CheckPayerBalanceArgs {
+    pub payer_balance: u64,
+    pub transfer_lamports: u64,
+    pub rent_exempt_minimum: u64,
+    pub estimated_fee: u64,
+    pub reserve_floor: u64,
+}
+
+/// Upper bound on the fee of a transfer tx: the base fee for every signature
+/// plus the max priority fee the user is willing to pay
+pub fn estimate_max_tx_fee(num_signatures: u64, fee_limit_cb: u64) -> u64 {
+    num_signatures
+        .saturating_mul(LAMPORTS_PER_SIGNATURE)
+        .saturating_add(fee_limit_cb)
+}
+
+/// Ensures the payer is left with at least the rent exempt minimum plus the
+/// reserve floor after paying for the transfer and its fees.
+/// Returns the minimum balance required on success.
+pub fn check_payer_balance(args: CheckPayerBalanceArgs) -> Result<u64, String> {
+    let CheckPayerBalanceArgs {
+        payer_balance,
+        transfer_lamports,
+        rent_exempt_minimum,
+        estimated_fee,
+        reserve_floor,
+    } = args;
+
+    let required = transfer_lamports
+        .checked_add(estimated_fee)
+        .and_then(|v| v.checked_add(rent_exempt_minimum))
+        .and_then(|v| v.checked_add(reserve_floor))
+        .ok_or_else(|| "Error: Overflow in calculating required payer balance".to_string())?;
+
+    if payer_balance < required {
+        let sol = |amt| TokenAmt { amt, decimals: 9 };
+        return Err(format!(
+            "Error: Payer balance of {} SOL is below the {} SOL required \
+            (transfer: {} SOL, estimated fee: {} SOL, rent exempt minimum: {} SOL, reserve floor: {} SOL)",
+            sol(payer_balance),
+            sol(required),
+            sol(transfer_lamports),
+            sol(estimated_fee),
+            sol(rent_exempt_minimum),
+            sol(reserve_floor),
+        ));
+    }
+
+    Ok(required)
+}
+
+/// Parses a SOL amount to lamports exactly, without going through floats
+pub fn validate_sol_amount(input: &str) -> Result<u64, String> {
+    let input = input.trim();
+    let (integer, fraction) = input.split_once('.').unwrap_or((input, ""));
+
+    let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if (integer.is_empty() && fraction.is_empty()) || !is_digits(integer) || !is_digits(fraction) {
+        return Err("Error: Please enter a valid, non-negative SOL amount".to_string());
+    }
+    if fraction.len() > 9 {
+        return Err("Error: SOL amount can have at most 9 decimals".to_string());
+    }
+
+    let integer: u64 = match integer.trim_start_matches('0') {
+        "" => 0,
+        integer => integer
+            .parse()
+            .map_err(|_| "Error: SOL amount is too large".to_string())?,
+    };
+    let fraction: u64 = format!("{:0<9}", fraction)
+        .parse()
+        .map_err(|_| "Error: Please enter a valid, non-negative SOL amount".to_string())?;
+
+    integer
+        .checked_mul(LAMPORTS_PER_SOL)
+        .and_then(|lamports| lamports.checked_add(fraction))
+        .ok_or_else(|| "Error: SOL amount is too large".to_string())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedTransferFile {
+    pub identity_pubkey: String,
+    pub stake_pool_pubkey: String,
+    pub payer_pubkey: String,
+    pub summary: PrintTransferSummaryArgs,
+    /// Durable nonce account the transaction was signed against, if any
+    pub nonce_account: Option<String>,
+    pub blockhash: String,
+    /// Base58 encoded, bincode serialized `VersionedTransaction`
+    pub transaction: String,
+}
+
+pub fn read_json_file<T: DeserializeOwned>(path: &str) -> Result<T, String> {
+    File::open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path, e))
+        .and_then(|file| {
+            serde_json::from_reader(file).map_err(|e| format!("Failed to parse {}: {}", path, e))
+        })
+}
+
+/// Returns `Ok(None)` if no rewards have been calculated yet
+pub fn read_rewards_file(path: &str) -> Result<Option<EpochRewards>, String> {
+    if !Path::new(path).exists() {
+        return Ok(None);
+    }
+    read_json_file(path).map(Some)
 }
 
-#[derive(Debug, Deserialize, Default)]
-struct Pool {
+pub fn write_json_file<T: Serialize>(path: &str, value: &T) -> Result<(), String> {
+    // Create all parent directories if they don't exist
+    if let Some(parent) = Path::new(path).parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory - {}", e))?;
+    }
+
+    File::create(path)
+        .map_err(|e| e.to_string())
+        .and_then(|file| serde_json::to_writer_pretty(file, value).map_err(|e| e.to_string()))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct Pool {
+    /// Stake pool program type, e.g. `Spl`, `SanctumSpl`, `SanctumSplMulti`
     #[serde(default)]
-    pool: Option<String>,
+    pub program: Option<String>,
+    #[serde(default)]
+    pub pool: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
-struct LstInfo {
-    name: String,
-    symbol: String,
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LstInfo {
+    pub name: String,
+    pub symbol: String,
+    pub mint: String,
+    pub decimals: u8,
     #[serde(default)]
-    pool: Pool,
+    pub logo_uri: Option<String>,
+    #[serde(default)]
+    pub pool: Pool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -265,30 +651,94 @@ struct LstList {
     sanctum_lst_list: Vec<LstInfo>,
 }
 
-pub async fn get_lst_info(pool_pubkey: &str) -> Result<(String, String), String> {
-    // Fetch TOML content from GitHub
-    let url = "https://raw.githubusercontent.com/igneous-labs/sanctum-lst-list/refs/heads/master/sanctum-lst-list.toml";
-    let response = reqwest::get(url)
+fn parse_lst_list(toml_content: &str) -> Result<LstList, String> {
+    toml::from_str(toml_content).map_err(|e| format!("Failed to parse TOML: {}", e))
+}
+
+fn is_lst_list_cache_fresh(cache_path: &str) -> bool {
+    std::fs::metadata(cache_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < Duration::from_secs(LST_LIST_CACHE_TTL_SECS))
+}
+
+async fn fetch_lst_list_toml() -> Result<String, String> {
+    let response = reqwest::get(LST_LIST_URL)
         .await
+        .and_then(|response| response.error_for_status())
         .map_err(|e| format!("Failed to fetch TOML: {}", e))?;
 
-    let toml_content = response
+    response
         .text()
         .await
-        .map_err(|e| format!("Failed to read response: {}", e))?;
+        .map_err(|e| format!("Failed to read response: {}", e))
+}
+
+/// Loads the sanctum LST list, either from `lst_list_path` if provided, or from
+/// the local cache, refreshing it from GitHub once it is older than the TTL.
+/// Falls back to a stale cache if GitHub is unreachable or serves a list that
+/// fails to parse, returning why as a warning.
+async fn load_lst_list(lst_list_path: Option<&str>) -> Result<(LstList, Option<String>), String> {
+    if let Some(path) = lst_list_path {
+        let toml_content =
+            std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        return Ok((parse_lst_list(&toml_content)?, None));
+    }
 
-    // Parse TOML
-    let lst_list: LstList =
-        toml::from_str(&toml_content).map_err(|e| format!("Failed to parse TOML: {}", e))?;
+    let cache_path = get_lst_list_cache_path()?;
 
-    // Find matching pool
-    for lst in lst_list.sanctum_lst_list {
-        if let Some(pool_str) = lst.pool.pool {
-            if pool_str == pool_pubkey {
-                return Ok((lst.name, lst.symbol));
+    if is_lst_list_cache_fresh(&cache_path) {
+        if let Ok(lst_list) = std::fs::read_to_string(&cache_path)
+            .map_err(|e| e.to_string())
+            .and_then(|toml_content| parse_lst_list(&toml_content))
+        {
+            return Ok((lst_list, None));
+        }
+    }
+
+    // A list that fails to parse is as unusable as an unreachable one, and isn't cached
+    let fetched = fetch_lst_list_toml().await.and_then(|toml_content| {
+        parse_lst_list(&toml_content).map(|lst_list| (lst_list, toml_content))
+    });
+
+    match fetched {
+        Ok((lst_list, toml_content)) => {
+            // Failing to cache shouldn't stop us from using the freshly fetched list
+            if let Some(parent) = Path::new(&cache_path).parent() {
+                let _ = std::fs::create_dir_all(parent)
+                    .and_then(|_| std::fs::write(&cache_path, &toml_content));
             }
+            Ok((lst_list, None))
+        }
+        Err(fetch_err) => {
+            let toml_content = std::fs::read_to_string(&cache_path)
+                .map_err(|_| format!("{} and no cached LST list is available", fetch_err))?;
+            let lst_list = parse_lst_list(&toml_content)?;
+            Ok((
+                lst_list,
+                Some(format!(
+                    "{}, using cached LST list from {}",
+                    fetch_err, cache_path
+                )),
+            ))
         }
     }
+}
+
+/// Returns the LST list entry for the given stake pool, along with a warning if
+/// a stale cached list was used.
+/// `Ok((None, _))` means the LST list was loaded but does not contain the pool.
+pub async fn get_lst_info(
+    pool_pubkey: &str,
+    lst_list_path: Option<&str>,
+) -> Result<(Option<LstInfo>, Option<String>), String> {
+    let (lst_list, warning) = load_lst_list(lst_list_path).await?;
 
-    Err(format!("No LST found for pool {}", pool_pubkey))
+    // Find matching pool
+    let lst_info = lst_list
+        .sanctum_lst_list
+        .into_iter()
+        .find(|lst| lst.pool.pool.as_deref() == Some(pool_pubkey));
+    Ok((lst_info, warning))
 }
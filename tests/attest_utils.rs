@@ -0,0 +1,65 @@
+use sanctum_block_rewards_cli::attestation_message;
+use sanctum_block_rewards_cli::sign_attestation;
+use sanctum_block_rewards_cli::verify_attestation;
+use sanctum_block_rewards_cli::EpochRewards;
+use solana_sdk::offchain_message::OffchainMessage;
+use solana_sdk::signature::Keypair;
+use solana_sdk::signature::Signature;
+use solana_sdk::signer::Signer;
+use std::str::FromStr;
+
+mod common;
+
+use common::epoch_rewards;
+
+#[test]
+fn test_attestation() {
+    let identity = Keypair::new();
+    let attestation = sign_attestation(&identity, 746, &epoch_rewards(42_412_437, None)).unwrap();
+
+    assert_eq!(attestation.identity_pubkey, identity.pubkey().to_string());
+    assert_eq!(
+        attestation.message,
+        format!(
+            "SANCTUMLSTCLAIM-rewards-{}-746-42412437-40-rpc",
+            identity.pubkey()
+        )
+    );
+    assert_eq!(
+        attestation.message,
+        attestation_message(&identity.pubkey(), 746, 42_412_437, 40, "rpc")
+    );
+    assert_eq!(verify_attestation(&attestation), Ok(()));
+
+    // Signed as a standard off-chain message
+    assert!(OffchainMessage::new(0, attestation.message.as_bytes())
+        .unwrap()
+        .verify(
+            &identity.pubkey(),
+            &Signature::from_str(&attestation.signature).unwrap()
+        )
+        .unwrap());
+
+    // Edited rewards
+    let mut edited = attestation.clone();
+    edited.total_block_rewards += 1;
+    assert!(verify_attestation(&edited).is_err());
+
+    // Edited rewards and message
+    edited.message = attestation_message(&identity.pubkey(), 746, 42_412_438, 40, "rpc");
+    assert!(verify_attestation(&edited).is_err());
+
+    // Claimed by another identity
+    let other = Keypair::new();
+    let mut edited = attestation.clone();
+    edited.identity_pubkey = other.pubkey().to_string();
+    edited.message = attestation_message(&other.pubkey(), 746, 42_412_437, 40, "rpc");
+    assert!(verify_attestation(&edited).is_err());
+
+    // Rewards files written by older versions can't be attested
+    let rewards = EpochRewards {
+        num_leader_slots: None,
+        ..epoch_rewards(42_412_437, None)
+    };
+    assert!(sign_attestation(&identity, 746, &rewards).is_err());
+}
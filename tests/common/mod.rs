@@ -0,0 +1,670 @@
+#![allow(dead_code)]
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use sanctum_block_rewards_cli::{
+    encode_tx, transfer_to_reserve_and_update_stake_pool_balance_ixs_for_account, EpochRewards,
+    LstInfo, Pool, PoolBalance, PrintTransferSummaryArgs, RewardsSplit, RewardsSplitBps,
+    SignedTransferFile,
+};
+use serde_json::{json, Value};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    account::Account,
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    message::{v0::Message, VersionedMessage},
+    pubkey,
+    pubkey::Pubkey,
+    signature::Signature,
+    stake::{
+        self,
+        state::{Authorized, Lockup, Meta, StakeStateV2},
+    },
+    transaction::VersionedTransaction,
+};
+use std::{
+    collections::HashMap,
+    io::Read,
+    path::Path,
+    process::{Command, Output, Stdio},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+};
+use tiny_http::{Header, Response, Server};
+
+pub const IDENTITY: &str = "GdnSyH3YtwcxFvQrVVJMm1JhTS4QVX7MFsX56uJLUfiZ";
+/// Epoch of the recorded fixtures, its first slot is 322272000
+pub const FIXTURE_EPOCH: u64 = 746;
+pub const FIXTURE_SKIPPED_SLOT: u64 = 322368304;
+
+// https://github.com/solana-labs/solana/blob/27eff8408b7223bb3c4ab70523f8a8dca3ca6645/rpc-client-api/src/custom_error.rs#L17C1-L17C60
+const JSON_RPC_ERROR_CODE_SKIPPED_SLOT: i64 = -32007;
+const JSON_RPC_ERROR_CODE_METHOD_NOT_FOUND: i64 = -32601;
+
+pub const SPL_TOKEN_PROGRAM_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+/// Rent exempt minimum of a stake account
+pub const RESERVE_RENT_EXEMPT_RESERVE: u64 = 2_282_880;
+
+/// Rewards file contents for `total_block_rewards` over 40 leader slots, as
+/// calculated from RPC without `--detailed`
+pub fn epoch_rewards(total_block_rewards: u64, mev_rewards: Option<u64>) -> EpochRewards {
+    EpochRewards {
+        total_block_rewards,
+        source: Some("rpc".to_string()),
+        num_leader_slots: Some(40),
+        slot_rewards: None,
+        fee_breakdown: None,
+        mev_rewards,
+    }
+}
+
+/// Runs the `sanctum-rewards` binary with `home` as `$HOME`, so that its rewards
+/// files and ledger are isolated, and without a terminal to prompt on
+pub fn run_cli(home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_sanctum-rewards"))
+        .env("HOME", home)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap()
+}
+
+pub fn output_text(output: &Output) -> String {
+    format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+}
+
+/// Decodes the base64 transaction or message printed by `--send-mode dump-msg`
+pub fn dumped_message(output: &str) -> Option<VersionedMessage> {
+    output.split_whitespace().find_map(|word| {
+        let bytes = BASE64_STANDARD.decode(word).ok()?;
+        bincode::deserialize::<VersionedTransaction>(&bytes)
+            .map(|tx| tx.message)
+            .or_else(|_| bincode::deserialize::<VersionedMessage>(&bytes))
+            .ok()
+    })
+}
+
+type MockResponse = Result<Value, (i64, String)>;
+
+/// Responses of a mock JSON-RPC server, keyed by method and optionally by the
+/// request's first param, e.g. the slot of `getBlock` or the pubkey of `getAccountInfo`
+#[derive(Debug, Clone, Default)]
+pub struct MockRpc {
+    responses: HashMap<(String, Option<String>), MockResponse>,
+}
+
+fn first_param_key(params: &Value) -> Option<String> {
+    match params.get(0)? {
+        Value::String(s) => Some(s.clone()),
+        Value::Null => None,
+        value => Some(value.to_string()),
+    }
+}
+
+fn read_fixture(name: &str) -> Value {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/rpc")
+        .join(format!("{}.json", name));
+    let content = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("Failed to read fixture {:?}: {}", path, e));
+    serde_json::from_str(&content).unwrap()
+}
+
+impl MockRpc {
+    pub fn new() -> Self {
+        Self::default().with_result(
+            "getVersion",
+            None,
+            json!({ "solana-core": "2.1.13", "feature-set": 1725507508 }),
+        )
+    }
+
+    /// The recorded mainnet responses of epoch [`FIXTURE_EPOCH`], in which
+    /// [`IDENTITY`] led slots 322272000, 322272001 and the skipped [`FIXTURE_SKIPPED_SLOT`]
+    pub fn mainnet_fixtures() -> Self {
+        Self::new()
+            .with_fixture("getEpochInfo", None, "getEpochInfo")
+            .with_fixture("getEpochSchedule", None, "getEpochSchedule")
+            .with_fixture(
+                "getLeaderSchedule",
+                Some("322272000"),
+                "getLeaderSchedule_322272000",
+            )
+            .with_fixture("getBlock", Some("322272000"), "getBlock_322272000")
+            .with_fixture("getBlock", Some("322272001"), "getBlock_322272001")
+            .with_skipped_slot(FIXTURE_SKIPPED_SLOT)
+    }
+
+    pub fn with_result(mut self, method: &str, key: Option<&str>, result: Value) -> Self {
+        self.responses
+            .insert((method.to_string(), key.map(str::to_string)), Ok(result));
+        self
+    }
+
+    pub fn with_error(mut self, method: &str, key: Option<&str>, code: i64, message: &str) -> Self {
+        self.responses.insert(
+            (method.to_string(), key.map(str::to_string)),
+            Err((code, message.to_string())),
+        );
+        self
+    }
+
+    /// Responds with `tests/fixtures/rpc/<name>.json`
+    pub fn with_fixture(self, method: &str, key: Option<&str>, name: &str) -> Self {
+        self.with_result(method, key, read_fixture(name))
+    }
+
+    pub fn with_skipped_slot(self, slot: u64) -> Self {
+        self.with_error(
+            "getBlock",
+            Some(&slot.to_string()),
+            JSON_RPC_ERROR_CODE_SKIPPED_SLOT,
+            &format!(
+                "Slot {} was skipped, or missing due to ledger jump to recent snapshot",
+                slot
+            ),
+        )
+    }
+
+    pub fn with_account(self, pubkey: &Pubkey, account: &Account) -> Self {
+        self.with_result(
+            "getAccountInfo",
+            Some(&pubkey.to_string()),
+            json!({
+                "context": { "slot": 322800000 },
+                "value": {
+                    "data": [bs58::encode(&account.data).into_string(), "base58"],
+                    "executable": account.executable,
+                    "lamports": account.lamports,
+                    "owner": account.owner.to_string(),
+                    "rentEpoch": account.rent_epoch,
+                    "space": account.data.len(),
+                },
+            }),
+        )
+    }
+
+    fn respond(&self, request: &Value) -> Value {
+        let method = request["method"].as_str().unwrap_or_default().to_string();
+        let key = first_param_key(&request["params"]);
+
+        let response = self
+            .responses
+            .get(&(method.clone(), key))
+            .or_else(|| self.responses.get(&(method.clone(), None)))
+            .cloned()
+            .unwrap_or_else(|| {
+                Err((
+                    JSON_RPC_ERROR_CODE_METHOD_NOT_FOUND,
+                    format!("No mock response for {} {}", method, request["params"]),
+                ))
+            });
+
+        match response {
+            Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": request["id"] }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "error": { "code": code, "message": message },
+                "id": request["id"],
+            }),
+        }
+    }
+
+    /// Serves the responses on a random local port until the server is dropped
+    pub fn start(self) -> MockServer {
+        serve(move |request| {
+            let response = match serde_json::from_str::<Value>(&request.body) {
+                Ok(request) => self.respond(&request),
+                Err(e) => json!({
+                    "jsonrpc": "2.0",
+                    "error": { "code": -32700, "message": e.to_string() },
+                    "id": null,
+                }),
+            };
+            (200, response)
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    pub url: String,
+    pub body: String,
+    pub api_key: Option<String>,
+}
+
+/// Serves JSON responses from `handler` on a random local port until the server is dropped
+fn serve<F>(handler: F) -> MockServer
+where
+    F: Fn(&MockRequest) -> (u16, Value) + Send + 'static,
+{
+    let server = Arc::new(Server::http("127.0.0.1:0").unwrap());
+    let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+
+    let handle = std::thread::spawn({
+        let server = server.clone();
+        let requests = requests.clone();
+        move || {
+            for mut request in server.incoming_requests() {
+                let mut body = String::new();
+                let _ = request.as_reader().read_to_string(&mut body);
+                let mock_request = MockRequest {
+                    method: request.method().to_string(),
+                    url: request.url().to_string(),
+                    body,
+                    api_key: request
+                        .headers()
+                        .iter()
+                        .find(|header| header.field.equiv("X-Dune-API-Key"))
+                        .map(|header| header.value.to_string()),
+                };
+
+                let (status, response) = handler(&mock_request);
+                requests.lock().unwrap().push(mock_request);
+
+                let _ = request.respond(
+                    Response::from_string(response.to_string())
+                        .with_status_code(status)
+                        .with_header(
+                            Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                                .unwrap(),
+                        ),
+                );
+            }
+        }
+    });
+
+    MockServer {
+        url,
+        server,
+        requests,
+        handle: Some(handle),
+    }
+}
+
+pub struct MockServer {
+    pub url: String,
+    server: Arc<Server>,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    pub fn rpc(&self) -> RpcClient {
+        RpcClient::new_with_commitment(self.url.clone(), CommitmentConfig::confirmed())
+    }
+
+    /// Every request served so far
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+pub const DUNE_EXECUTION_ID: &str = "01JNR8WQ6M9ZC5V1F2X3Y4Z5A6";
+
+/// A Dune API whose executions go through `states` on successive status requests,
+/// then return `rows`. The last state repeats.
+#[derive(Debug, Clone)]
+pub struct MockDune {
+    pub column_names: Vec<String>,
+    pub rows: Vec<Value>,
+    pub states: Vec<&'static str>,
+    pub execution_ended_at: &'static str,
+}
+
+impl MockDune {
+    pub fn new(rows: Vec<Value>) -> Self {
+        Self {
+            column_names: vec![
+                "epoch".to_string(),
+                "block_rewards".to_string(),
+                "last_slot".to_string(),
+            ],
+            rows,
+            states: vec!["QUERY_STATE_COMPLETED"],
+            execution_ended_at: "2025-03-08T05:30:00.123Z",
+        }
+    }
+
+    pub fn with_states(self, states: &[&'static str]) -> Self {
+        Self {
+            states: states.to_vec(),
+            ..self
+        }
+    }
+
+    fn respond(&self, request: &MockRequest, num_status_requests: &AtomicUsize) -> (u16, Value) {
+        if request.api_key.is_none() {
+            return (401, json!({ "error": "invalid API Key" }));
+        }
+
+        let path = request.url.split('?').next().unwrap_or_default();
+        let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+        match (request.method.as_str(), segments.as_slice()) {
+            ("POST", ["query", _, "execute"]) => (
+                200,
+                json!({ "execution_id": DUNE_EXECUTION_ID, "state": "QUERY_STATE_PENDING" }),
+            ),
+            ("GET", ["execution", DUNE_EXECUTION_ID, "status"]) => {
+                let i = num_status_requests.fetch_add(1, Ordering::SeqCst);
+                let state = self.states[i.min(self.states.len() - 1)];
+                (
+                    200,
+                    json!({ "execution_id": DUNE_EXECUTION_ID, "state": state }),
+                )
+            }
+            ("GET", ["execution", DUNE_EXECUTION_ID, "results"]) => (
+                200,
+                json!({
+                    "execution_id": DUNE_EXECUTION_ID,
+                    "state": "QUERY_STATE_COMPLETED",
+                    "execution_ended_at": self.execution_ended_at,
+                    "result": {
+                        "rows": self.rows,
+                        "metadata": {
+                            "column_names": self.column_names,
+                            "total_row_count": self.rows.len(),
+                        },
+                    },
+                }),
+            ),
+            _ => (404, json!({ "error": format!("Not found: {}", path) })),
+        }
+    }
+
+    pub fn start(self) -> MockServer {
+        let num_status_requests = AtomicUsize::new(0);
+        serve(move |request| self.respond(request, &num_status_requests))
+    }
+}
+
+/// A stake pool updated for `last_update_epoch` with a valid reserve, owned by `program_id`
+#[derive(Debug, Clone)]
+pub struct StakePoolFixture {
+    pub program_id: Pubkey,
+    pub stake_pool: Pubkey,
+    pub manager: Pubkey,
+    pub validator_list: Pubkey,
+    pub reserve_stake: Pubkey,
+    pub pool_mint: Pubkey,
+    pub manager_fee_account: Pubkey,
+    pub reserve_lamports: u64,
+    pub total_lamports: u64,
+    pub pool_token_supply: u64,
+    pub last_update_epoch: u64,
+}
+
+impl StakePoolFixture {
+    pub fn new(program_id: Pubkey, last_update_epoch: u64) -> Self {
+        Self {
+            program_id,
+            stake_pool: Pubkey::new_from_array([1; 32]),
+            manager: Pubkey::new_from_array([2; 32]),
+            validator_list: Pubkey::new_from_array([3; 32]),
+            reserve_stake: Pubkey::new_from_array([4; 32]),
+            pool_mint: Pubkey::new_from_array([5; 32]),
+            manager_fee_account: Pubkey::new_from_array([6; 32]),
+            reserve_lamports: RESERVE_RENT_EXEMPT_RESERVE,
+            total_lamports: 1_100_000_000_000,
+            pool_token_supply: 1_000_000_000_000,
+            last_update_epoch,
+        }
+    }
+
+    pub fn withdraw_authority(&self) -> Pubkey {
+        self.withdraw_authority_and_bump().0
+    }
+
+    fn withdraw_authority_and_bump(&self) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[self.stake_pool.as_ref(), b"withdraw"], &self.program_id)
+    }
+
+    pub fn deposit_authority(&self) -> Pubkey {
+        Pubkey::find_program_address(&[self.stake_pool.as_ref(), b"deposit"], &self.program_id).0
+    }
+
+    /// Borsh layout of the SPL stake pool program's `StakePool`, with a 1% epoch fee
+    /// and every optional field unset
+    pub fn stake_pool_data(&self) -> Vec<u8> {
+        let fee = |denominator: u64, numerator: u64| {
+            [denominator.to_le_bytes(), numerator.to_le_bytes()].concat()
+        };
+
+        let mut data = vec![1u8]; // AccountType::StakePool
+        data.extend(self.manager.to_bytes()); // manager
+        data.extend(self.manager.to_bytes()); // staker
+        data.extend(self.deposit_authority().to_bytes()); // stake_deposit_authority
+        data.push(self.withdraw_authority_and_bump().1); // stake_withdraw_bump_seed
+        data.extend(self.validator_list.to_bytes());
+        data.extend(self.reserve_stake.to_bytes());
+        data.extend(self.pool_mint.to_bytes());
+        data.extend(self.manager_fee_account.to_bytes());
+        data.extend(SPL_TOKEN_PROGRAM_ID.to_bytes());
+        data.extend(self.total_lamports.to_le_bytes());
+        data.extend(self.pool_token_supply.to_le_bytes());
+        data.extend(self.last_update_epoch.to_le_bytes());
+        data.extend([0u8; 48]); // lockup
+        data.extend(fee(100, 1)); // epoch_fee
+        data.push(0); // next_epoch_fee
+        data.push(0); // preferred_deposit_validator_vote_address
+        data.push(0); // preferred_withdraw_validator_vote_address
+        data.extend(fee(0, 0)); // stake_deposit_fee
+        data.extend(fee(0, 0)); // stake_withdrawal_fee
+        data.push(0); // next_stake_withdrawal_fee
+        data.push(0); // stake_referral_fee
+        data.push(0); // sol_deposit_authority
+        data.extend(fee(0, 0)); // sol_deposit_fee
+        data.push(0); // sol_referral_fee
+        data.push(0); // sol_withdraw_authority
+        data.extend(fee(0, 0)); // sol_withdrawal_fee
+        data.push(0); // next_sol_withdrawal_fee
+        data.extend(self.pool_token_supply.to_le_bytes()); // last_epoch_pool_token_supply
+        data.extend(self.total_lamports.to_le_bytes()); // last_epoch_total_lamports
+        data
+    }
+
+    pub fn stake_pool_account(&self) -> Account {
+        Account {
+            lamports: 5_143_440,
+            data: self.stake_pool_data(),
+            owner: self.program_id,
+            executable: false,
+            rent_epoch: u64::MAX,
+        }
+    }
+
+    pub fn reserve_account(&self) -> Account {
+        let withdraw_authority = self.withdraw_authority();
+        let mut data = bincode::serialize(&StakeStateV2::Initialized(Meta {
+            rent_exempt_reserve: RESERVE_RENT_EXEMPT_RESERVE,
+            authorized: Authorized {
+                staker: withdraw_authority,
+                withdrawer: withdraw_authority,
+            },
+            lockup: Lockup::default(),
+        }))
+        .unwrap();
+        data.resize(StakeStateV2::size_of(), 0);
+
+        Account {
+            lamports: self.reserve_lamports,
+            data,
+            owner: stake::program::ID,
+            executable: false,
+            rent_epoch: u64::MAX,
+        }
+    }
+
+    /// An empty `ValidatorList` that can't hold any validator
+    pub fn validator_list_account(&self) -> Account {
+        let mut data = vec![2u8]; // AccountType::ValidatorList
+        data.extend(0u32.to_le_bytes()); // max_validators
+        data.extend(0u32.to_le_bytes()); // validators
+
+        Account {
+            lamports: 953_520,
+            data,
+            owner: self.program_id,
+            executable: false,
+            rent_epoch: u64::MAX,
+        }
+    }
+
+    /// SPL token `Mint` layout, minted by the pool's withdraw authority
+    pub fn pool_mint_account(&self) -> Account {
+        let mut data = vec![1, 0, 0, 0]; // mint_authority
+        data.extend(self.withdraw_authority().to_bytes());
+        data.extend(self.pool_token_supply.to_le_bytes());
+        data.push(9); // decimals
+        data.push(1); // is_initialized
+        data.extend([0u8; 36]); // freeze_authority
+
+        Account {
+            lamports: 1_461_600,
+            data,
+            owner: SPL_TOKEN_PROGRAM_ID,
+            executable: false,
+            rent_epoch: u64::MAX,
+        }
+    }
+
+    /// SPL token `Account` layout of the manager's pool token account, with no balance
+    pub fn manager_fee_token_account(&self) -> Account {
+        let mut data = self.pool_mint.to_bytes().to_vec();
+        data.extend(self.manager.to_bytes()); // owner
+        data.extend(0u64.to_le_bytes()); // amount
+        data.extend([0u8; 36]); // delegate
+        data.push(1); // AccountState::Initialized
+        data.extend([0u8; 12]); // is_native
+        data.extend(0u64.to_le_bytes()); // delegated_amount
+        data.extend([0u8; 36]); // close_authority
+
+        Account {
+            lamports: 2_039_280,
+            data,
+            owner: SPL_TOKEN_PROGRAM_ID,
+            executable: false,
+            rent_epoch: u64::MAX,
+        }
+    }
+
+    /// Every account the stake pool program reads in `UpdateStakePoolBalance`
+    pub fn accounts(&self) -> Vec<(Pubkey, Account)> {
+        vec![
+            (self.stake_pool, self.stake_pool_account()),
+            (self.validator_list, self.validator_list_account()),
+            (self.reserve_stake, self.reserve_account()),
+            (self.pool_mint, self.pool_mint_account()),
+            (self.manager_fee_account, self.manager_fee_token_account()),
+        ]
+    }
+
+    /// Serves the stake pool and reserve accounts from `mock`
+    pub fn with_accounts(&self, mock: MockRpc) -> MockRpc {
+        mock.with_account(&self.stake_pool, &self.stake_pool_account())
+            .with_account(&self.reserve_stake, &self.reserve_account())
+    }
+
+    /// Writes a `sanctum-lst-list.toml` listing this pool's LST, for `--lst-list`
+    pub fn write_lst_list(&self, path: &Path) {
+        std::fs::write(
+            path,
+            format!(
+                r#"[[sanctum_lst_list]]
+name = "Test LST"
+symbol = "testSOL"
+mint = "{}"
+decimals = 9
+
+[sanctum_lst_list.pool]
+program = "Spl"
+pool = "{}"
+"#,
+                self.pool_mint, self.stake_pool
+            ),
+        )
+        .unwrap();
+    }
+
+    /// File written by `transfer --sign-only` for distributing `split` of
+    /// [`IDENTITY`]'s rewards of [`FIXTURE_EPOCH`] to this pool. The tx isn't signed.
+    pub fn signed_transfer_file(
+        &self,
+        payer_pubkey: &Pubkey,
+        bps: &RewardsSplitBps,
+        split: &RewardsSplit,
+    ) -> SignedTransferFile {
+        let ixs = transfer_to_reserve_and_update_stake_pool_balance_ixs_for_account(
+            &self.stake_pool_account(),
+            payer_pubkey,
+            &Pubkey::from_str(IDENTITY).unwrap(),
+            &self.stake_pool,
+            split.transfer_lamports,
+            FIXTURE_EPOCH,
+        )
+        .unwrap();
+        let tx = VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: VersionedMessage::V0(
+                Message::try_compile(payer_pubkey, &ixs, &[], Hash::default()).unwrap(),
+            ),
+        };
+        let pool_balance = PoolBalance {
+            total_lamports: self.total_lamports,
+            pool_token_supply: self.pool_token_supply,
+        };
+
+        SignedTransferFile {
+            identity_pubkey: IDENTITY.to_string(),
+            stake_pool_pubkey: self.stake_pool.to_string(),
+            payer_pubkey: payer_pubkey.to_string(),
+            summary: PrintTransferSummaryArgs {
+                epoch: FIXTURE_EPOCH,
+                payer_balance: 1_000_000_000,
+                total_block_rewards: split.total_block_rewards,
+                total_rewards_bps: bps.total_rewards_bps,
+                stake_pool_rewards: split.stake_pool_rewards,
+                lst_rewards_bps: bps.lst_rewards_bps,
+                lst_rewards: split.lst_rewards,
+                total_mev_rewards: split.total_mev_rewards,
+                mev_rewards_bps: bps.mev_rewards_bps,
+                stake_pool_mev_rewards: split.stake_pool_mev_rewards,
+                lst_mev_rewards: split.lst_mev_rewards,
+                lst_info: LstInfo {
+                    name: "Test LST".to_string(),
+                    symbol: "testSOL".to_string(),
+                    mint: self.pool_mint.to_string(),
+                    decimals: 9,
+                    logo_uri: None,
+                    pool: Pool::default(),
+                },
+                pool_balance_pre: pool_balance,
+                pool_balance_post: pool_balance,
+                epoch_duration_secs: 172_800.0,
+                fee_breakdown: None,
+            },
+            nonce_account: None,
+            blockhash: Hash::default().to_string(),
+            transaction: encode_tx(&tx).unwrap(),
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use sanctum_block_rewards_cli::check_endorsement_tx;
+use sanctum_block_rewards_cli::check_vote_account_identity;
+use sanctum_block_rewards_cli::endorse_ix;
+use sanctum_block_rewards_cli::endorsement_memo;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Clock;
+use solana_sdk::hash::Hash;
+use solana_sdk::message::v0::Message;
+use solana_sdk::message::VersionedMessage;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+use solana_sdk::signer::Signer;
+use solana_sdk::transaction::VersionedTransaction;
+use solana_sdk::vote;
+use solana_sdk::vote::state::VoteInit;
+use solana_sdk::vote::state::VoteState;
+use solana_sdk::vote::state::VoteStateVersions;
+
+fn endorsement_tx(
+    payer: &Keypair,
+    identity: &Keypair,
+    ix_identity: &Pubkey,
+) -> VersionedTransaction {
+    let stake_pool = Pubkey::new_from_array([1; 32]);
+    let vote = Pubkey::new_from_array([2; 32]);
+    let message = Message::try_compile(
+        &payer.pubkey(),
+        &[endorse_ix(ix_identity, &stake_pool, &vote)],
+        &[],
+        Hash::default(),
+    )
+    .unwrap();
+    let signers: Vec<&Keypair> = if payer.pubkey() == identity.pubkey() {
+        vec![payer]
+    } else {
+        vec![payer, identity]
+    };
+    VersionedTransaction::try_new(VersionedMessage::V0(message), &signers).unwrap()
+}
+
+#[test]
+fn test_endorsement_memo() {
+    let stake_pool = Pubkey::new_from_array([1; 32]);
+    let vote = Pubkey::new_from_array([2; 32]);
+    assert_eq!(
+        endorsement_memo(&stake_pool, &vote),
+        format!("SANCTUMLSTCLAIM-{}-{}", stake_pool, vote)
+    );
+
+    let ix = endorse_ix(&Pubkey::new_from_array([3; 32]), &stake_pool, &vote);
+    assert_eq!(ix.program_id, spl_memo::v1::id());
+    assert!(ix.accounts[0].is_signer);
+    assert!(ix.accounts.iter().any(|meta| meta.pubkey == stake_pool));
+}
+
+#[test]
+fn test_check_endorsement_tx() {
+    let stake_pool = Pubkey::new_from_array([1; 32]);
+    let vote = Pubkey::new_from_array([2; 32]);
+    let identity = Keypair::new();
+    let payer = Keypair::new();
+
+    // Paid by the identity
+    let tx = endorsement_tx(&identity, &identity, &identity.pubkey());
+    assert_eq!(
+        check_endorsement_tx(&tx, &identity.pubkey(), &stake_pool, &vote),
+        Ok(())
+    );
+
+    // Paid by a separate payer
+    let tx = endorsement_tx(&payer, &identity, &identity.pubkey());
+    assert_eq!(
+        check_endorsement_tx(&tx, &identity.pubkey(), &stake_pool, &vote),
+        Ok(())
+    );
+
+    // Not signed by the identity
+    let tx = endorsement_tx(&payer, &payer, &payer.pubkey());
+    assert!(check_endorsement_tx(&tx, &identity.pubkey(), &stake_pool, &vote).is_err());
+
+    // Endorses another stake pool or vote account
+    let tx = endorsement_tx(&identity, &identity, &identity.pubkey());
+    let other = Pubkey::new_unique();
+    assert!(check_endorsement_tx(&tx, &identity.pubkey(), &other, &vote).is_err());
+    assert!(check_endorsement_tx(&tx, &identity.pubkey(), &stake_pool, &other).is_err());
+}
+
+#[test]
+fn test_check_vote_account_identity() {
+    let vote = Pubkey::new_unique();
+    let identity = Pubkey::new_unique();
+    let vote_state = VoteState::new(
+        &VoteInit {
+            node_pubkey: identity,
+            authorized_voter: Pubkey::new_unique(),
+            authorized_withdrawer: Pubkey::new_unique(),
+            commission: 5,
+        },
+        &Clock::default(),
+    );
+    let mut data = bincode::serialize(&VoteStateVersions::new_current(vote_state)).unwrap();
+    data.resize(VoteState::size_of(), 0);
+    let vote_account = Account {
+        lamports: 27_074_400,
+        data,
+        owner: vote::program::ID,
+        executable: false,
+        rent_epoch: u64::MAX,
+    };
+
+    assert_eq!(
+        check_vote_account_identity(&vote, &vote_account, &identity),
+        Ok(())
+    );
+
+    // Another validator's vote account
+    assert!(check_vote_account_identity(&vote, &vote_account, &Pubkey::new_unique()).is_err());
+
+    // Not a vote account
+    let mut not_vote_account = vote_account.clone();
+    not_vote_account.owner = Pubkey::new_unique();
+    assert!(check_vote_account_identity(&vote, &not_vote_account, &identity).is_err());
+
+    let mut empty_vote_account = vote_account;
+    empty_vote_account.data.clear();
+    assert!(check_vote_account_identity(&vote, &empty_vote_account, &identity).is_err());
+}
@@ -0,0 +1,143 @@
+use sanctum_block_rewards_cli::find_tip_distribution_account;
+use sanctum_block_rewards_cli::get_mev_rewards_for_epoch;
+use sanctum_block_rewards_cli::read_mev_rewards_file;
+use sanctum_block_rewards_cli::MerkleRoot;
+use sanctum_block_rewards_cli::TipDistributionAccount;
+use sanctum_block_rewards_cli::JITO_TIP_DISTRIBUTION_PROGRAM_ID;
+use serde_json::json;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+mod common;
+
+use common::MockRpc;
+
+/// Anchor allocates `8 + size_of::<TipDistributionAccount>()` bytes, enough for a
+/// `Some` merkle root, and leaves the rest zeroed
+const TIP_DISTRIBUTION_ACCOUNT_LEN: usize = 168;
+const VALIDATOR_VOTE_ACCOUNT: Pubkey = pubkey!("3N7s9zXMZ4QqvHQR15t8GNHyqc89KduzMP7423eWiD5g");
+
+fn write(data: &mut [u8], offset: usize, bytes: &[u8]) -> usize {
+    data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    offset + bytes.len()
+}
+
+/// Field by field like the tip distribution program's `TipDistributionAccount`:
+/// vote account, upload authority, merkle root, epoch created at, commission, expires at, bump
+fn tip_distribution_account_data(max_total_claim: Option<u64>, commission_bps: u16) -> Vec<u8> {
+    let mut data = vec![0u8; TIP_DISTRIBUTION_ACCOUNT_LEN];
+    let mut offset = write(&mut data, 0, &[85, 64, 113, 198, 234, 94, 120, 123]);
+    offset = write(&mut data, offset, VALIDATOR_VOTE_ACCOUNT.as_ref());
+    offset = write(&mut data, offset, &[2u8; 32]);
+    match max_total_claim {
+        Some(max_total_claim) => {
+            offset = write(&mut data, offset, &[1]);
+            offset = write(&mut data, offset, &[3u8; 32]);
+            offset = write(&mut data, offset, &max_total_claim.to_le_bytes());
+            offset = write(&mut data, offset, &10u64.to_le_bytes());
+            offset = write(&mut data, offset, &0u64.to_le_bytes());
+            offset = write(&mut data, offset, &0u64.to_le_bytes());
+        }
+        None => offset = write(&mut data, offset, &[0]),
+    }
+    offset = write(&mut data, offset, &746u64.to_le_bytes());
+    offset = write(&mut data, offset, &commission_bps.to_le_bytes());
+    offset = write(&mut data, offset, &756u64.to_le_bytes());
+    write(&mut data, offset, &[255]);
+    data
+}
+
+#[test]
+fn test_tip_distribution_account() {
+    let rent_exempt_minimum = 2_500_000;
+
+    // Before the merkle root upload, tips are the balance above rent exemption
+    let account =
+        TipDistributionAccount::deserialize_account_data(&tip_distribution_account_data(None, 800))
+            .unwrap();
+    assert_eq!(
+        account.validator_vote_account,
+        VALIDATOR_VOTE_ACCOUNT.to_bytes()
+    );
+    assert_eq!(account.merkle_root, None);
+    assert_eq!(account.epoch_created_at, 746);
+    assert_eq!(account.validator_commission_bps, 800);
+    assert_eq!(account.expires_at, 756);
+    assert_eq!(account.bump, 255);
+    assert_eq!(
+        account.validator_mev_rewards(rent_exempt_minimum + 10_000_000_000, rent_exempt_minimum),
+        Some(800_000_000)
+    );
+
+    // After the upload, tips are the max total claim regardless of claims made so far
+    let account = TipDistributionAccount::deserialize_account_data(&tip_distribution_account_data(
+        Some(10_000_000_000),
+        1_000,
+    ))
+    .unwrap();
+    assert_eq!(
+        account.merkle_root,
+        Some(MerkleRoot {
+            root: [3u8; 32],
+            max_total_claim: 10_000_000_000,
+            max_num_nodes: 10,
+            total_funds_claimed: 0,
+            num_nodes_claimed: 0,
+        })
+    );
+    assert_eq!(account.epoch_created_at, 746);
+    assert_eq!(account.validator_commission_bps, 1_000);
+    assert_eq!(account.expires_at, 756);
+    assert_eq!(
+        account.validator_mev_rewards(rent_exempt_minimum, rent_exempt_minimum),
+        Some(1_000_000_000)
+    );
+
+    assert!(TipDistributionAccount::deserialize_account_data(&[0u8; 8]).is_err());
+    assert!(TipDistributionAccount::deserialize_account_data(&[0u8; 168]).is_err());
+
+    // Anything but zero padding after the account means the layout is off
+    let mut data = tip_distribution_account_data(None, 800);
+    data[TIP_DISTRIBUTION_ACCOUNT_LEN - 1] = 1;
+    assert!(TipDistributionAccount::deserialize_account_data(&data).is_err());
+}
+
+#[tokio::test]
+async fn test_get_mev_rewards_for_epoch() {
+    let (tip_distribution_account, _bump) =
+        find_tip_distribution_account(&VALIDATOR_VOTE_ACCOUNT, 746);
+    let rent_exempt_minimum = 2_060_160;
+    let server = MockRpc::new()
+        .with_account(
+            &tip_distribution_account,
+            &Account {
+                lamports: rent_exempt_minimum + 5_000_000_000,
+                data: tip_distribution_account_data(Some(10_000_000_000), 800),
+                owner: JITO_TIP_DISTRIBUTION_PROGRAM_ID,
+                executable: false,
+                rent_epoch: u64::MAX,
+            },
+        )
+        .with_result(
+            "getMinimumBalanceForRentExemption",
+            Some(&TIP_DISTRIBUTION_ACCOUNT_LEN.to_string()),
+            json!(rent_exempt_minimum),
+        )
+        .start();
+
+    assert_eq!(
+        get_mev_rewards_for_epoch(&server.rpc(), &VALIDATOR_VOTE_ACCOUNT, 746).await,
+        Ok(800_000_000)
+    );
+}
+
+#[test]
+fn test_read_mev_rewards_file() {
+    let path = std::env::temp_dir().join("test-sanctum-mev-rewards.json");
+    let path = path.to_str().unwrap();
+    std::fs::write(path, r#"{ "epoch": 746, "mev_rewards": 123456789 }"#).unwrap();
+
+    assert_eq!(read_mev_rewards_file(path, 746), Ok(123_456_789));
+    assert!(read_mev_rewards_file(path, 747).is_err());
+}
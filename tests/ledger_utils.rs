@@ -0,0 +1,247 @@
+use sanctum_block_rewards_cli::calculate_rewards_split;
+use sanctum_block_rewards_cli::write_json_file;
+use sanctum_block_rewards_cli::EpochRewards;
+use sanctum_block_rewards_cli::Ledger;
+use sanctum_block_rewards_cli::LedgerQuery;
+use sanctum_block_rewards_cli::RewardsSplitBps;
+use sanctum_block_rewards_cli::SlotReward;
+use sanctum_block_rewards_cli::TransferRecord;
+use sanctum_block_rewards_cli::TransferStatus;
+use sanctum_block_rewards_cli::KNOWN_STAKE_POOL_PROGRAM_IDS;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+
+mod common;
+
+use common::epoch_rewards;
+use common::StakePoolFixture;
+use common::FIXTURE_EPOCH;
+use common::IDENTITY;
+
+/// Rewards with their per slot breakdown, which the ledger stores separately
+fn slot_epoch_rewards() -> EpochRewards {
+    EpochRewards {
+        num_leader_slots: Some(2),
+        slot_rewards: Some(vec![
+            SlotReward {
+                slot: 322272000,
+                lamports: 42_412_437,
+                skipped: false,
+                fee_breakdown: None,
+            },
+            SlotReward {
+                slot: 322272001,
+                lamports: 0,
+                skipped: true,
+                fee_breakdown: None,
+            },
+        ]),
+        ..epoch_rewards(42_412_437, Some(1_000_000))
+    }
+}
+
+fn transfer(identity: &Pubkey, epoch: u64, stake_pool: &Pubkey) -> TransferRecord {
+    TransferRecord {
+        identity_pubkey: identity.to_string(),
+        epoch,
+        stake_pool_pubkey: stake_pool.to_string(),
+        payer_pubkey: identity.to_string(),
+        transfer_lamports: 31_809_327,
+        plan: None,
+        signature: Some(Signature::default().to_string()),
+        status: TransferStatus::Sent,
+    }
+}
+
+#[test]
+fn test_ledger_queries() {
+    let identity = Pubkey::new_unique();
+    let other_identity = Pubkey::new_unique();
+    let stake_pool = Pubkey::new_unique();
+    let other_stake_pool = Pubkey::new_unique();
+    let mut ledger = Ledger::open_in_memory().unwrap();
+
+    ledger
+        .record_calculation(&identity, 746, &slot_epoch_rewards())
+        .unwrap();
+    ledger
+        .record_calculation(&identity, 747, &slot_epoch_rewards())
+        .unwrap();
+    ledger
+        .record_calculation(&other_identity, 746, &slot_epoch_rewards())
+        .unwrap();
+    ledger
+        .record_transfer(&transfer(&identity, 746, &stake_pool))
+        .unwrap();
+    ledger
+        .record_transfer(&transfer(&identity, 747, &other_stake_pool))
+        .unwrap();
+
+    // Calculations round trip with their per slot rewards
+    let calculations = ledger
+        .calculations(&LedgerQuery {
+            identity_pubkey: Some(identity),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(calculations.len(), 2);
+    assert_eq!(calculations[0].epoch, 746);
+    assert_eq!(calculations[0].rewards, slot_epoch_rewards());
+
+    let calculations = ledger
+        .calculations(&LedgerQuery {
+            epoch: Some(746),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(calculations.len(), 2);
+
+    // By stake pool, through the transfers made from them
+    let calculations = ledger
+        .calculations(&LedgerQuery {
+            stake_pool_pubkey: Some(stake_pool),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(calculations.len(), 1);
+    assert_eq!(calculations[0].identity_pubkey, identity.to_string());
+    assert_eq!(calculations[0].epoch, 746);
+
+    let transfers = ledger
+        .transfers(&LedgerQuery {
+            stake_pool_pubkey: Some(other_stake_pool),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(transfers.len(), 1);
+    assert_eq!(
+        transfers[0].transfer,
+        transfer(&identity, 747, &other_stake_pool)
+    );
+
+    assert!(ledger
+        .transfers(&LedgerQuery {
+            identity_pubkey: Some(other_identity),
+            ..Default::default()
+        })
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_ledger_transfer_status() {
+    let identity = Pubkey::new_unique();
+    let stake_pool = Pubkey::new_unique();
+    let mut ledger = Ledger::open_in_memory().unwrap();
+    let query = LedgerQuery {
+        identity_pubkey: Some(identity),
+        ..Default::default()
+    };
+
+    let planned = TransferRecord {
+        signature: None,
+        status: TransferStatus::Planned,
+        ..transfer(&identity, 746, &stake_pool)
+    };
+    let id = ledger.record_transfer(&planned).unwrap();
+
+    // Each step of the distribution updates the same record
+    let signed = TransferRecord {
+        status: TransferStatus::Signed,
+        ..transfer(&identity, 746, &stake_pool)
+    };
+    assert_eq!(ledger.record_transfer(&signed).unwrap(), id);
+    assert_eq!(
+        ledger
+            .record_transfer(&transfer(&identity, 746, &stake_pool))
+            .unwrap(),
+        id
+    );
+
+    let transfers = ledger.transfers(&query).unwrap();
+    assert_eq!(transfers.len(), 1);
+    assert_eq!(transfers[0].transfer, transfer(&identity, 746, &stake_pool));
+
+    // An older status, e.g. from importing the plan file, doesn't override it
+    assert_eq!(ledger.record_transfer(&planned).unwrap(), id);
+    let transfers = ledger.transfers(&query).unwrap();
+    assert_eq!(transfers.len(), 1);
+    assert_eq!(transfers[0].transfer.status, TransferStatus::Sent);
+
+    // Other stake pools and epochs are separate distributions
+    ledger
+        .record_transfer(&transfer(&identity, 747, &stake_pool))
+        .unwrap();
+    ledger
+        .record_transfer(&transfer(&identity, 746, &Pubkey::new_unique()))
+        .unwrap();
+    assert_eq!(ledger.transfers(&query).unwrap().len(), 3);
+}
+
+#[test]
+fn test_transfer_record_from_signed_transfer() {
+    let payer = Pubkey::new_unique();
+    let fixture = StakePoolFixture::new(KNOWN_STAKE_POOL_PROGRAM_IDS[0], FIXTURE_EPOCH + 1);
+    let bps = RewardsSplitBps {
+        total_rewards_bps: 7_500,
+        lst_rewards_bps: 10_000,
+        mev_rewards_bps: 0,
+    };
+    let split = calculate_rewards_split(&epoch_rewards(42_412_437, None), &bps).unwrap();
+    let mut signed_transfer = fixture.signed_transfer_file(&payer, &bps, &split);
+
+    // The amount comes from the tx, not from the summary shown for review
+    signed_transfer.summary.lst_rewards += 1;
+    let record =
+        TransferRecord::from_signed_transfer(&signed_transfer, TransferStatus::Signed).unwrap();
+    assert_eq!(
+        record,
+        TransferRecord {
+            identity_pubkey: IDENTITY.to_string(),
+            epoch: FIXTURE_EPOCH,
+            stake_pool_pubkey: fixture.stake_pool.to_string(),
+            payer_pubkey: payer.to_string(),
+            transfer_lamports: split.transfer_lamports,
+            plan: None,
+            signature: Some(Signature::default().to_string()),
+            status: TransferStatus::Signed,
+        }
+    );
+}
+
+#[test]
+fn test_ledger_import() {
+    let identity = Pubkey::new_unique();
+    let dir =
+        std::env::temp_dir().join(format!("test-sanctum-ledger-import-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let dir = dir.to_str().unwrap();
+
+    write_json_file(
+        &format!("{}/rewards_{}_746.json", dir, identity),
+        &slot_epoch_rewards(),
+    )
+    .unwrap();
+    std::fs::write(format!("{}/plan_{}_746.json", dir, identity), "{").unwrap();
+    std::fs::write(format!("{}/sanctum-lst-list.toml", dir), "").unwrap();
+
+    let mut ledger = Ledger::open_in_memory().unwrap();
+    let summary = ledger.import_dir(dir).unwrap();
+    assert_eq!(summary.calculations, 1);
+    assert_eq!(summary.transfers, 0);
+    assert_eq!(summary.skipped, 0);
+    // The truncated plan is reported, other files are ignored
+    assert_eq!(summary.failed.len(), 1);
+
+    // Importing again doesn't duplicate records
+    let summary = ledger.import_dir(dir).unwrap();
+    assert_eq!(summary.calculations, 0);
+    assert_eq!(summary.skipped, 1);
+
+    let calculations = ledger.calculations(&LedgerQuery::default()).unwrap();
+    assert_eq!(calculations.len(), 1);
+    assert_eq!(calculations[0].identity_pubkey, identity.to_string());
+    assert_eq!(calculations[0].rewards, slot_epoch_rewards());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,607 @@
+use chrono::DateTime;
+use chrono::Utc;
+use sanctum_block_rewards_cli::check_dune_row_freshness;
+use sanctum_block_rewards_cli::read_json_file;
+use sanctum_block_rewards_cli::DuneClient;
+use sanctum_block_rewards_cli::DuneQuery;
+use sanctum_block_rewards_cli::DuneResultRow;
+use sanctum_block_rewards_cli::DuneRewardSource;
+use sanctum_block_rewards_cli::DuneRow;
+use sanctum_block_rewards_cli::EpochRewards;
+use sanctum_block_rewards_cli::LedgerRewardSource;
+use sanctum_block_rewards_cli::RewardSource;
+use sanctum_block_rewards_cli::SlotReward;
+use sanctum_block_rewards_cli::DUNE_INDEXING_LAG_SECS;
+use serde_json::json;
+use serde_json::Value;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::epoch_schedule::EpochSchedule;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+mod common;
+
+use common::output_text;
+use common::run_cli;
+use common::MockDune;
+use common::MockRpc;
+use common::MockServer;
+use common::DUNE_EXECUTION_ID;
+
+const IDENTITY: &str = "GdnSyH3YtwcxFvQrVVJMm1JhTS4QVX7MFsX56uJLUfiZ";
+const OTHER_IDENTITY: &str = "DRpbCBMxVnDK7maPM5tGv6MvB3v1sRMC86PZ8okm21hy";
+
+fn block_json(leader: &str, fee: i64) -> String {
+    format!(
+        r#"{{
+            "previousBlockhash": "5dLNuYn1R9uBQNBf8Tg7fh4n2s8Y1pHhHAPWUBv5FWSG",
+            "blockhash": "7nXaMB4gPuWmZ3TP5ZdC1ubKj4NzR9nNE8aArvYYPnZv",
+            "parentSlot": 322271999,
+            "rewards": [
+                {{
+                    "pubkey": "{leader}",
+                    "lamports": {fee},
+                    "postBalance": 1000000000,
+                    "rewardType": "Fee",
+                    "commission": null
+                }}
+            ],
+            "blockTime": 1741234567,
+            "blockHeight": 300000000
+        }}"#
+    )
+}
+
+#[tokio::test]
+async fn test_ledger_reward_source() {
+    let blocks_dir = std::env::temp_dir().join("test-sanctum-ledger");
+    let _ = std::fs::remove_dir_all(&blocks_dir);
+    std::fs::create_dir_all(&blocks_dir).unwrap();
+
+    // Raw getBlock response, slot taken from the file name
+    std::fs::write(
+        blocks_dir.join("322272000.json"),
+        format!(
+            r#"{{ "jsonrpc": "2.0", "id": 1, "result": {} }}"#,
+            block_json(IDENTITY, 10_000)
+        ),
+    )
+    .unwrap();
+    // `solana block --output json` style with a slot field, in a JSON lines dump
+    std::fs::write(
+        blocks_dir.join("dump.jsonl"),
+        [
+            block_json(IDENTITY, 25_000).replacen('{', r#"{ "slot": 322272002, "#, 1),
+            block_json(OTHER_IDENTITY, 7_000).replacen('{', r#"{ "slot": 322272004, "#, 1),
+            // Previous epoch
+            block_json(IDENTITY, 1).replacen('{', r#"{ "slot": 322271999, "#, 1),
+        ]
+        .map(|block| block.replace('\n', ""))
+        .join("\n"),
+    )
+    .unwrap();
+    // Not a block file
+    std::fs::write(blocks_dir.join("README.md"), "ignored").unwrap();
+
+    let source =
+        LedgerRewardSource::new(blocks_dir.clone(), EpochSchedule::without_warmup(), false);
+    let identity = Pubkey::from_str(IDENTITY).unwrap();
+    let epoch = 746;
+
+    let leader_slots = source.fetch_leader_slots(&identity, epoch).await.unwrap();
+    assert_eq!(leader_slots, vec![322272000, 322272002]);
+
+    // The blocks read for the leader slots are reused for the rewards
+    std::fs::remove_dir_all(&blocks_dir).unwrap();
+
+    assert_eq!(
+        source
+            .fetch_epoch_rewards(&identity, epoch, &[322272000, 322272001, 322272002])
+            .await
+            .unwrap(),
+        EpochRewards {
+            total_block_rewards: 35_000,
+            source: Some("ledger".to_string()),
+            num_leader_slots: Some(3),
+            slot_rewards: Some(vec![
+                SlotReward {
+                    slot: 322272000,
+                    lamports: 10_000,
+                    skipped: false,
+                    fee_breakdown: None,
+                },
+                SlotReward {
+                    slot: 322272001,
+                    lamports: 0,
+                    skipped: true,
+                    fee_breakdown: None,
+                },
+                SlotReward {
+                    slot: 322272002,
+                    lamports: 25_000,
+                    skipped: false,
+                    fee_breakdown: None,
+                },
+            ]),
+            fee_breakdown: None,
+            mev_rewards: None,
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_ledger_reward_source_negative_reward() {
+    let blocks_dir = std::env::temp_dir().join("test-sanctum-ledger-negative");
+    let _ = std::fs::remove_dir_all(&blocks_dir);
+    std::fs::create_dir_all(&blocks_dir).unwrap();
+    std::fs::write(
+        blocks_dir.join("322272000.json"),
+        block_json(IDENTITY, -5_000),
+    )
+    .unwrap();
+
+    let source = LedgerRewardSource::new(blocks_dir, EpochSchedule::without_warmup(), false);
+    let identity = Pubkey::from_str(IDENTITY).unwrap();
+
+    assert!(source
+        .fetch_epoch_rewards(&identity, 746, &[322272000])
+        .await
+        .is_err());
+}
+
+#[test]
+fn test_dune_query_parse_rows() {
+    let rows: Vec<DuneResultRow> = serde_json::from_str(
+        r#"[
+            { "epoch": 746, "total_fees": "18446744073709551615", "leader_slots": 12 },
+            { "epoch": 745.0, "total_fees": 2.5e10, "leader_slots": 10 }
+        ]"#,
+    )
+    .unwrap();
+    let column_names = ["epoch", "total_fees", "leader_slots"].map(String::from);
+
+    // The default columns don't match this query
+    let err = DuneQuery::default()
+        .parse_rows(&column_names, &rows)
+        .unwrap_err();
+    assert!(err.contains("no `block_rewards` column"));
+    assert!(err.contains("epoch, total_fees, leader_slots"));
+
+    let query = DuneQuery {
+        rewards_column: "total_fees".to_string(),
+        ..DuneQuery::default()
+    };
+    let expected = vec![
+        DuneRow {
+            epoch: 746,
+            total_block_rewards: u64::MAX,
+            last_slot: None,
+            last_block_time: None,
+        },
+        DuneRow {
+            epoch: 745,
+            total_block_rewards: 25_000_000_000,
+            last_slot: None,
+            last_block_time: None,
+        },
+    ];
+    assert_eq!(query.parse_rows(&column_names, &rows), Ok(expected.clone()));
+    // Column names taken from the rows when missing from the metadata
+    assert_eq!(query.parse_rows(&[], &rows), Ok(expected));
+
+    let query = DuneQuery {
+        rewards_column: "epoch".to_string(),
+        epoch_column: "leader_slots".to_string(),
+        ..DuneQuery::default()
+    };
+    assert_eq!(
+        query.parse_rows(&column_names, &rows).map(|rows| rows
+            .iter()
+            .map(|row| (row.epoch, row.total_block_rewards))
+            .collect()),
+        Ok(vec![(12, 746), (10, 745)])
+    );
+
+    let rows: Vec<DuneResultRow> =
+        serde_json::from_str(r#"[{ "epoch": 746, "block_rewards": -1 }]"#).unwrap();
+    assert!(DuneQuery::default()
+        .parse_rows(&[], &rows)
+        .unwrap_err()
+        .contains("Invalid value -1 in column `block_rewards`"));
+}
+
+#[test]
+fn test_check_dune_row_freshness() {
+    let epoch_last_slot = 322703999;
+    let epoch_end_time = 1_741_400_000;
+    let row = DuneRow {
+        epoch: 746,
+        total_block_rewards: 1,
+        last_slot: None,
+        last_block_time: None,
+    };
+
+    let rows: Vec<DuneResultRow> = serde_json::from_str(
+        r#"[{ "epoch": 746, "block_rewards": 1, "last_slot": 322703999, "last_block_time": "2025-03-08 02:13:20.000 UTC" }]"#,
+    )
+    .unwrap();
+    assert_eq!(
+        DuneQuery::default().parse_rows(&[], &rows),
+        Ok(vec![DuneRow {
+            last_slot: Some(epoch_last_slot),
+            last_block_time: Some(epoch_end_time),
+            ..row
+        }])
+    );
+
+    // Last slot takes precedence
+    let with_last_slot = |last_slot| DuneRow {
+        last_slot: Some(last_slot),
+        last_block_time: Some(epoch_end_time),
+        ..row
+    };
+    assert_eq!(
+        check_dune_row_freshness(
+            &with_last_slot(epoch_last_slot + 10),
+            epoch_last_slot,
+            None,
+            None
+        ),
+        Ok(true)
+    );
+    assert!(check_dune_row_freshness(
+        &with_last_slot(epoch_last_slot - 1),
+        epoch_last_slot,
+        Some(epoch_end_time),
+        None
+    )
+    .is_err());
+
+    let with_last_block_time = |last_block_time| DuneRow {
+        last_block_time: Some(last_block_time),
+        ..row
+    };
+    assert_eq!(
+        check_dune_row_freshness(
+            &with_last_block_time(epoch_end_time),
+            epoch_last_slot,
+            Some(epoch_end_time),
+            None
+        ),
+        Ok(true)
+    );
+    assert!(check_dune_row_freshness(
+        &with_last_block_time(epoch_end_time - 600),
+        epoch_last_slot,
+        Some(epoch_end_time),
+        None
+    )
+    .is_err());
+
+    // Falls back to the execution time
+    let ended_at = |secs_after_epoch_end| {
+        DateTime::<Utc>::from_timestamp(epoch_end_time + secs_after_epoch_end, 0)
+    };
+    assert_eq!(
+        check_dune_row_freshness(
+            &row,
+            epoch_last_slot,
+            Some(epoch_end_time),
+            ended_at(DUNE_INDEXING_LAG_SECS)
+        ),
+        Ok(true)
+    );
+    assert!(
+        check_dune_row_freshness(&row, epoch_last_slot, Some(epoch_end_time), ended_at(3600))
+            .unwrap_err()
+            .contains("60 minutes")
+    );
+    assert_eq!(
+        check_dune_row_freshness(&row, epoch_last_slot, None, ended_at(0)),
+        Ok(false)
+    );
+}
+
+fn dune_source<'a>(rpc: &'a RpcClient, dune: &MockServer) -> DuneRewardSource<'a> {
+    DuneRewardSource {
+        rpc,
+        epoch_schedule: EpochSchedule::without_warmup(),
+        client: DuneClient::new("test-api-key").with_base_url(&dune.url),
+        query: DuneQuery::default(),
+        timeout_secs: 60,
+        max_age_secs: None,
+        execution_id: None,
+        allow_stale: false,
+        allow_partial: false,
+        quiet: true,
+    }
+}
+
+// Epoch 746 ends at slot 322703999
+fn dune_row(epoch: u64, block_rewards: u64) -> Value {
+    json!({ "epoch": epoch, "block_rewards": block_rewards, "last_slot": 322704100 })
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_dune_reward_source() {
+    let rpc_server = MockRpc::mainnet_fixtures().start();
+    let rpc = rpc_server.rpc();
+    let dune = MockDune::new(vec![
+        dune_row(745, 1_000_000_000),
+        dune_row(746, 1_234_567_890),
+    ])
+    .with_states(&[
+        "QUERY_STATE_PENDING",
+        "QUERY_STATE_EXECUTING",
+        "QUERY_STATE_COMPLETED",
+    ])
+    .start();
+    let source = dune_source(&rpc, &dune);
+    let identity = Pubkey::from_str(IDENTITY).unwrap();
+
+    let leader_slots = source.fetch_leader_slots(&identity, 746).await.unwrap();
+    let rewards = source
+        .fetch_epoch_rewards(&identity, 746, &leader_slots)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        rewards,
+        EpochRewards {
+            total_block_rewards: 1_234_567_890,
+            source: Some("dune".to_string()),
+            num_leader_slots: Some(3),
+            slot_rewards: None,
+            fee_breakdown: None,
+            mev_rewards: None,
+        }
+    );
+
+    let requests = dune.requests();
+    let execute = requests
+        .iter()
+        .find(|request| request.method == "POST")
+        .unwrap();
+    assert_eq!(
+        execute.url,
+        format!("/query/{}/execute", DuneQuery::default().query_id)
+    );
+    assert_eq!(execute.api_key.as_deref(), Some("test-api-key"));
+    assert_eq!(
+        serde_json::from_str::<Value>(&execute.body).unwrap(),
+        json!({ "query_parameters": { "epoch": "746", "identity_pubkey": IDENTITY } })
+    );
+    assert_eq!(
+        requests
+            .iter()
+            .filter(|request| request.url.ends_with("/status"))
+            .count(),
+        3
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_dune_reward_source_unchecked_freshness() {
+    // Without a last slot column, the epoch's end time is needed but can't be fetched
+    let rpc_server = MockRpc::mainnet_fixtures().start();
+    let rpc = rpc_server.rpc();
+    let dune = MockDune::new(vec![
+        json!({ "epoch": 746, "block_rewards": 1_234_567_890 }),
+    ])
+    .start();
+    let identity = Pubkey::from_str(IDENTITY).unwrap();
+
+    let source = dune_source(&rpc, &dune);
+    let leader_slots = source.fetch_leader_slots(&identity, 746).await.unwrap();
+    let err = source
+        .fetch_epoch_rewards(&identity, 746, &leader_slots)
+        .await
+        .unwrap_err();
+    assert!(
+        err.contains("could not check whether Dune had indexed all of epoch 746"),
+        "{}",
+        err
+    );
+
+    let source = DuneRewardSource {
+        allow_stale: true,
+        ..dune_source(&rpc, &dune)
+    };
+    let rewards = source
+        .fetch_epoch_rewards(&identity, 746, &leader_slots)
+        .await
+        .unwrap();
+    assert_eq!(rewards.total_block_rewards, 1_234_567_890);
+}
+
+/// Runs `calculate --source dune` under a fresh `$HOME` against the mock Dune API
+#[test]
+fn test_calculate_with_dune() {
+    let home = std::env::temp_dir().join(format!(
+        "test-sanctum-calculate-dune-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&home);
+    let rpc_server = MockRpc::mainnet_fixtures().start();
+    let dune = MockDune::new(vec![dune_row(746, 1_234_567_890)]).start();
+
+    let calculate = || {
+        output_text(&run_cli(
+            &home,
+            &[
+                "--rpc-url",
+                &rpc_server.url,
+                "calculate",
+                "--source",
+                "dune",
+                "--dune-api-key",
+                "test-api-key",
+                "--dune-api-url",
+                &dune.url,
+                "--identity-pubkey",
+                IDENTITY,
+                "--epoch",
+                "746",
+                "--yes",
+            ],
+        ))
+    };
+
+    let output = calculate();
+
+    let path = home.join(format!(".local/sanctum/rewards_{}_746.json", IDENTITY));
+    assert_eq!(
+        read_json_file::<Value>(path.to_str().unwrap())
+            .unwrap_or_else(|e| panic!("{}\n{}", e, output)),
+        json!({
+            "total_block_rewards": 1_234_567_890u64,
+            "source": "dune",
+            "num_leader_slots": 3,
+        })
+    );
+    assert!(dune
+        .requests()
+        .iter()
+        .any(|request| request.api_key.as_deref() == Some("test-api-key")));
+
+    // An existing rewards file is reused without querying Dune again
+    let num_requests = dune.requests().len();
+    let output = calculate();
+    assert!(output.contains("Rewards file found"), "{}", output);
+    assert_eq!(dune.requests().len(), num_requests);
+
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[tokio::test]
+async fn test_dune_reward_source_missing_epoch() {
+    let rpc_server = MockRpc::mainnet_fixtures().start();
+    let rpc = rpc_server.rpc();
+    let dune = MockDune::new(vec![dune_row(745, 1_000_000_000)]).start();
+    let source = dune_source(&rpc, &dune);
+    let identity = Pubkey::from_str(IDENTITY).unwrap();
+
+    assert_eq!(
+        source.fetch_epoch_rewards(&identity, 746, &[]).await,
+        Err("Error: No rewards data found for epoch 746".to_string())
+    );
+
+    // Ranges only return the epochs Dune has rows for
+    let epochs_rewards = source
+        .fetch_epochs_rewards(&identity, &[745, 746])
+        .await
+        .unwrap();
+    assert_eq!(epochs_rewards.keys().collect::<Vec<_>>(), vec![&745]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_dune_reward_source_failed_execution() {
+    let rpc_server = MockRpc::mainnet_fixtures().start();
+    let rpc = rpc_server.rpc();
+    let identity = Pubkey::from_str(IDENTITY).unwrap();
+
+    for (state, error) in [
+        ("QUERY_STATE_FAILED", "Error: Query execution failed"),
+        ("QUERY_STATE_CANCELLED", "Error: Query execution cancelled"),
+    ] {
+        let dune = MockDune::new(vec![dune_row(746, 1_234_567_890)])
+            .with_states(&["QUERY_STATE_EXECUTING", state])
+            .start();
+        let source = dune_source(&rpc, &dune);
+
+        assert_eq!(
+            source.fetch_epoch_rewards(&identity, 746, &[]).await,
+            Err(error.to_string())
+        );
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_dune_reward_source_timeout() {
+    let rpc_server = MockRpc::mainnet_fixtures().start();
+    let rpc = rpc_server.rpc();
+    let identity = Pubkey::from_str(IDENTITY).unwrap();
+    let dune = MockDune::new(vec![dune_row(746, 1_234_567_890)])
+        .with_states(&["QUERY_STATE_EXECUTING"])
+        .start();
+    let source = DuneRewardSource {
+        timeout_secs: 15,
+        ..dune_source(&rpc, &dune)
+    };
+
+    let err = source
+        .fetch_epoch_rewards(&identity, 746, &[])
+        .await
+        .unwrap_err();
+    assert!(err.contains(&format!("--execution-id {}", DUNE_EXECUTION_ID)));
+
+    // Waiting for the timed out execution doesn't execute the query again
+    let dune = MockDune::new(vec![dune_row(746, 1_234_567_890)]).start();
+    let source = DuneRewardSource {
+        execution_id: Some(DUNE_EXECUTION_ID.to_string()),
+        ..dune_source(&rpc, &dune)
+    };
+    assert_eq!(
+        source
+            .fetch_epoch_rewards(&identity, 746, &[])
+            .await
+            .unwrap()
+            .total_block_rewards,
+        1_234_567_890
+    );
+    assert!(dune
+        .requests()
+        .iter()
+        .all(|request| request.method != "POST"));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_dune_reward_source_short_timeout() {
+    let rpc_server = MockRpc::mainnet_fixtures().start();
+    let rpc = rpc_server.rpc();
+    let identity = Pubkey::from_str(IDENTITY).unwrap();
+    let dune = MockDune::new(vec![dune_row(746, 1_234_567_890)]).start();
+
+    // A timeout shorter than the poll interval still polls once
+    let source = DuneRewardSource {
+        timeout_secs: 1,
+        ..dune_source(&rpc, &dune)
+    };
+    assert_eq!(
+        source
+            .fetch_epoch_rewards(&identity, 746, &[])
+            .await
+            .unwrap()
+            .total_block_rewards,
+        1_234_567_890
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_dune_reward_source_partial_execution() {
+    let rpc_server = MockRpc::mainnet_fixtures().start();
+    let rpc = rpc_server.rpc();
+    let identity = Pubkey::from_str(IDENTITY).unwrap();
+    let dune = MockDune::new(vec![dune_row(746, 1_234_567_890)])
+        .with_states(&["QUERY_STATE_COMPLETED_PARTIAL"])
+        .start();
+
+    let err = dune_source(&rpc, &dune)
+        .fetch_epoch_rewards(&identity, 746, &[])
+        .await
+        .unwrap_err();
+    assert!(err.contains("--allow-partial"), "{}", err);
+
+    let source = DuneRewardSource {
+        allow_partial: true,
+        ..dune_source(&rpc, &dune)
+    };
+    assert_eq!(
+        source
+            .fetch_epoch_rewards(&identity, 746, &[])
+            .await
+            .unwrap()
+            .total_block_rewards,
+        1_234_567_890
+    );
+}
@@ -0,0 +1,1034 @@
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+use proptest::prelude::*;
+use sanctum_block_rewards_cli::build_distribution_plan;
+use sanctum_block_rewards_cli::build_distribution_tx;
+use sanctum_block_rewards_cli::calculate_epoch_rewards;
+use sanctum_block_rewards_cli::calculate_rewards_split;
+use sanctum_block_rewards_cli::check_plan_rewards;
+use sanctum_block_rewards_cli::decode_tx;
+use sanctum_block_rewards_cli::distribution_ixs;
+use sanctum_block_rewards_cli::fallback_lst_info;
+use sanctum_block_rewards_cli::find_distribution;
+use sanctum_block_rewards_cli::get_stake_pool;
+use sanctum_block_rewards_cli::parse_distribution_memo;
+use sanctum_block_rewards_cli::parse_distribution_tx;
+use sanctum_block_rewards_cli::read_rewards_file;
+use sanctum_block_rewards_cli::transfer_to_reserve_and_update_stake_pool_balance_ixs_for_account;
+use sanctum_block_rewards_cli::validate_distribution_plan;
+use sanctum_block_rewards_cli::validate_stake_pool;
+use sanctum_block_rewards_cli::verify_distribution_transfer;
+use sanctum_block_rewards_cli::verify_signed_transfer;
+use sanctum_block_rewards_cli::CalculateOpts;
+use sanctum_block_rewards_cli::DistributionMemo;
+use sanctum_block_rewards_cli::DistributionPlan;
+use sanctum_block_rewards_cli::DistributionPlanArgs;
+use sanctum_block_rewards_cli::DistributionTransfer;
+use sanctum_block_rewards_cli::DistributionTxArgs;
+use sanctum_block_rewards_cli::EpochRewards;
+use sanctum_block_rewards_cli::ExpectedBalances;
+use sanctum_block_rewards_cli::LstInfo;
+use sanctum_block_rewards_cli::PlanInstruction;
+use sanctum_block_rewards_cli::Pool;
+use sanctum_block_rewards_cli::PoolBalance;
+use sanctum_block_rewards_cli::RewardSourceOpts;
+use sanctum_block_rewards_cli::RewardsSplit;
+use sanctum_block_rewards_cli::RewardsSplitBps;
+use sanctum_block_rewards_cli::SignedTransferFile;
+use sanctum_block_rewards_cli::SlotReward;
+use sanctum_block_rewards_cli::DEFAULT_MAX_DISTRIBUTION_SIGNATURES;
+use sanctum_block_rewards_cli::KNOWN_STAKE_POOL_PROGRAM_IDS;
+use sanctum_block_rewards_cli::MAX_BPS;
+use serde_json::json;
+use serde_json::Value;
+use solana_sdk::compute_budget;
+use solana_sdk::hash::Hash;
+use solana_sdk::message::v0::Message;
+use solana_sdk::message::VersionedMessage;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::write_keypair_file;
+use solana_sdk::signature::Keypair;
+use solana_sdk::signature::Signature;
+use solana_sdk::signer::Signer;
+use solana_sdk::system_instruction::advance_nonce_account;
+use solana_sdk::system_instruction::SystemInstruction;
+use solana_sdk::system_program;
+use solana_sdk::transaction::VersionedTransaction;
+use spl_stake_pool_interface::StakePool;
+use std::str::FromStr;
+
+mod common;
+
+use common::dumped_message;
+use common::epoch_rewards;
+use common::output_text;
+use common::run_cli;
+use common::MockRpc;
+use common::StakePoolFixture;
+use common::FIXTURE_EPOCH;
+use common::FIXTURE_SKIPPED_SLOT;
+use common::IDENTITY;
+
+#[test]
+fn test_calculate_rewards_split() {
+    let bps = RewardsSplitBps {
+        total_rewards_bps: 7_500,
+        lst_rewards_bps: 5_000,
+        mev_rewards_bps: 10_000,
+    };
+
+    assert_eq!(
+        calculate_rewards_split(&epoch_rewards(10_000_000_000, None), &bps),
+        Ok(RewardsSplit {
+            total_block_rewards: 10_000_000_000,
+            stake_pool_rewards: 7_500_000_000,
+            lst_rewards: 3_750_000_000,
+            total_mev_rewards: 0,
+            stake_pool_mev_rewards: 0,
+            lst_mev_rewards: 0,
+            transfer_lamports: 3_750_000_000,
+        })
+    );
+
+    assert_eq!(
+        calculate_rewards_split(&epoch_rewards(10_000_000_000, Some(2_000_000_000)), &bps),
+        Ok(RewardsSplit {
+            total_block_rewards: 10_000_000_000,
+            stake_pool_rewards: 7_500_000_000,
+            lst_rewards: 3_750_000_000,
+            total_mev_rewards: 2_000_000_000,
+            stake_pool_mev_rewards: 1_500_000_000,
+            lst_mev_rewards: 1_500_000_000,
+            transfer_lamports: 5_250_000_000,
+        })
+    );
+
+    // More than 100%
+    let over_100_pct = RewardsSplitBps {
+        total_rewards_bps: 10_001,
+        ..bps
+    };
+    assert!(calculate_rewards_split(&epoch_rewards(10_000_000_000, None), &over_100_pct).is_err());
+
+    // LST block and MEV rewards together overflow u64
+    let all = RewardsSplitBps {
+        total_rewards_bps: 10_000,
+        lst_rewards_bps: 10_000,
+        mev_rewards_bps: 10_000,
+    };
+    assert!(calculate_rewards_split(&epoch_rewards(u64::MAX, Some(1)), &all).is_err());
+}
+
+/// `value * bps / MAX_BPS` rounded down, from the quotient and remainder of
+/// `value / MAX_BPS` rather than the wide product `checked_pct` uses.
+/// Neither term can overflow u64 since `bps <= MAX_BPS`.
+fn reference_pct(value: u64, bps: u64) -> u64 {
+    let quotient = value / MAX_BPS;
+    let remainder = value % MAX_BPS;
+    quotient * bps + remainder * bps / MAX_BPS
+}
+
+proptest! {
+    #[test]
+    fn prop_calculate_rewards_split(
+        total_block_rewards: u64,
+        mev_rewards: Option<u64>,
+        total_rewards_bps in 0..=MAX_BPS,
+        lst_rewards_bps in 0..=MAX_BPS,
+        mev_rewards_bps in 0..=MAX_BPS,
+    ) {
+        let bps = RewardsSplitBps {
+            total_rewards_bps,
+            lst_rewards_bps,
+            mev_rewards_bps,
+        };
+        let total_mev_rewards = mev_rewards.unwrap_or(0);
+
+        let stake_pool_rewards = reference_pct(total_block_rewards, total_rewards_bps);
+        let lst_rewards = reference_pct(stake_pool_rewards, lst_rewards_bps);
+        let stake_pool_mev_rewards = reference_pct(total_mev_rewards, total_rewards_bps);
+        let lst_mev_rewards = reference_pct(stake_pool_mev_rewards, mev_rewards_bps);
+        let transfer_lamports = u128::from(lst_rewards) + u128::from(lst_mev_rewards);
+
+        match calculate_rewards_split(&epoch_rewards(total_block_rewards, mev_rewards), &bps) {
+            Ok(split) => {
+                prop_assert!(split.lst_rewards <= split.stake_pool_rewards);
+                prop_assert!(split.stake_pool_rewards <= split.total_block_rewards);
+                prop_assert!(split.lst_mev_rewards <= split.stake_pool_mev_rewards);
+                prop_assert!(split.stake_pool_mev_rewards <= split.total_mev_rewards);
+                // Never more than the validator earned
+                prop_assert!(
+                    u128::from(split.transfer_lamports)
+                        <= u128::from(total_block_rewards) + u128::from(total_mev_rewards)
+                );
+                prop_assert_eq!(
+                    split,
+                    RewardsSplit {
+                        total_block_rewards,
+                        stake_pool_rewards,
+                        lst_rewards,
+                        total_mev_rewards,
+                        stake_pool_mev_rewards,
+                        lst_mev_rewards,
+                        transfer_lamports: transfer_lamports as u64,
+                    }
+                );
+            }
+            // Only the sum of two shares of up to u64::MAX each can overflow
+            Err(_) => prop_assert!(transfer_lamports > u128::from(u64::MAX)),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_calculate_epoch_rewards_rpc() {
+    let server = MockRpc::mainnet_fixtures().start();
+    let rpc = server.rpc();
+    let identity_pubkey = Pubkey::from_str(IDENTITY).unwrap();
+
+    let rewards = calculate_epoch_rewards(
+        &rpc,
+        &identity_pubkey,
+        FIXTURE_EPOCH,
+        CalculateOpts {
+            source: RewardSourceOpts::Rpc { detailed: false },
+            mev: None,
+            quiet: true,
+        },
+    )
+    .await
+    .unwrap();
+
+    let slot_reward = |slot, lamports, skipped| SlotReward {
+        slot,
+        lamports,
+        skipped,
+        fee_breakdown: None,
+    };
+    assert_eq!(
+        rewards,
+        EpochRewards {
+            total_block_rewards: 23_481_923 + 18_930_514,
+            source: Some("rpc".to_string()),
+            num_leader_slots: Some(3),
+            slot_rewards: Some(vec![
+                slot_reward(322272000, 23_481_923, false),
+                slot_reward(322272001, 18_930_514, false),
+                slot_reward(FIXTURE_SKIPPED_SLOT, 0, true),
+            ]),
+            fee_breakdown: None,
+            mev_rewards: None,
+        }
+    );
+}
+
+/// Runs `calculate` and `transfer` against the recorded mainnet fixtures, with the
+/// transfer dumped instead of sent
+#[test]
+fn test_transfer_flow() {
+    let dir =
+        std::env::temp_dir().join(format!("test-sanctum-transfer-flow-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let home = dir.join("home");
+    let payer_path = dir.join("payer.json");
+    let lst_list_path = dir.join("sanctum-lst-list.toml");
+
+    let payer = Keypair::new();
+    write_keypair_file(&payer, &payer_path).unwrap();
+    let fixture = StakePoolFixture::new(KNOWN_STAKE_POOL_PROGRAM_IDS[1], FIXTURE_EPOCH + 1);
+    fixture.write_lst_list(&lst_list_path);
+
+    let server = fixture
+        .with_accounts(MockRpc::mainnet_fixtures())
+        .with_result(
+            "getBalance",
+            None,
+            json!({ "context": { "slot": 322800000 }, "value": 10_000_000_000u64 }),
+        )
+        .with_result("getMinimumBalanceForRentExemption", None, json!(890_880))
+        .with_result("getSignaturesForAddress", None, json!([]))
+        .with_result(
+            "getLatestBlockhash",
+            None,
+            json!({
+                "context": { "slot": 322800000 },
+                "value": {
+                    "blockhash": Hash::new_unique().to_string(),
+                    "lastValidBlockHeight": 300518395
+                }
+            }),
+        )
+        .start();
+
+    let epoch = FIXTURE_EPOCH.to_string();
+    let stake_pool = fixture.stake_pool.to_string();
+    let transfer = || {
+        output_text(&run_cli(
+            &home,
+            &[
+                "--rpc-url",
+                &server.url,
+                "--send-mode",
+                "dump-msg",
+                "transfer",
+                "--payer",
+                payer_path.to_str().unwrap(),
+                "--identity-pubkey",
+                IDENTITY,
+                "--epoch",
+                &epoch,
+                "--stake-pool-pubkey",
+                &stake_pool,
+                "--total-rewards-pct",
+                "75",
+                "--lst-rewards-pct",
+                "100",
+                "--lst-list",
+                lst_list_path.to_str().unwrap(),
+                "--yes",
+            ],
+        ))
+    };
+
+    // Nothing to transfer before the rewards are calculated
+    let output = transfer();
+    assert!(output.contains("Failed to find rewards"), "{}", output);
+    assert!(dumped_message(&output).is_none(), "{}", output);
+
+    let output = output_text(&run_cli(
+        &home,
+        &[
+            "--rpc-url",
+            &server.url,
+            "calculate",
+            "--identity-pubkey",
+            IDENTITY,
+            "--epoch",
+            &epoch,
+            "--yes",
+        ],
+    ));
+    let rewards_file_path = home.join(format!(
+        ".local/sanctum/rewards_{}_{}.json",
+        IDENTITY, FIXTURE_EPOCH
+    ));
+    let rewards = read_rewards_file(rewards_file_path.to_str().unwrap())
+        .unwrap()
+        .unwrap_or_else(|| panic!("{}", output));
+    assert_eq!(rewards.total_block_rewards, 23_481_923 + 18_930_514);
+    assert_eq!(rewards.num_leader_slots, Some(3));
+    assert_eq!(rewards.source.as_deref(), Some("rpc"));
+
+    let output = transfer();
+    let message = dumped_message(&output).unwrap_or_else(|| panic!("{}", output));
+    let account_keys = message.static_account_keys();
+    assert_eq!(account_keys[0], payer.pubkey());
+
+    // dump-msg doesn't prepend compute budget instructions
+    let instructions = message.instructions();
+    assert_eq!(instructions.len(), 3, "{}", output);
+    assert_eq!(
+        bincode::deserialize::<SystemInstruction>(&instructions[0].data).unwrap(),
+        SystemInstruction::Transfer {
+            lamports: 31_809_327
+        }
+    );
+    assert_eq!(
+        account_keys[usize::from(instructions[0].accounts[1])],
+        fixture.reserve_stake
+    );
+    assert_eq!(
+        String::from_utf8(instructions[2].data.clone()).unwrap(),
+        format!(
+            "sbr-{}-{}-{}-31809327",
+            FIXTURE_EPOCH, IDENTITY, fixture.stake_pool
+        )
+    );
+
+    // The earlier distribution check looked for the memo on chain
+    assert!(server
+        .requests()
+        .iter()
+        .any(|request| request.body.contains("getSignaturesForAddress")));
+
+    // A reviewed plan is signed without any RPC call given a blockhash and a CU limit
+    let output = output_text(&run_cli(
+        &home,
+        &[
+            "--rpc-url",
+            &server.url,
+            "plan",
+            "--payer-pubkey",
+            &payer.pubkey().to_string(),
+            "--identity-pubkey",
+            IDENTITY,
+            "--epoch",
+            &epoch,
+            "--stake-pool-pubkey",
+            &stake_pool,
+            "--total-rewards-pct",
+            "75",
+            "--lst-rewards-pct",
+            "100",
+            "--lst-list",
+            lst_list_path.to_str().unwrap(),
+        ],
+    ));
+    let plan_file_path = home.join(format!(
+        ".local/sanctum/plan_{}_{}.json",
+        IDENTITY, FIXTURE_EPOCH
+    ));
+    assert!(plan_file_path.exists(), "{}", output);
+
+    let num_requests = server.requests().len();
+    let blockhash = Hash::new_unique();
+    let output = output_text(&run_cli(
+        &home,
+        &[
+            "--rpc-url",
+            &server.url,
+            "transfer",
+            "--payer",
+            payer_path.to_str().unwrap(),
+            "--sign-only",
+            plan_file_path.to_str().unwrap(),
+            "--blockhash",
+            &blockhash.to_string(),
+            "--compute-unit-limit",
+            "100000",
+        ],
+    ));
+    assert_eq!(server.requests().len(), num_requests, "{}", output);
+
+    let signed_transfer_path = home.join(format!(
+        ".local/sanctum/signed_transfer_{}_{}.json",
+        IDENTITY, FIXTURE_EPOCH
+    ));
+    let signed_transfer: SignedTransferFile = serde_json::from_str(
+        &std::fs::read_to_string(&signed_transfer_path).unwrap_or_else(|_| panic!("{}", output)),
+    )
+    .unwrap();
+    assert_eq!(signed_transfer.blockhash, blockhash.to_string());
+    let tx = decode_tx(&signed_transfer.transaction).unwrap();
+    assert_eq!(*tx.message.recent_blockhash(), blockhash);
+    let transfer = verify_signed_transfer(&tx, &signed_transfer, &fixture.reserve_stake).unwrap();
+    assert_eq!(transfer.lamports, 31_809_327);
+
+    drop(server);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_build_distribution_tx() {
+    let identity_pubkey = Pubkey::from_str(IDENTITY).unwrap();
+    let payer_pubkey = Pubkey::new_unique();
+    let fixture = StakePoolFixture::new(KNOWN_STAKE_POOL_PROGRAM_IDS[1], FIXTURE_EPOCH + 1);
+    let server = fixture.with_accounts(MockRpc::mainnet_fixtures()).start();
+    let rpc = server.rpc();
+
+    let (stake_pool, stake_pool_account) =
+        validate_stake_pool(&rpc, &fixture.stake_pool, FIXTURE_EPOCH + 1)
+            .await
+            .unwrap();
+    assert_eq!(stake_pool.reserve_stake, fixture.reserve_stake);
+    assert_eq!(stake_pool_account, fixture.stake_pool_account());
+
+    // Stale pools are refused
+    assert!(
+        validate_stake_pool(&rpc, &fixture.stake_pool, FIXTURE_EPOCH + 2)
+            .await
+            .is_err()
+    );
+
+    let split = calculate_rewards_split(
+        &epoch_rewards(42_412_437, None),
+        &RewardsSplitBps {
+            total_rewards_bps: 7_500,
+            lst_rewards_bps: 10_000,
+            mev_rewards_bps: 0,
+        },
+    )
+    .unwrap();
+    assert_eq!(split.transfer_lamports, 31_809_327);
+
+    // Without compute budget instructions the tx is built without any RPC call
+    let num_requests = server.requests().len();
+    let message = build_distribution_tx(
+        &rpc,
+        &DistributionTxArgs {
+            payer_pubkey,
+            identity_pubkey,
+            stake_pool_pubkey: fixture.stake_pool,
+            epoch: FIXTURE_EPOCH,
+            transfer_lamports: split.transfer_lamports,
+            fee_limit_cb: 0,
+            nonce: None,
+        },
+        &stake_pool_account,
+        Hash::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(server.requests().len(), num_requests);
+
+    let account_keys = message.static_account_keys();
+    assert_eq!(account_keys[0], payer_pubkey);
+
+    let instructions = message.instructions();
+    assert_eq!(instructions.len(), 3);
+
+    let transfer = &instructions[0];
+    assert_eq!(*transfer.program_id(account_keys), system_program::ID);
+    assert_eq!(
+        bincode::deserialize::<SystemInstruction>(&transfer.data).unwrap(),
+        SystemInstruction::Transfer {
+            lamports: 31_809_327
+        }
+    );
+    assert_eq!(
+        account_keys[usize::from(transfer.accounts[1])],
+        fixture.reserve_stake
+    );
+
+    assert_eq!(
+        *instructions[1].program_id(account_keys),
+        fixture.program_id
+    );
+
+    assert_eq!(
+        String::from_utf8(instructions[2].data.clone()).unwrap(),
+        format!(
+            "sbr-{}-{}-{}-31809327",
+            FIXTURE_EPOCH, identity_pubkey, fixture.stake_pool
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_distribution_ixs_with_nonce() {
+    let identity_pubkey = Pubkey::from_str(IDENTITY).unwrap();
+    let payer_pubkey = Pubkey::new_unique();
+    let nonce_account = Pubkey::new_unique();
+    let fixture = StakePoolFixture::new(KNOWN_STAKE_POOL_PROGRAM_IDS[0], FIXTURE_EPOCH + 1);
+    let server = MockRpc::new()
+        .with_result(
+            "getLatestBlockhash",
+            None,
+            json!({
+                "context": { "slot": 322800000 },
+                "value": {
+                    "blockhash": "7nXaMB4gPuWmZ3TP5ZdC1ubKj4NzR9nNE8aArvYYPnZv",
+                    "lastValidBlockHeight": 300518395
+                },
+            }),
+        )
+        .with_result(
+            "simulateTransaction",
+            None,
+            json!({
+                "context": { "slot": 322800000 },
+                "value": {
+                    "err": null,
+                    "logs": [],
+                    "accounts": null,
+                    "unitsConsumed": 24_300,
+                    "returnData": null
+                },
+            }),
+        )
+        .start();
+    let rpc = server.rpc();
+
+    let ixs = distribution_ixs(
+        &rpc,
+        &DistributionTxArgs {
+            payer_pubkey,
+            identity_pubkey,
+            stake_pool_pubkey: fixture.stake_pool,
+            epoch: FIXTURE_EPOCH,
+            transfer_lamports: 31_809_327,
+            fee_limit_cb: 10_000,
+            nonce: Some((nonce_account, payer_pubkey)),
+        },
+        &fixture.stake_pool_account(),
+    )
+    .await
+    .unwrap();
+
+    let nonce_ix = advance_nonce_account(&nonce_account, &payer_pubkey);
+
+    // The nonce ix comes first, then the compute budget ixs
+    assert_eq!(ixs.len(), 6);
+    assert_eq!(ixs[0], nonce_ix);
+    assert_eq!(ixs[1].program_id, compute_budget::ID);
+    assert_eq!(ixs[2].program_id, compute_budget::ID);
+    assert_eq!(
+        ixs[3..].to_vec(),
+        transfer_to_reserve_and_update_stake_pool_balance_ixs_for_account(
+            &fixture.stake_pool_account(),
+            &payer_pubkey,
+            &identity_pubkey,
+            &fixture.stake_pool,
+            31_809_327,
+            FIXTURE_EPOCH,
+        )
+        .unwrap()
+    );
+
+    // The compute units were simulated with the nonce ix included
+    let simulation = server
+        .requests()
+        .into_iter()
+        .find(|request| request.body.contains("simulateTransaction"))
+        .unwrap();
+    let body: Value = serde_json::from_str(&simulation.body).unwrap();
+    let encoded_tx = body["params"][0].as_str().unwrap();
+    let tx_bytes = match body["params"][1]["encoding"].as_str() {
+        Some("base64") => BASE64_STANDARD.decode(encoded_tx).unwrap(),
+        _ => bs58::decode(encoded_tx).into_vec().unwrap(),
+    };
+    let tx: VersionedTransaction = bincode::deserialize(&tx_bytes).unwrap();
+    let account_keys = tx.message.static_account_keys();
+    assert!(tx.message.instructions().iter().any(|ix| {
+        *ix.program_id(account_keys) == system_program::ID && ix.data == nonce_ix.data
+    }));
+}
+
+#[tokio::test]
+async fn test_validate_distribution_plan() {
+    let identity_pubkey = Pubkey::from_str(IDENTITY).unwrap();
+    let payer_pubkey = Pubkey::new_unique();
+    let fixture = StakePoolFixture::new(KNOWN_STAKE_POOL_PROGRAM_IDS[0], FIXTURE_EPOCH + 1);
+    let server = fixture.with_accounts(MockRpc::mainnet_fixtures()).start();
+    let rpc = server.rpc();
+
+    let bps = RewardsSplitBps {
+        total_rewards_bps: 7_500,
+        lst_rewards_bps: 10_000,
+        mev_rewards_bps: 5_000,
+    };
+    let split = calculate_rewards_split(&epoch_rewards(42_412_437, Some(1_000_000)), &bps).unwrap();
+    let ixs = transfer_to_reserve_and_update_stake_pool_balance_ixs_for_account(
+        &fixture.stake_pool_account(),
+        &payer_pubkey,
+        &identity_pubkey,
+        &fixture.stake_pool,
+        split.transfer_lamports,
+        FIXTURE_EPOCH,
+    )
+    .unwrap();
+    let pool_balance = PoolBalance {
+        total_lamports: fixture.total_lamports,
+        pool_token_supply: fixture.pool_token_supply,
+    };
+
+    let plan = DistributionPlan {
+        identity_pubkey: IDENTITY.to_string(),
+        stake_pool_pubkey: fixture.stake_pool.to_string(),
+        payer_pubkey: payer_pubkey.to_string(),
+        epoch: FIXTURE_EPOCH,
+        bps,
+        split,
+        expected_balances: ExpectedBalances {
+            reserve_pre: fixture.reserve_lamports,
+            reserve_post: fixture.reserve_lamports + split.transfer_lamports,
+            pool_balance_pre: pool_balance,
+            pool_balance_post: pool_balance,
+        },
+        instructions: ixs.iter().map(PlanInstruction::from).collect(),
+        lst_info: LstInfo {
+            name: "Test LST".to_string(),
+            symbol: "testSOL".to_string(),
+            mint: fixture.pool_mint.to_string(),
+            decimals: 9,
+            logo_uri: None,
+            pool: Pool::default(),
+        },
+        epoch_duration_secs: 172_800.0,
+        fee_breakdown: None,
+        created_at: "2025-03-01T00:00:00+00:00".to_string(),
+    };
+
+    // Plan files round trip
+    let plan: DistributionPlan =
+        serde_json::from_str(&serde_json::to_string(&plan).unwrap()).unwrap();
+
+    let (validated_ixs, stake_pool) = validate_distribution_plan(&rpc, &plan, FIXTURE_EPOCH + 1)
+        .await
+        .unwrap();
+    assert_eq!(validated_ixs, ixs);
+    assert_eq!(stake_pool.reserve_stake, fixture.reserve_stake);
+
+    // Stale pool
+    assert!(validate_distribution_plan(&rpc, &plan, FIXTURE_EPOCH + 2)
+        .await
+        .is_err());
+
+    // Edited lamports that don't follow from the percentages
+    let mut edited = plan.clone();
+    edited.split.lst_rewards += 1;
+    edited.split.transfer_lamports += 1;
+    assert!(validate_distribution_plan(&rpc, &edited, FIXTURE_EPOCH + 1)
+        .await
+        .is_err());
+
+    // Edited instructions
+    let mut edited = plan.clone();
+    edited.instructions[0].accounts[1].pubkey = Pubkey::new_unique().to_string();
+    assert!(validate_distribution_plan(&rpc, &edited, FIXTURE_EPOCH + 1)
+        .await
+        .is_err());
+
+    // The plan must split the rewards recorded for its epoch
+    assert_eq!(
+        check_plan_rewards(&plan, &epoch_rewards(42_412_437, Some(1_000_000))),
+        Ok(())
+    );
+    assert!(check_plan_rewards(&plan, &epoch_rewards(42_412_438, Some(1_000_000))).is_err());
+    assert!(check_plan_rewards(&plan, &epoch_rewards(42_412_437, None)).is_err());
+}
+
+#[tokio::test]
+async fn test_build_distribution_plan() {
+    let identity_pubkey = Pubkey::from_str(IDENTITY).unwrap();
+    let payer_pubkey = Pubkey::new_unique();
+    let fixture = StakePoolFixture::new(KNOWN_STAKE_POOL_PROGRAM_IDS[0], FIXTURE_EPOCH + 1);
+    let server = fixture.with_accounts(MockRpc::mainnet_fixtures()).start();
+    let rpc = server.rpc();
+    let (stake_pool, stake_pool_account) =
+        validate_stake_pool(&rpc, &fixture.stake_pool, FIXTURE_EPOCH + 1)
+            .await
+            .unwrap();
+
+    let bps = RewardsSplitBps {
+        total_rewards_bps: 7_500,
+        lst_rewards_bps: 10_000,
+        mev_rewards_bps: 5_000,
+    };
+    let rewards = epoch_rewards(42_412_437, Some(1_000_000));
+    let lst_info = fallback_lst_info(&stake_pool, &fixture.stake_pool);
+    assert_eq!(lst_info.mint, fixture.pool_mint.to_string());
+    assert_eq!(lst_info.decimals, 9);
+
+    let plan = build_distribution_plan(
+        DistributionPlanArgs {
+            identity_pubkey,
+            stake_pool_pubkey: fixture.stake_pool,
+            payer_pubkey,
+            epoch: FIXTURE_EPOCH,
+            bps,
+            lst_info,
+            reserve_balance: fixture.reserve_lamports,
+            epoch_duration_secs: 172_800.0,
+        },
+        &rewards,
+        &stake_pool,
+        &stake_pool_account,
+    )
+    .unwrap();
+
+    let split = calculate_rewards_split(&rewards, &bps).unwrap();
+    assert_eq!(plan.split, split);
+    assert_eq!(plan.payer_pubkey, payer_pubkey.to_string());
+    assert_eq!(
+        plan.expected_balances.reserve_post,
+        fixture.reserve_lamports + split.transfer_lamports
+    );
+    let pool_balance = PoolBalance::from_stake_pool(&stake_pool);
+    assert_eq!(plan.expected_balances.pool_balance_pre, pool_balance);
+    assert_eq!(
+        plan.expected_balances.pool_balance_post,
+        pool_balance
+            .after_reward(split.transfer_lamports, &stake_pool.epoch_fee)
+            .unwrap()
+    );
+    assert_eq!(plan.fee_breakdown, rewards.fee_breakdown);
+
+    // What `plan` writes is what `execute` accepts
+    let (ixs, _) = validate_distribution_plan(&rpc, &plan, FIXTURE_EPOCH + 1)
+        .await
+        .unwrap();
+    assert_eq!(
+        ixs.iter().map(PlanInstruction::from).collect::<Vec<_>>(),
+        plan.instructions
+    );
+    assert_eq!(check_plan_rewards(&plan, &rewards), Ok(()));
+
+    // The reserve balance can't overflow
+    assert!(build_distribution_plan(
+        DistributionPlanArgs {
+            identity_pubkey,
+            stake_pool_pubkey: fixture.stake_pool,
+            payer_pubkey,
+            epoch: FIXTURE_EPOCH,
+            bps,
+            lst_info: plan.lst_info.clone(),
+            reserve_balance: u64::MAX,
+            epoch_duration_secs: 172_800.0,
+        },
+        &rewards,
+        &stake_pool,
+        &stake_pool_account,
+    )
+    .is_err());
+}
+
+#[tokio::test]
+async fn test_find_distribution() {
+    let identity_pubkey = Pubkey::from_str(IDENTITY).unwrap();
+    let fixture = StakePoolFixture::new(KNOWN_STAKE_POOL_PROGRAM_IDS[0], FIXTURE_EPOCH + 1);
+    let bps = RewardsSplitBps {
+        total_rewards_bps: 7_500,
+        lst_rewards_bps: 10_000,
+        mev_rewards_bps: 0,
+    };
+    let split = calculate_rewards_split(&epoch_rewards(42_412_437, None), &bps).unwrap();
+    let signed_transfer = fixture.signed_transfer_file(&Pubkey::new_unique(), &bps, &split);
+    let tx = decode_tx(&signed_transfer.transaction).unwrap();
+
+    // First slot of the epoch after the fixture's
+    let min_slot = 322_704_000;
+    let distribution = Signature::new_unique();
+    let memo = format!(
+        "sbr-{}-{}-{}-{}",
+        FIXTURE_EPOCH, identity_pubkey, fixture.stake_pool, split.transfer_lamports
+    );
+    let server = MockRpc::new()
+        .with_result(
+            "getSignaturesForAddress",
+            Some(&fixture.stake_pool.to_string()),
+            json!([
+                {
+                    "signature": Signature::new_unique().to_string(),
+                    "slot": min_slot + 200,
+                    "err": null,
+                    "memo": null,
+                    "blockTime": null,
+                    "confirmationStatus": "finalized"
+                },
+                {
+                    "signature": distribution.to_string(),
+                    "slot": min_slot + 100,
+                    "err": null,
+                    "memo": format!("[{}] {}", memo.len(), memo),
+                    "blockTime": null,
+                    "confirmationStatus": "finalized"
+                },
+                {
+                    "signature": Signature::new_unique().to_string(),
+                    "slot": min_slot - 100,
+                    "err": null,
+                    "memo": null,
+                    "blockTime": null,
+                    "confirmationStatus": "finalized"
+                },
+            ]),
+        )
+        .with_result(
+            "getTransaction",
+            Some(&distribution.to_string()),
+            json!({
+                "slot": min_slot + 100,
+                "transaction": [BASE64_STANDARD.encode(bincode::serialize(&tx).unwrap()), "base64"],
+                "meta": null,
+                "blockTime": null,
+                "version": 0
+            }),
+        )
+        .start();
+    let rpc = server.rpc();
+
+    assert_eq!(
+        find_distribution(
+            &rpc,
+            FIXTURE_EPOCH,
+            &identity_pubkey,
+            &fixture.stake_pool,
+            min_slot,
+            DEFAULT_MAX_DISTRIBUTION_SIGNATURES
+        )
+        .await,
+        Ok(Some(distribution))
+    );
+
+    // Distributions of other epochs don't count
+    assert_eq!(
+        find_distribution(
+            &rpc,
+            FIXTURE_EPOCH + 1,
+            &identity_pubkey,
+            &fixture.stake_pool,
+            min_slot,
+            DEFAULT_MAX_DISTRIBUTION_SIGNATURES
+        )
+        .await,
+        Ok(None)
+    );
+
+    // Nor do transactions before the epoch ended
+    assert_eq!(
+        find_distribution(
+            &rpc,
+            FIXTURE_EPOCH,
+            &identity_pubkey,
+            &fixture.stake_pool,
+            min_slot + 150,
+            DEFAULT_MAX_DISTRIBUTION_SIGNATURES
+        )
+        .await,
+        Ok(None)
+    );
+
+    // Running out of signatures before the epoch ended can't rule a distribution out
+    assert!(find_distribution(
+        &rpc,
+        FIXTURE_EPOCH + 1,
+        &identity_pubkey,
+        &fixture.stake_pool,
+        min_slot - 200,
+        3
+    )
+    .await
+    .is_err());
+}
+
+#[tokio::test]
+async fn test_verify_distribution_transfer() {
+    let identity_pubkey = Pubkey::from_str(IDENTITY).unwrap();
+    let payer_pubkey = Pubkey::new_unique();
+    let fixture = StakePoolFixture::new(KNOWN_STAKE_POOL_PROGRAM_IDS[1], FIXTURE_EPOCH + 1);
+    let server = fixture.with_accounts(MockRpc::new()).start();
+    let stake_pool = get_stake_pool(&server.rpc(), &fixture.stake_pool)
+        .await
+        .unwrap();
+
+    let bps = RewardsSplitBps {
+        total_rewards_bps: 7_500,
+        lst_rewards_bps: 10_000,
+        mev_rewards_bps: 0,
+    };
+    let split = calculate_rewards_split(&epoch_rewards(42_412_437, None), &bps).unwrap();
+
+    let ixs = transfer_to_reserve_and_update_stake_pool_balance_ixs_for_account(
+        &fixture.stake_pool_account(),
+        &payer_pubkey,
+        &identity_pubkey,
+        &fixture.stake_pool,
+        split.transfer_lamports,
+        FIXTURE_EPOCH,
+    )
+    .unwrap();
+    let message = Message::try_compile(&payer_pubkey, &ixs, &[], Hash::default()).unwrap();
+    let tx = VersionedTransaction {
+        signatures: vec![Signature::default()],
+        message: VersionedMessage::V0(message),
+    };
+
+    let transfer = parse_distribution_tx(&tx).unwrap();
+    assert_eq!(
+        transfer,
+        DistributionTransfer {
+            from: payer_pubkey,
+            to: fixture.reserve_stake,
+            lamports: 31_809_327,
+            memo: DistributionMemo {
+                epoch: FIXTURE_EPOCH,
+                identity_pubkey,
+                stake_pool_pubkey: fixture.stake_pool,
+                transfer_lamports: 31_809_327,
+            },
+        }
+    );
+    assert_eq!(
+        verify_distribution_transfer(&transfer, &stake_pool, &split),
+        Ok(())
+    );
+
+    // Made with other percentages
+    let other_split = calculate_rewards_split(
+        &epoch_rewards(42_412_437, None),
+        &RewardsSplitBps {
+            lst_rewards_bps: 9_000,
+            ..bps
+        },
+    )
+    .unwrap();
+    assert!(verify_distribution_transfer(&transfer, &stake_pool, &other_split).is_err());
+
+    // Sent somewhere else than the pool's reserve
+    let other_pool = StakePool {
+        reserve_stake: Pubkey::new_unique(),
+        ..stake_pool.clone()
+    };
+    assert!(verify_distribution_transfer(&transfer, &other_pool, &split).is_err());
+
+    // Memo disagreeing with the transfer
+    let mut edited = transfer;
+    edited.memo.transfer_lamports += 1;
+    assert!(verify_distribution_transfer(&edited, &stake_pool, &split).is_err());
+
+    assert!(parse_distribution_memo("sbr-746-notapubkey-notapubkey-1").is_err());
+    assert!(parse_distribution_memo(&format!(
+        "sbr-746-{}-{}",
+        identity_pubkey, fixture.stake_pool
+    ))
+    .is_err());
+}
+
+#[test]
+fn test_verify_signed_transfer() {
+    let identity_pubkey = Pubkey::from_str(IDENTITY).unwrap();
+    let payer_pubkey = Pubkey::new_unique();
+    let fixture = StakePoolFixture::new(KNOWN_STAKE_POOL_PROGRAM_IDS[0], FIXTURE_EPOCH + 1);
+
+    let bps = RewardsSplitBps {
+        total_rewards_bps: 7_500,
+        lst_rewards_bps: 10_000,
+        mev_rewards_bps: 5_000,
+    };
+    let split = calculate_rewards_split(&epoch_rewards(42_412_437, Some(1_000_000)), &bps).unwrap();
+    let signed_transfer = fixture.signed_transfer_file(&payer_pubkey, &bps, &split);
+    let tx = decode_tx(&signed_transfer.transaction).unwrap();
+
+    let transfer = verify_signed_transfer(&tx, &signed_transfer, &fixture.reserve_stake).unwrap();
+    assert_eq!(transfer.lamports, split.transfer_lamports);
+
+    // Paid by someone else
+    let ixs = transfer_to_reserve_and_update_stake_pool_balance_ixs_for_account(
+        &fixture.stake_pool_account(),
+        &payer_pubkey,
+        &identity_pubkey,
+        &fixture.stake_pool,
+        split.transfer_lamports,
+        FIXTURE_EPOCH,
+    )
+    .unwrap();
+    let other_payer_tx = VersionedTransaction {
+        signatures: vec![Signature::default()],
+        message: VersionedMessage::V0(
+            Message::try_compile(&Pubkey::new_unique(), &ixs, &[], Hash::default()).unwrap(),
+        ),
+    };
+    assert!(
+        verify_signed_transfer(&other_payer_tx, &signed_transfer, &fixture.reserve_stake).is_err()
+    );
+
+    // Sent somewhere else than the pool's reserve
+    assert!(verify_signed_transfer(&tx, &signed_transfer, &Pubkey::new_unique()).is_err());
+
+    // Summary showing another amount
+    let mut edited = signed_transfer.clone();
+    edited.summary.lst_rewards += 1;
+    assert!(verify_signed_transfer(&tx, &edited, &fixture.reserve_stake).is_err());
+
+    // Summary for another epoch, and so another memo
+    let mut edited = signed_transfer.clone();
+    edited.summary.epoch += 1;
+    assert!(verify_signed_transfer(&tx, &edited, &fixture.reserve_stake).is_err());
+
+    // File naming another stake pool
+    let mut edited = signed_transfer;
+    edited.stake_pool_pubkey = Pubkey::new_unique().to_string();
+    assert!(verify_signed_transfer(&tx, &edited, &fixture.reserve_stake).is_err());
+}
@@ -0,0 +1,236 @@
+use sanctum_block_rewards_cli::stake_pool_from_account;
+use sanctum_block_rewards_cli::transfer_to_reserve_and_update_stake_pool_balance_ixs_for_account;
+use sanctum_block_rewards_cli::transfer_to_reserve_and_update_stake_pool_balance_ixs_for_pool;
+use sanctum_block_rewards_cli::validate_stake_pool_accounts;
+use sanctum_block_rewards_cli::with_auto_cb_ixs;
+use sanctum_block_rewards_cli::KNOWN_STAKE_POOL_PROGRAM_IDS;
+use sanctum_solana_client_utils::buffer_compute_units;
+use sanctum_solana_client_utils::calc_compute_unit_price;
+use serde_json::json;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::AccountMeta;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_program;
+use std::str::FromStr;
+
+mod common;
+
+use common::MockRpc;
+use common::StakePoolFixture;
+use common::IDENTITY;
+use common::SPL_TOKEN_PROGRAM_ID;
+
+const PAYER: Pubkey = pubkey!("9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM");
+const MEMO_PROGRAM_ID: Pubkey = pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+fn expected_ixs(fixture: &StakePoolFixture, lst_rewards: u64, epoch: u64) -> Vec<Instruction> {
+    // SystemInstruction::Transfer
+    let mut transfer_data = vec![2, 0, 0, 0];
+    transfer_data.extend(lst_rewards.to_le_bytes());
+
+    vec![
+        Instruction {
+            program_id: system_program::ID,
+            accounts: vec![
+                AccountMeta::new(PAYER, true),
+                AccountMeta::new(fixture.reserve_stake, false),
+            ],
+            data: transfer_data,
+        },
+        Instruction {
+            program_id: fixture.program_id,
+            accounts: vec![
+                AccountMeta::new(fixture.stake_pool, false),
+                AccountMeta::new_readonly(fixture.withdraw_authority(), false),
+                AccountMeta::new(fixture.validator_list, false),
+                AccountMeta::new_readonly(fixture.reserve_stake, false),
+                AccountMeta::new(fixture.manager_fee_account, false),
+                AccountMeta::new(fixture.pool_mint, false),
+                AccountMeta::new_readonly(SPL_TOKEN_PROGRAM_ID, false),
+            ],
+            // StakePoolInstruction::UpdateStakePoolBalance
+            data: vec![7],
+        },
+        Instruction {
+            program_id: MEMO_PROGRAM_ID,
+            accounts: vec![AccountMeta::new_readonly(PAYER, true)],
+            data: format!(
+                "sbr-{}-{}-{}-{}",
+                epoch, IDENTITY, fixture.stake_pool, lst_rewards
+            )
+            .into_bytes(),
+        },
+    ]
+}
+
+#[test]
+fn test_transfer_to_reserve_and_update_stake_pool_balance_ixs() {
+    let identity_pubkey = Pubkey::from_str(IDENTITY).unwrap();
+
+    for program_id in KNOWN_STAKE_POOL_PROGRAM_IDS {
+        let fixture = StakePoolFixture::new(program_id, 747);
+
+        let ixs = transfer_to_reserve_and_update_stake_pool_balance_ixs_for_account(
+            &fixture.stake_pool_account(),
+            &PAYER,
+            &identity_pubkey,
+            &fixture.stake_pool,
+            31_809_327,
+            746,
+        )
+        .unwrap();
+
+        assert_eq!(ixs, expected_ixs(&fixture, 31_809_327, 746));
+
+        let stake_pool = stake_pool_from_account(&fixture.stake_pool_account()).unwrap();
+        assert_eq!(
+            transfer_to_reserve_and_update_stake_pool_balance_ixs_for_pool(
+                &program_id,
+                &stake_pool,
+                &PAYER,
+                &identity_pubkey,
+                &fixture.stake_pool,
+                31_809_327,
+                746,
+            ),
+            Ok(ixs)
+        );
+    }
+
+    // Uninitialized stake pool
+    let fixture = StakePoolFixture::new(KNOWN_STAKE_POOL_PROGRAM_IDS[0], 747);
+    let mut stake_pool_account = fixture.stake_pool_account();
+    stake_pool_account.data.fill(0);
+    assert!(
+        transfer_to_reserve_and_update_stake_pool_balance_ixs_for_account(
+            &stake_pool_account,
+            &PAYER,
+            &identity_pubkey,
+            &fixture.stake_pool,
+            31_809_327,
+            746,
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_validate_stake_pool_accounts() {
+    for program_id in KNOWN_STAKE_POOL_PROGRAM_IDS {
+        let fixture = StakePoolFixture::new(program_id, 747);
+        let stake_pool = validate_stake_pool_accounts(
+            &fixture.stake_pool,
+            &fixture.stake_pool_account(),
+            &fixture.reserve_account(),
+            747,
+        )
+        .unwrap();
+        assert_eq!(stake_pool.reserve_stake, fixture.reserve_stake);
+        assert_eq!(stake_pool.total_lamports, fixture.total_lamports);
+    }
+
+    let fixture = StakePoolFixture::new(KNOWN_STAKE_POOL_PROGRAM_IDS[0], 747);
+
+    // Owned by an unknown program
+    let mut stake_pool_account = fixture.stake_pool_account();
+    stake_pool_account.owner = Pubkey::new_unique();
+    let err = validate_stake_pool_accounts(
+        &fixture.stake_pool,
+        &stake_pool_account,
+        &fixture.reserve_account(),
+        747,
+    )
+    .unwrap_err();
+    assert!(err.contains("not a known stake pool program"), "{}", err);
+
+    // Not updated for the current epoch
+    let err = validate_stake_pool_accounts(
+        &fixture.stake_pool,
+        &fixture.stake_pool_account(),
+        &fixture.reserve_account(),
+        748,
+    )
+    .unwrap_err();
+    assert!(err.contains("last updated in epoch 747"), "{}", err);
+
+    // Reserve that isn't a stake account
+    let mut reserve_account = fixture.reserve_account();
+    reserve_account.owner = system_program::ID;
+    let err = validate_stake_pool_accounts(
+        &fixture.stake_pool,
+        &fixture.stake_pool_account(),
+        &reserve_account,
+        747,
+    )
+    .unwrap_err();
+    assert!(err.contains("is not a stake account"), "{}", err);
+
+    // Reserve withdrawable by the withdraw authority of another program's pool
+    let other_fixture = StakePoolFixture::new(KNOWN_STAKE_POOL_PROGRAM_IDS[1], 747);
+    let err = validate_stake_pool_accounts(
+        &fixture.stake_pool,
+        &fixture.stake_pool_account(),
+        &other_fixture.reserve_account(),
+        747,
+    )
+    .unwrap_err();
+    assert!(err.contains("owned by the stake pool"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_with_auto_cb_ixs() {
+    let server = MockRpc::new()
+        .with_result(
+            "getLatestBlockhash",
+            None,
+            json!({
+                "context": { "slot": 322800000 },
+                "value": {
+                    "blockhash": "7nXaMB4gPuWmZ3TP5ZdC1ubKj4NzR9nNE8aArvYYPnZv",
+                    "lastValidBlockHeight": 300518395
+                },
+            }),
+        )
+        .with_result(
+            "simulateTransaction",
+            None,
+            json!({
+                "context": { "slot": 322800000 },
+                "value": {
+                    "err": null,
+                    "logs": [],
+                    "accounts": null,
+                    "unitsConsumed": 24_300,
+                    "returnData": null
+                },
+            }),
+        )
+        .start();
+    let rpc = server.rpc();
+    let fixture = StakePoolFixture::new(KNOWN_STAKE_POOL_PROGRAM_IDS[1], 747);
+    let ixs = expected_ixs(&fixture, 31_809_327, 746);
+
+    // Disabled
+    assert_eq!(
+        with_auto_cb_ixs(&rpc, &PAYER, ixs.clone(), &[], 0).await,
+        ixs
+    );
+
+    let fee_limit_cb = 10_000;
+    let units = buffer_compute_units(24_300, 1.1).saturating_add(300);
+    let mut expected = vec![
+        ComputeBudgetInstruction::set_compute_unit_price(calc_compute_unit_price(
+            units,
+            fee_limit_cb,
+        )),
+        ComputeBudgetInstruction::set_compute_unit_limit(units),
+    ];
+    expected.extend(ixs.clone());
+
+    assert_eq!(
+        with_auto_cb_ixs(&rpc, &PAYER, ixs, &[], fee_limit_cb).await,
+        expected
+    );
+}
@@ -0,0 +1,78 @@
+use sanctum_block_rewards_cli::annualized_apy_pct;
+use sanctum_block_rewards_cli::exchange_rate_increase_bps;
+use sanctum_block_rewards_cli::PoolBalance;
+use spl_stake_pool_interface::Fee;
+
+#[test]
+fn test_pool_balance_after_reward_no_fee() {
+    let pre = PoolBalance {
+        total_lamports: 1_100_000_000_000,
+        pool_token_supply: 1_000_000_000_000,
+    };
+    let no_fee = Fee {
+        denominator: 100,
+        numerator: 0,
+    };
+
+    let post = pre.after_reward(11_000_000_000, &no_fee).unwrap();
+    assert_eq!(
+        post,
+        PoolBalance {
+            total_lamports: 1_111_000_000_000,
+            pool_token_supply: 1_000_000_000_000,
+        }
+    );
+    assert!((pre.sol_value(9) - 1.1).abs() < 1e-12);
+    assert!((exchange_rate_increase_bps(&pre, &post) - 100.0).abs() < 1e-9); // 1%
+
+    // No reward, no change
+    assert_eq!(pre.after_reward(0, &no_fee), Some(pre));
+}
+
+#[test]
+fn test_pool_balance_after_reward_with_epoch_fee() {
+    let pre = PoolBalance {
+        total_lamports: 1_000_000_000_000,
+        pool_token_supply: 1_000_000_000_000,
+    };
+    // 10% epoch fee
+    let epoch_fee = Fee {
+        denominator: 10,
+        numerator: 1,
+    };
+
+    let post = pre.after_reward(10_000_000_000, &epoch_fee).unwrap();
+    // fee_lamports = 1 SOL, fee tokens = 1000 * 1 / (1010 - 1) SOL worth of tokens
+    assert_eq!(post.total_lamports, 1_010_000_000_000);
+    assert_eq!(post.pool_token_supply, 1_000_991_080_277);
+
+    // Holders only get the rewards net of the 10% manager fee
+    assert!((exchange_rate_increase_bps(&pre, &post) - 90.0).abs() < 1e-6);
+
+    // Empty pool: all rewards go to the manager
+    let empty = PoolBalance {
+        total_lamports: 0,
+        pool_token_supply: 0,
+    };
+    assert_eq!(
+        empty.after_reward(5, &epoch_fee),
+        Some(PoolBalance {
+            total_lamports: 5,
+            pool_token_supply: 5,
+        })
+    );
+
+    // Overflow
+    assert_eq!(pre.after_reward(u64::MAX, &epoch_fee), None);
+}
+
+#[test]
+fn test_annualized_apy_pct() {
+    // A year of 1 day epochs (365.25 epochs), 1 bps each
+    let apy = annualized_apy_pct(1.0, 86_400.0);
+    assert!((apy - ((1.0001f64).powf(365.25) - 1.0) * 100.0).abs() < 1e-9);
+    assert!(apy > 3.65 && apy < 3.75); // slightly above simple interest due to compounding
+
+    assert_eq!(annualized_apy_pct(0.0, 172_800.0), 0.0);
+    assert_eq!(annualized_apy_pct(10.0, 0.0), 0.0);
+}
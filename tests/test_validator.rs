@@ -0,0 +1,312 @@
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+use sanctum_block_rewards_cli::calculate_rewards_split;
+use sanctum_block_rewards_cli::validate_stake_pool;
+use sanctum_block_rewards_cli::write_json_file;
+use sanctum_block_rewards_cli::RewardsSplitBps;
+use sanctum_block_rewards_cli::KNOWN_STAKE_POOL_PROGRAM_IDS;
+use serde_json::json;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::account::Account;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::write_keypair_file;
+use solana_sdk::signature::Keypair;
+use solana_sdk::signature::Signature;
+use solana_sdk::signer::Signer;
+use solana_transaction_status::UiTransactionEncoding;
+use std::net::TcpListener;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Child;
+use std::process::Command;
+use std::process::Output;
+use std::process::Stdio;
+use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
+
+mod common;
+
+use common::dumped_message;
+use common::epoch_rewards;
+use common::output_text;
+use common::run_cli;
+use common::StakePoolFixture;
+use common::IDENTITY;
+use common::RESERVE_RENT_EXEMPT_RESERVE;
+
+const SLOTS_PER_EPOCH: u64 = 10_000;
+/// The validator starts at the first slot of this epoch, so that there is a
+/// completed epoch to transfer rewards for
+const STAKE_POOL_EPOCH: u64 = 10;
+const REWARDS_EPOCH: u64 = STAKE_POOL_EPOCH - 1;
+const TOTAL_BLOCK_REWARDS: u64 = 42_412_437;
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(120);
+
+const BPS: RewardsSplitBps = RewardsSplitBps {
+    total_rewards_bps: 7_500,
+    lst_rewards_bps: 5_000,
+    mev_rewards_bps: 0,
+};
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// `--account` file in the format of `solana account --output json`
+fn write_account_file(path: &Path, pubkey: &Pubkey, account: &Account) {
+    write_json_file(
+        path.to_str().unwrap(),
+        &json!({
+            "pubkey": pubkey.to_string(),
+            "account": {
+                "lamports": account.lamports,
+                "data": [BASE64_STANDARD.encode(&account.data), "base64"],
+                "owner": account.owner.to_string(),
+                "executable": account.executable,
+                "rentEpoch": account.rent_epoch,
+                "space": account.data.len(),
+            },
+        }),
+    )
+    .unwrap();
+}
+
+struct TestValidator {
+    process: Child,
+    rpc_url: String,
+}
+
+impl TestValidator {
+    async fn start(dir: &Path, mint: &Pubkey, accounts: &[(Pubkey, Account)]) -> Self {
+        let rpc_port = free_port();
+        let mut command = Command::new("solana-test-validator");
+        command
+            .arg("--ledger")
+            .arg(dir.join("ledger"))
+            .args(["--reset", "--quiet"])
+            .args(["--rpc-port", &rpc_port.to_string()])
+            .args(["--faucet-port", &free_port().to_string()])
+            .args(["--slots-per-epoch", &SLOTS_PER_EPOCH.to_string()])
+            .args([
+                "--warp-slot",
+                &(STAKE_POOL_EPOCH * SLOTS_PER_EPOCH).to_string(),
+            ])
+            .args(["--mint", &mint.to_string()]);
+
+        let program_id = KNOWN_STAKE_POOL_PROGRAM_IDS[0].to_string();
+        match std::env::var("SPL_STAKE_POOL_PROGRAM_SO") {
+            Ok(path) => command.args(["--bpf-program", &program_id, &path]),
+            Err(_) => command.args([
+                "--url",
+                "mainnet-beta",
+                "--clone-upgradeable-program",
+                &program_id,
+            ]),
+        };
+
+        for (pubkey, account) in accounts {
+            let path = dir.join(format!("{}.json", pubkey));
+            write_account_file(&path, pubkey, account);
+            command.arg("--account").arg(pubkey.to_string()).arg(path);
+        }
+
+        let process = command
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to start solana-test-validator, is it on the PATH?");
+
+        let validator = Self {
+            process,
+            rpc_url: format!("http://127.0.0.1:{}", rpc_port),
+        };
+        validator.wait_for_epoch().await;
+        validator
+    }
+
+    fn rpc(&self) -> RpcClient {
+        RpcClient::new_with_commitment(self.rpc_url.clone(), CommitmentConfig::confirmed())
+    }
+
+    async fn wait_for_epoch(&self) {
+        let rpc = self.rpc();
+        let start = Instant::now();
+        loop {
+            if let Ok(epoch_info) = rpc.get_epoch_info().await {
+                assert_eq!(epoch_info.epoch, STAKE_POOL_EPOCH);
+                return;
+            }
+            assert!(
+                start.elapsed() < STARTUP_TIMEOUT,
+                "solana-test-validator did not start in {:?}",
+                STARTUP_TIMEOUT
+            );
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+}
+
+impl Drop for TestValidator {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+struct TransferEnv {
+    home: PathBuf,
+    payer_path: PathBuf,
+    lst_list_path: PathBuf,
+    fixture: StakePoolFixture,
+}
+
+impl TransferEnv {
+    /// Writes the payer keypair, rewards file and LST list under a fresh `$HOME`
+    fn new(dir: &Path, payer: &Keypair, fixture: StakePoolFixture) -> Self {
+        let home = dir.join("home");
+        let payer_path = dir.join("payer.json");
+        let lst_list_path = dir.join("sanctum-lst-list.toml");
+
+        write_keypair_file(payer, &payer_path).unwrap();
+
+        write_json_file(
+            home.join(format!(
+                ".local/sanctum/rewards_{}_{}.json",
+                IDENTITY, REWARDS_EPOCH
+            ))
+            .to_str()
+            .unwrap(),
+            &epoch_rewards(TOTAL_BLOCK_REWARDS, None),
+        )
+        .unwrap();
+
+        fixture.write_lst_list(&lst_list_path);
+
+        Self {
+            home,
+            payer_path,
+            lst_list_path,
+            fixture,
+        }
+    }
+
+    fn transfer(&self, validator: &TestValidator, send_mode: &str) -> Output {
+        run_cli(
+            &self.home,
+            &[
+                "--rpc-url",
+                &validator.rpc_url,
+                "--send-mode",
+                send_mode,
+                "transfer",
+                "--payer",
+                self.payer_path.to_str().unwrap(),
+                "--identity-pubkey",
+                IDENTITY,
+                "--epoch",
+                &REWARDS_EPOCH.to_string(),
+                "--stake-pool-pubkey",
+                &self.fixture.stake_pool.to_string(),
+                "--total-rewards-pct",
+                "75",
+                "--lst-rewards-pct",
+                "50",
+                "--lst-list",
+                self.lst_list_path.to_str().unwrap(),
+                "--yes",
+            ],
+        )
+    }
+}
+
+/// Needs `solana-test-validator` on the PATH. The stake pool program is cloned
+/// from mainnet unless `SPL_STAKE_POOL_PROGRAM_SO` points to a local build of it
+#[tokio::test]
+#[ignore = "requires solana-test-validator"]
+async fn test_transfer_on_test_validator() {
+    let dir = std::env::temp_dir().join(format!("test-sanctum-validator-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut fixture = StakePoolFixture::new(KNOWN_STAKE_POOL_PROGRAM_IDS[0], STAKE_POOL_EPOCH);
+    fixture.total_lamports = 1_100_000_000_000;
+    fixture.reserve_lamports = RESERVE_RENT_EXEMPT_RESERVE + fixture.total_lamports;
+
+    let payer = Keypair::new();
+    let validator = TestValidator::start(&dir, &payer.pubkey(), &fixture.accounts()).await;
+    let rpc = validator.rpc();
+    let env = TransferEnv::new(&dir, &payer, fixture);
+    let fixture = &env.fixture;
+
+    let lst_rewards = calculate_rewards_split(&epoch_rewards(TOTAL_BLOCK_REWARDS, None), &BPS)
+        .unwrap()
+        .transfer_lamports;
+    let memo = format!(
+        "sbr-{}-{}-{}-{}",
+        REWARDS_EPOCH, IDENTITY, fixture.stake_pool, lst_rewards
+    );
+    let reserve_pre = rpc.get_balance(&fixture.reserve_stake).await.unwrap();
+
+    // sim-only doesn't change anything
+    let output = output_text(&env.transfer(&validator, "sim-only"));
+    assert!(output.contains(&memo), "{}", output);
+    assert_eq!(
+        rpc.get_balance(&fixture.reserve_stake).await.unwrap(),
+        reserve_pre
+    );
+
+    // dump-msg prints the unsigned transaction
+    let output = output_text(&env.transfer(&validator, "dump-msg"));
+    let message = dumped_message(&output).unwrap_or_else(|| panic!("{}", output));
+    assert!(message
+        .instructions()
+        .iter()
+        .any(|ix| ix.data == memo.as_bytes()));
+    assert_eq!(
+        rpc.get_balance(&fixture.reserve_stake).await.unwrap(),
+        reserve_pre
+    );
+
+    let output = output_text(&env.transfer(&validator, "send-actual"));
+
+    let reserve_post = rpc.get_balance(&fixture.reserve_stake).await.unwrap();
+    assert_eq!(reserve_post, reserve_pre + lst_rewards, "{}", output);
+
+    let (stake_pool, _) = validate_stake_pool(&rpc, &fixture.stake_pool, STAKE_POOL_EPOCH)
+        .await
+        .unwrap();
+    assert_eq!(
+        stake_pool.total_lamports,
+        fixture.total_lamports + lst_rewards
+    );
+    assert_eq!(
+        stake_pool.total_lamports,
+        reserve_post - RESERVE_RENT_EXEMPT_RESERVE
+    );
+    // The 1% epoch fee is minted to the manager
+    assert!(stake_pool.pool_token_supply > fixture.pool_token_supply);
+
+    let signatures = rpc
+        .get_signatures_for_address(&fixture.stake_pool)
+        .await
+        .unwrap();
+    assert_eq!(signatures.len(), 1);
+    let tx = rpc
+        .get_transaction(
+            &Signature::from_str(&signatures[0].signature).unwrap(),
+            UiTransactionEncoding::Json,
+        )
+        .await
+        .unwrap();
+    let logs = Option::<Vec<String>>::from(tx.transaction.meta.unwrap().log_messages).unwrap();
+    assert!(logs.iter().any(|log| log.contains(&memo)), "{:?}", logs);
+
+    drop(validator);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
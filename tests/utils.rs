@@ -1,8 +1,27 @@
+use proptest::prelude::*;
+use sanctum_block_rewards_cli::block_fee_breakdown;
+use sanctum_block_rewards_cli::check_payer_balance;
 use sanctum_block_rewards_cli::checked_pct;
+use sanctum_block_rewards_cli::estimate_max_tx_fee;
+use sanctum_block_rewards_cli::get_lst_info;
 use sanctum_block_rewards_cli::get_total_block_rewards_for_slots;
-use sanctum_block_rewards_cli::SOLANA_PUBLIC_RPC;
-use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_sdk::commitment_config::CommitmentConfig;
+use sanctum_block_rewards_cli::read_rewards_file;
+use sanctum_block_rewards_cli::validate_bps;
+use sanctum_block_rewards_cli::validate_sol_amount;
+use sanctum_block_rewards_cli::write_json_file;
+use sanctum_block_rewards_cli::CheckPayerBalanceArgs;
+use sanctum_block_rewards_cli::EpochRewards;
+use sanctum_block_rewards_cli::FeeBreakdown;
+use sanctum_block_rewards_cli::LstInfo;
+use sanctum_block_rewards_cli::Pool;
+use sanctum_block_rewards_cli::SlotReward;
+use sanctum_block_rewards_cli::MAX_BPS;
+use solana_transaction_status::UiConfirmedBlock;
+
+mod common;
+
+use common::MockRpc;
+use common::FIXTURE_SKIPPED_SLOT;
 
 #[test]
 fn test_checked_pct() {
@@ -18,19 +37,252 @@ fn test_checked_pct() {
     // Test with 0 value
     assert_eq!(checked_pct(0, 5000), Some(0)); // 50% of 0 is 0
 
-    // Test overflow cases
-    assert_eq!(checked_pct(u64::MAX, 10000), None); // Should overflow
-    assert_eq!(checked_pct(u64::MAX, 5000), None); // Should overflow
+    // Large values don't overflow
+    assert_eq!(checked_pct(u64::MAX, 10000), Some(u64::MAX));
+    assert_eq!(checked_pct(u64::MAX, 5000), Some(u64::MAX / 2));
+
+    // Rounded down
+    assert_eq!(checked_pct(999, 5000), Some(499));
+
+    // More than 100%
+    assert_eq!(checked_pct(100, 10001), None);
+}
+
+#[test]
+fn test_validate_bps() {
+    assert_eq!(validate_bps("75"), Ok(7_500));
+    assert_eq!(validate_bps("12.5"), Ok(1_250));
+    assert_eq!(validate_bps("0.01"), Ok(1));
+    assert_eq!(validate_bps(".5"), Ok(50));
+    assert_eq!(validate_bps("100.00"), Ok(10_000));
+    assert_eq!(validate_bps("0100"), Ok(10_000));
+
+    assert!(validate_bps("100.01").is_err());
+    assert!(validate_bps("12.345").is_err());
+    assert!(validate_bps("-5").is_err());
+    assert!(validate_bps("NaN").is_err());
+    assert!(validate_bps("inf").is_err());
+    assert!(validate_bps("1e2").is_err());
+    assert!(validate_bps(".").is_err());
+    assert!(validate_bps("").is_err());
+}
+
+#[test]
+fn test_validate_sol_amount() {
+    assert_eq!(validate_sol_amount("1"), Ok(1_000_000_000));
+    assert_eq!(validate_sol_amount("0.1"), Ok(100_000_000));
+    assert_eq!(validate_sol_amount(".000000001"), Ok(1));
+    assert_eq!(validate_sol_amount("1.23456789"), Ok(1_234_567_890));
+    assert_eq!(validate_sol_amount("18446744073.709551615"), Ok(u64::MAX));
+
+    assert!(validate_sol_amount("0.0000000001").is_err());
+    assert!(validate_sol_amount("18446744073.709551616").is_err());
+    assert!(validate_sol_amount("-1").is_err());
+    assert!(validate_sol_amount("NaN").is_err());
+    assert!(validate_sol_amount("1e9").is_err());
+    assert!(validate_sol_amount(".").is_err());
+    assert!(validate_sol_amount("").is_err());
+}
+
+proptest! {
+    #[test]
+    fn prop_validate_bps_round_trips(bps in 0..=MAX_BPS) {
+        prop_assert_eq!(validate_bps(&format!("{}.{:02}", bps / 100, bps % 100)), Ok(bps));
+        prop_assert_eq!(validate_bps(&format!("{}", bps as f64 / 100.0)), Ok(bps));
+    }
+
+    #[test]
+    fn prop_validate_bps_rejects_over_100_pct(bps in MAX_BPS + 1..u64::MAX / 100) {
+        prop_assert!(validate_bps(&format!("{}.{:02}", bps / 100, bps % 100)).is_err());
+    }
+
+    #[test]
+    fn prop_validate_bps_is_at_most_100_pct(input in "\\PC*") {
+        if let Ok(bps) = validate_bps(&input) {
+            prop_assert!(bps <= MAX_BPS);
+        }
+    }
+
+    #[test]
+    fn prop_checked_pct_rounds_down(value: u64, bps in 0..=MAX_BPS) {
+        let share = checked_pct(value, bps).unwrap();
+        prop_assert!(share <= value);
+
+        // share is the largest integer with share * 100% <= value * bps
+        let exact = u128::from(value) * u128::from(bps);
+        prop_assert!(u128::from(share) * u128::from(MAX_BPS) <= exact);
+        prop_assert!((u128::from(share) + 1) * u128::from(MAX_BPS) > exact);
+    }
+}
+
+#[test]
+fn test_estimate_max_tx_fee() {
+    assert_eq!(estimate_max_tx_fee(1, 0), 5_000);
+    assert_eq!(estimate_max_tx_fee(2, 1), 10_001);
+    assert_eq!(estimate_max_tx_fee(1, u64::MAX), u64::MAX); // Saturates
+}
+
+#[test]
+fn test_check_payer_balance() {
+    let args = |payer_balance| CheckPayerBalanceArgs {
+        payer_balance,
+        transfer_lamports: 1_000_000_000,
+        rent_exempt_minimum: 890_880,
+        estimated_fee: 5_001,
+        reserve_floor: 1_000_000_000,
+    };
+
+    // Exactly enough
+    assert_eq!(check_payer_balance(args(2_000_895_881)), Ok(2_000_895_881));
+
+    // One lamport short
+    assert!(check_payer_balance(args(2_000_895_880)).is_err());
+
+    // Overflow in required balance
+    assert!(check_payer_balance(CheckPayerBalanceArgs {
+        payer_balance: u64::MAX,
+        transfer_lamports: u64::MAX,
+        rent_exempt_minimum: 1,
+        estimated_fee: 0,
+        reserve_floor: 0,
+    })
+    .is_err());
+}
+
+#[test]
+fn test_read_rewards_file() {
+    let dir = std::env::temp_dir().join("test-sanctum-rewards-file");
+    let path = dir.join("rewards.json");
+    let path = path.to_str().unwrap();
+    let _ = std::fs::remove_file(path);
+
+    // Not calculated yet
+    assert_eq!(read_rewards_file(path), Ok(None));
+
+    // Files written by older versions only have the total
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(path, r#"{ "total_block_rewards": 123 }"#).unwrap();
+    assert_eq!(
+        read_rewards_file(path),
+        Ok(Some(EpochRewards {
+            total_block_rewards: 123,
+            source: None,
+            num_leader_slots: None,
+            slot_rewards: None,
+            fee_breakdown: None,
+            mev_rewards: None,
+        }))
+    );
+
+    let rewards = EpochRewards {
+        total_block_rewards: 5,
+        source: Some("rpc".to_string()),
+        num_leader_slots: Some(2),
+        slot_rewards: Some(vec![
+            SlotReward {
+                slot: 1,
+                lamports: 5,
+                skipped: false,
+                fee_breakdown: Some(FeeBreakdown {
+                    num_transactions: 1,
+                    base_fees: 5_000,
+                    priority_fees: 2_500,
+                    burned_fees: 2_500,
+                }),
+            },
+            SlotReward {
+                slot: 2,
+                lamports: 0,
+                skipped: true,
+                fee_breakdown: None,
+            },
+        ]),
+        fee_breakdown: Some(FeeBreakdown {
+            num_transactions: 1,
+            base_fees: 5_000,
+            priority_fees: 2_500,
+            burned_fees: 2_500,
+        }),
+        mev_rewards: Some(3),
+    };
+    write_json_file(path, &rewards).unwrap();
+    assert_eq!(read_rewards_file(path), Ok(Some(rewards)));
+
+    std::fs::write(path, r#"{ "block_rewards": 123 }"#).unwrap();
+    assert!(read_rewards_file(path).is_err());
 }
 
 #[tokio::test]
-async fn test_get_total_block_rewards_for_slots_skipped_slot() {
-    let rpc = RpcClient::new_with_commitment(
-        SOLANA_PUBLIC_RPC.to_string(),
-        CommitmentConfig::confirmed(),
+async fn test_get_lst_info_from_local_lst_list() {
+    let lst_list_path = std::env::temp_dir().join("test-sanctum-lst-list.toml");
+    std::fs::write(
+        &lst_list_path,
+        r#"
+[[sanctum_lst_list]]
+name = "Sanctum Infinity"
+symbol = "INF"
+mint = "5oVNBeEEQvYi1cX3ir8Dx5n1P7pdxydbGF2X4TxVusJm"
+decimals = 9
+
+[[sanctum_lst_list]]
+name = "Example LST"
+symbol = "exSOL"
+mint = "exSoLq6sTkcFnbeJDD7N1oFVNtqkGZNNhRbPL7vhTXG"
+decimals = 9
+logo_uri = "https://example.com/exsol.png"
+pool = { program = "SanctumSpl", pool = "8Dv3hNYcEWEaa4qVx9BTN1Wfvtha1z8cWDUXb7KVACVe" }
+"#,
+    )
+    .unwrap();
+    let lst_list_path = lst_list_path.to_str().unwrap();
+
+    assert_eq!(
+        get_lst_info(
+            "8Dv3hNYcEWEaa4qVx9BTN1Wfvtha1z8cWDUXb7KVACVe",
+            Some(lst_list_path)
+        )
+        .await,
+        Ok((
+            Some(LstInfo {
+                name: "Example LST".to_string(),
+                symbol: "exSOL".to_string(),
+                mint: "exSoLq6sTkcFnbeJDD7N1oFVNtqkGZNNhRbPL7vhTXG".to_string(),
+                decimals: 9,
+                logo_uri: Some("https://example.com/exsol.png".to_string()),
+                pool: Pool {
+                    program: Some("SanctumSpl".to_string()),
+                    pool: Some("8Dv3hNYcEWEaa4qVx9BTN1Wfvtha1z8cWDUXb7KVACVe".to_string()),
+                },
+            }),
+            None
+        ))
     );
 
-    let slots = vec![322368304];
+    // Pool not in the list
+    assert_eq!(
+        get_lst_info(
+            "Fu9BYC6tWBo1KMKaP3CFoKfRhqv9akmy3DuYwnCyWiyC",
+            Some(lst_list_path)
+        )
+        .await,
+        Ok((None, None))
+    );
+
+    // Missing local file is an error rather than a silent fallback
+    assert!(get_lst_info(
+        "8Dv3hNYcEWEaa4qVx9BTN1Wfvtha1z8cWDUXb7KVACVe",
+        Some("/nonexistent/sanctum-lst-list.toml")
+    )
+    .await
+    .is_err());
+}
+
+#[tokio::test]
+async fn test_get_total_block_rewards_for_slots_skipped_slot() {
+    let server = MockRpc::mainnet_fixtures().start();
+    let rpc = server.rpc();
+
+    let slots = vec![FIXTURE_SKIPPED_SLOT];
     let total_rewards = get_total_block_rewards_for_slots(&rpc, &slots)
         .await
         .unwrap();
@@ -41,19 +293,88 @@ async fn test_get_total_block_rewards_for_slots_skipped_slot() {
 
 #[tokio::test]
 async fn test_get_total_block_rewards_for_slots_valid_block() {
-    let rpc = RpcClient::new_with_commitment(
-        SOLANA_PUBLIC_RPC.to_string(),
-        CommitmentConfig::confirmed(),
-    );
+    let server = MockRpc::mainnet_fixtures().start();
+    let rpc = server.rpc();
 
     let slots = vec![322272000];
     let total_rewards = get_total_block_rewards_for_slots(&rpc, &slots)
         .await
         .unwrap();
 
-    // This block exists and should have non-zero rewards
+    // The leader's fee reward in the recorded block
+    assert_eq!(total_rewards, 23_481_923);
+}
+
+#[tokio::test]
+async fn test_get_total_block_rewards_for_slots_rpc_error() {
+    let server = MockRpc::mainnet_fixtures()
+        .with_error(
+            "getBlock",
+            Some("322272001"),
+            -32004,
+            "Block not available for slot 322272001",
+        )
+        .start();
+    let rpc = server.rpc();
+
+    // Errors other than skipped slots are not swallowed
     assert!(
-        total_rewards > 0,
-        "Expected non-zero rewards for valid block"
+        get_total_block_rewards_for_slots(&rpc, &[322272000, 322272001])
+            .await
+            .is_err()
+    );
+}
+
+#[test]
+fn test_block_fee_breakdown() {
+    let block: UiConfirmedBlock = serde_json::from_str(
+        r#"{
+            "previousBlockhash": "5dLNuYn1R9uBQNBf8Tg7fh4n2s8Y1pHhHAPWUBv5FWSG",
+            "blockhash": "7nXaMB4gPuWmZ3TP5ZdC1ubKj4NzR9nNE8aArvYYPnZv",
+            "parentSlot": 322271999,
+            "transactions": [
+                {
+                    "transaction": {
+                        "signatures": ["1111111111111111111111111111111111111111111111111111111111111111"],
+                        "message": {
+                            "header": { "numRequiredSignatures": 1, "numReadonlySignedAccounts": 0, "numReadonlyUnsignedAccounts": 1 },
+                            "accountKeys": ["GdnSyH3YtwcxFvQrVVJMm1JhTS4QVX7MFsX56uJLUfiZ", "11111111111111111111111111111111"],
+                            "recentBlockhash": "5dLNuYn1R9uBQNBf8Tg7fh4n2s8Y1pHhHAPWUBv5FWSG",
+                            "instructions": [{ "programIdIndex": 1, "accounts": [0], "data": "2" }]
+                        }
+                    },
+                    "meta": { "err": null, "status": { "Ok": null }, "fee": 7000, "preBalances": [0, 0], "postBalances": [0, 0] }
+                },
+                {
+                    "transaction": {
+                        "signatures": ["1111111111111111111111111111111111111111111111111111111111111111"],
+                        "message": {
+                            "header": { "numRequiredSignatures": 1, "numReadonlySignedAccounts": 0, "numReadonlyUnsignedAccounts": 1 },
+                            "accountKeys": ["GdnSyH3YtwcxFvQrVVJMm1JhTS4QVX7MFsX56uJLUfiZ", "Ed25519SigVerify111111111111111111111111111"],
+                            "recentBlockhash": "5dLNuYn1R9uBQNBf8Tg7fh4n2s8Y1pHhHAPWUBv5FWSG",
+                            "instructions": [{ "programIdIndex": 1, "accounts": [], "data": "9q" }]
+                        }
+                    },
+                    "meta": { "err": null, "status": { "Ok": null }, "fee": 15000, "preBalances": [0, 0], "postBalances": [0, 0] }
+                }
+            ],
+            "rewards": [
+                { "pubkey": "GdnSyH3YtwcxFvQrVVJMm1JhTS4QVX7MFsX56uJLUfiZ", "lamports": 12000, "postBalance": 1000000000, "rewardType": "Fee", "commission": null }
+            ],
+            "blockTime": 1741234567,
+            "blockHeight": 300000000
+        }"#,
+    )
+    .unwrap();
+
+    // 1 + (1 + 2 precompile) signatures, half of the base fees burned (SIMD-0096)
+    assert_eq!(
+        block_fee_breakdown(&block),
+        Ok(FeeBreakdown {
+            num_transactions: 2,
+            base_fees: 20_000,
+            priority_fees: 2_000,
+            burned_fees: 10_000,
+        })
     );
 }